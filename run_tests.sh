@@ -4,5 +4,8 @@ set -e
 
 cargo test
 cargo test --features multithreaded
+cargo test --features tokio
 cargo test --no-default-features --features no_std
 cargo test --no-default-features --features no_std,multithreaded
+cargo clippy --all-targets --no-default-features --features no_std -- -D warnings
+cargo clippy --all-targets --no-default-features --features no_std,multithreaded -- -D warnings
@@ -1,6 +1,6 @@
 import_stdlib!();
 
-use crate::{error::CBORError, CBORCase, CBOR};
+use crate::{error::CBORError, CBORCase, CBORRef, CBOR};
 
 use anyhow::{bail, Error, Result};
 
@@ -36,6 +36,17 @@ where
     }
 }
 
+impl<'a, T> TryFrom<CBORRef<'a>> for Vec<T>
+where
+    T: TryFrom<CBORRef<'a>, Error = Error>,
+{
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        cbor.try_into_array()?.into_iter().map(T::try_from).collect()
+    }
+}
+
 impl<T, const N: usize> From<[T; N]> for CBOR where T: Into<CBOR> {
     fn from(array: [T; N]) -> Self {
         CBORCase::Array(array.into_iter().map(|x| x.into()).collect()).into()
@@ -68,6 +79,17 @@ where
     }
 }
 
+impl<'a, T> TryFrom<CBORRef<'a>> for VecDeque<T>
+where
+    T: TryFrom<CBORRef<'a>, Error = Error>,
+{
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        cbor.try_into_array()?.into_iter().map(T::try_from).collect()
+    }
+}
+
 impl<T> From<HashSet<T>> for CBOR where T: Into<CBOR> {
     fn from(set: HashSet<T>) -> Self {
         CBORCase::Array(set.into_iter().map(|x| x.into()).collect()).into()
@@ -93,3 +115,14 @@ where
         }
     }
 }
+
+impl<'a, T> TryFrom<CBORRef<'a>> for HashSet<T>
+where
+    T: TryFrom<CBORRef<'a>, Error = Error> + Eq + hash::Hash,
+{
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        cbor.try_into_array()?.into_iter().map(T::try_from).collect()
+    }
+}
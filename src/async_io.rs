@@ -0,0 +1,411 @@
+import_stdlib!();
+
+use std::io;
+
+use anyhow::{bail, Error, Result};
+use bytes::{Buf, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{cbor::NODE_SIZE, decode::{decode_cbor_internal, parse_header_lenient, DecodeLimits, SliceSource, Source}, varint::MajorType, CBORError, DecodeError, DecodeOptions, EncodingProfile, CBOR};
+
+/// The number of bytes requested from the underlying reader at a time.
+const READ_CHUNK_LEN: usize = 8 * 1024;
+
+/// A decoder that reads dCBOR items from an [`AsyncRead`] implementation.
+///
+/// This is the asynchronous counterpart of
+/// [`CBORReader`](crate::CBORReader). Each item is held to the same
+/// deterministic encoding rules as [`CBOR::try_from_data`], and the
+/// [`DecodeOptions`] limits apply to each item separately. The bytes of each
+/// item are buffered until it is complete, but the depth, string, container,
+/// node, and allocation limits are checked as they arrive, so an item that
+/// would exceed them is rejected before it is buffered in full. Set
+/// [`DecodeOptions::max_allocated_bytes`] to bound how much is buffered for
+/// each item. Errors report the same offsets and paths as `CBORReader` would.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let data: &[u8] = &hex_literal::hex!("01 820203");
+/// let mut reader = AsyncCBORReader::new(data);
+/// assert_eq!(reader.read_cbor().await.unwrap(), Some(CBOR::from(1)));
+/// assert_eq!(reader.read_cbor().await.unwrap(), Some(CBOR::from([2, 3])));
+/// assert_eq!(reader.read_cbor().await.unwrap(), None);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct AsyncCBORReader<R> {
+    reader: R,
+    options: DecodeOptions,
+    buf: Vec<u8>,
+    scanner: ItemScanner,
+    position: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncCBORReader<R> {
+    /// Creates a new reader that decodes items from `reader` without limits.
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, DecodeOptions::default())
+    }
+
+    /// Creates a new reader that decodes items from `reader`, enforcing the
+    /// given limits on each item.
+    pub fn with_options(reader: R, options: DecodeOptions) -> Self {
+        Self { reader, options, buf: Vec::new(), scanner: ItemScanner::new(), position: 0 }
+    }
+
+    /// Reads the next item.
+    ///
+    /// Returns `Ok(None)` if the underlying reader is at its end before the
+    /// first byte of an item. Returns [`CBORError::Underrun`] if it ends
+    /// partway through an item, and any other I/O error unchanged.
+    ///
+    /// After an error the position of the underlying reader within the
+    /// stream is unspecified.
+    pub async fn read_cbor(&mut self) -> Result<Option<CBOR>> {
+        loop {
+            match self.scanner.scan(&self.buf, &self.options) {
+                Scan::Complete(len) => {
+                    let result = decode_item(&self.buf[..len], self.position, &self.options);
+                    self.buf.drain(..len);
+                    self.position += len;
+                    self.scanner = ItemScanner::new();
+                    return result.map(Some);
+                },
+                Scan::Invalid => return Err(item_error(&self.buf, self.position, &self.options)),
+                Scan::Incomplete => {},
+            }
+            self.buf.reserve(READ_CHUNK_LEN);
+            if self.reader.read_buf(&mut self.buf).await? == 0 {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                return Err(item_error(&self.buf, self.position, &self.options));
+            }
+        }
+    }
+
+    /// Returns the number of bytes in the items read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// Any bytes read ahead of the items returned so far are lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// An encoder that writes dCBOR items to an [`AsyncWrite`] implementation.
+///
+/// Items are written back to back, forming a CBOR Sequence that
+/// [`AsyncCBORReader`] reads back. Wrap unbuffered writers in a
+/// [`BufWriter`](tokio::io::BufWriter), and flush when done.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let mut writer = AsyncCBORWriter::new(Vec::new());
+/// writer.write_cbor(&CBOR::from(1)).await.unwrap();
+/// writer.write_cbor(&CBOR::from([2, 3])).await.unwrap();
+/// assert_eq!(writer.into_inner(), hex_literal::hex!("01 820203"));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct AsyncCBORWriter<W> {
+    writer: W,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncCBORWriter<W> {
+    /// Creates a new writer that encodes items to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes the encoded form of `cbor`.
+    pub async fn write_cbor(&mut self, cbor: &CBOR) -> io::Result<()> {
+        self.writer.write_all(&cbor.to_cbor_data()).await
+    }
+
+    /// Flushes the underlying writer.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush().await
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// A [`Decoder`] and [`Encoder`] that frame dCBOR items on a byte stream,
+/// for use with [`Framed`](tokio_util::codec::Framed) and its relatives.
+///
+/// Items are framed by their own encoding, with nothing in between, so the
+/// stream is a CBOR Sequence. Encoded items longer than the codec's maximum
+/// are rejected in both directions with
+/// [`CBORError::ItemLenLimitExceeded`], and decoded items are also held to
+/// the codec's [`DecodeOptions`]. Decoding errors report offsets relative to
+/// the start of the item.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// use tokio_util::codec::{Decoder, Encoder};
+///
+/// let mut codec = CBORCodec::new(1024);
+/// let mut buf = bytes::BytesMut::new();
+/// codec.encode(CBOR::from([1, 2]), &mut buf).unwrap();
+/// codec.encode(CBOR::from("a"), &mut buf).unwrap();
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some(CBOR::from([1, 2])));
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some(CBOR::from("a")));
+/// assert_eq!(codec.decode(&mut buf).unwrap(), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CBORCodec {
+    max_item_len: usize,
+    options: DecodeOptions,
+    scanner: ItemScanner,
+}
+
+impl CBORCodec {
+    /// Creates a new codec for items of up to `max_item_len` encoded bytes.
+    pub fn new(max_item_len: usize) -> Self {
+        Self::with_options(max_item_len, DecodeOptions::default())
+    }
+
+    /// Creates a new codec for items of up to `max_item_len` encoded bytes,
+    /// enforcing the given limits on each decoded item.
+    pub fn with_options(max_item_len: usize, options: DecodeOptions) -> Self {
+        Self { max_item_len, options, scanner: ItemScanner::new() }
+    }
+
+    /// Returns the maximum length of an encoded item.
+    pub fn max_item_len(&self) -> usize {
+        self.max_item_len
+    }
+
+    /// Returns the limits enforced on each decoded item.
+    pub fn options(&self) -> &DecodeOptions {
+        &self.options
+    }
+}
+
+impl Decoder for CBORCodec {
+    type Item = CBOR;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<CBOR>> {
+        match self.scanner.scan(src, &self.options) {
+            Scan::Complete(len) => {
+                if len > self.max_item_len {
+                    bail!(CBORError::ItemLenLimitExceeded(self.max_item_len));
+                }
+                self.scanner = ItemScanner::new();
+                let result = decode_item(&src[..len], 0, &self.options);
+                src.advance(len);
+                result.map(Some)
+            },
+            Scan::Incomplete => {
+                if src.len() > self.max_item_len {
+                    bail!(CBORError::ItemLenLimitExceeded(self.max_item_len));
+                }
+                Ok(None)
+            },
+            Scan::Invalid => Err(item_error(src, 0, &self.options)),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<CBOR>> {
+        match self.decode(src)? {
+            None if !src.is_empty() => Err(item_error(src, 0, &self.options)),
+            item => Ok(item),
+        }
+    }
+}
+
+impl Encoder<&CBOR> for CBORCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: &CBOR, dst: &mut BytesMut) -> Result<()> {
+        let data = item.to_cbor_data();
+        if data.len() > self.max_item_len {
+            bail!(CBORError::ItemLenLimitExceeded(self.max_item_len));
+        }
+        dst.extend_from_slice(&data);
+        Ok(())
+    }
+}
+
+impl Encoder<CBOR> for CBORCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: CBOR, dst: &mut BytesMut) -> Result<()> {
+        self.encode(&item, dst)
+    }
+}
+
+/// Decodes the item whose encoding is exactly `data`, which starts `base`
+/// bytes into the stream.
+fn decode_item(data: &[u8], base: usize, options: &DecodeOptions) -> Result<CBOR> {
+    let mut limits = DecodeLimits::new(options);
+    let mut source = PartialSource(SliceSource { data, pos: 0 });
+    decode_cbor_internal(&mut source, &mut limits).map_err(|mut error| {
+        if let Some(decode_error) = error.downcast_mut::<DecodeError>() {
+            decode_error.rebase(base);
+        }
+        error
+    })
+}
+
+/// Returns the error that the decoder reports for `data`, which the scanner
+/// found to be invalid or which ends partway through an item.
+///
+/// The scanner rejects only what the decoder rejects at the same point, so
+/// decoding what has been buffered so far reports the error with its path.
+fn item_error(data: &[u8], base: usize, options: &DecodeOptions) -> Error {
+    match decode_item(data, base, options) {
+        Err(error) => error,
+        Ok(_) => CBORError::Underrun.into(),
+    }
+}
+
+/// The part of a stream that has been buffered so far.
+///
+/// Unlike a plain [`SliceSource`], this does not report how many bytes
+/// remain, since more may follow, so the decoder fails at the same point as
+/// it would reading the stream directly.
+struct PartialSource<'a>(SliceSource<'a>);
+
+impl Source for PartialSource<'_> {
+    fn position(&self) -> usize {
+        self.0.position()
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.0.read_exact(buf)
+    }
+
+    fn read_content(&mut self, len: usize) -> Result<Vec<u8>> {
+        self.0.read_content(len)
+    }
+
+    fn key_data(&self, start: usize, key: &CBOR, profile: EncodingProfile) -> Vec<u8> {
+        self.0.key_data(start, key, profile)
+    }
+}
+
+/// The result of scanning the data buffered so far.
+enum Scan {
+    /// The first item ends after this many bytes.
+    Complete(usize),
+    /// More data is needed to find the end of the first item.
+    Incomplete,
+    /// The first item is malformed or exceeds the limits.
+    Invalid,
+}
+
+/// Finds the end of an item whose encoding arrives in pieces.
+///
+/// Only the structure of the item is examined, along with those limits that
+/// bound how much of it must be buffered: depth, string and container
+/// lengths, nodes, and allocated bytes, which are charged as the decoder
+/// charges them. Scanning resumes where it left off each time more data
+/// arrives, so each byte is examined only once however the item is split.
+#[derive(Debug, Clone)]
+struct ItemScanner {
+    pos: usize,
+    /// The number of items still to come in each open container, outermost
+    /// first, below a pseudo-container holding the item itself.
+    open: Vec<u64>,
+    nodes: usize,
+    allocated: usize,
+}
+
+impl ItemScanner {
+    fn new() -> Self {
+        Self { pos: 0, open: vec![1], nodes: 0, allocated: 0 }
+    }
+
+    fn scan(&mut self, data: &[u8], options: &DecodeOptions) -> Scan {
+        while let Some(&pending) = self.open.last() {
+            if pending == 0 {
+                self.open.pop();
+                continue;
+            }
+            let depth = self.open.len() - 1;
+            if depth > options.max_depth {
+                return Scan::Invalid;
+            }
+            let (major_type, value, header_len) = match parse_header_lenient(&data[self.pos..]) {
+                Ok(header) => header,
+                Err(CBORError::Underrun) => return Scan::Incomplete,
+                Err(_) => return Scan::Invalid,
+            };
+            if self.nodes >= options.max_nodes {
+                return Scan::Invalid;
+            }
+            let mut len = header_len;
+            let mut content_len = 0;
+            let mut items = 0;
+            match major_type {
+                MajorType::ByteString | MajorType::Text => {
+                    if value > options.max_string_len as u64 {
+                        return Scan::Invalid;
+                    }
+                    content_len = value as usize;
+                },
+                MajorType::Array | MajorType::Map => {
+                    if value > options.max_container_len as u64 {
+                        return Scan::Invalid;
+                    }
+                    items = if matches!(major_type, MajorType::Map) { value.saturating_mul(2) } else { value };
+                },
+                MajorType::Tagged => items = 1,
+                MajorType::Unsigned | MajorType::Negative | MajorType::Simple => {},
+            }
+            let allocated = self.allocated.saturating_add(NODE_SIZE.saturating_add(content_len));
+            if allocated > options.max_allocated_bytes {
+                return Scan::Invalid;
+            }
+            if content_len > data.len() - self.pos - header_len {
+                return Scan::Incomplete;
+            }
+            len += content_len;
+            self.open[depth] = pending - 1;
+            self.nodes += 1;
+            self.allocated = allocated;
+            self.pos += len;
+            if items > 0 {
+                self.open.push(items);
+            }
+        }
+        Scan::Complete(self.pos)
+    }
+}
@@ -0,0 +1,278 @@
+import_stdlib!();
+
+use anyhow::{bail, Error, Result};
+
+use crate::{CBORCase, CBORError, CBORRef, CBORRefCase, TagValue, CBOR, TAG_NEGATIVE_BIGNUM, TAG_POSITIVE_BIGNUM};
+
+/// Returns `true` if `tag` is one of the bignum tags.
+pub(crate) fn is_bignum_tag(tag: TagValue) -> bool {
+    tag == TAG_POSITIVE_BIGNUM || tag == TAG_NEGATIVE_BIGNUM
+}
+
+/// Checks that `magnitude`, the content of a bignum, has no leading zero
+/// bytes and is too large to have been encoded as a plain integer.
+pub(crate) fn validate_bignum(magnitude: &[u8]) -> Result<(), CBORError> {
+    if magnitude.len() <= 8 || magnitude[0] == 0 {
+        return Err(CBORError::NonCanonicalNumeric);
+    }
+    Ok(())
+}
+
+/// Returns `magnitude` without its leading zero bytes.
+pub(crate) fn trim_magnitude(magnitude: &[u8]) -> &[u8] {
+    let start = magnitude.iter().position(|&byte| byte != 0).unwrap_or(magnitude.len());
+    &magnitude[start..]
+}
+
+/// Returns the integer with the given sign and big-endian magnitude, where
+/// the magnitude of a negative integer `n` is `-1 - n`, as it is encoded.
+///
+/// Integers that fit in 64 bits are plain integers, and others are bignums
+/// without leading zero bytes.
+pub(crate) fn integer_from_magnitude(negative: bool, magnitude: &[u8]) -> CBOR {
+    let magnitude = trim_magnitude(magnitude);
+    if magnitude.len() <= 8 {
+        let n = magnitude.iter().fold(0u64, |n, &byte| n << 8 | byte as u64);
+        return if negative { CBORCase::Negative(n) } else { CBORCase::Unsigned(n) }.into();
+    }
+    let tag = if negative { TAG_NEGATIVE_BIGNUM } else { TAG_POSITIVE_BIGNUM };
+    CBOR::to_tagged_value(tag, CBOR::to_byte_string(magnitude))
+}
+
+/// Returns the decimal representation of the integer with the given sign and
+/// big-endian magnitude, where the magnitude of a negative integer `n` is
+/// `-1 - n`.
+pub(crate) fn integer_to_decimal(negative: bool, magnitude: &[u8]) -> String {
+    const BASE: u64 = 1_000_000_000;
+    let magnitude = trim_magnitude(magnitude);
+    // The magnitude in 32-bit words, most significant first.
+    let mut bytes = vec![0; (4 - magnitude.len() % 4) % 4];
+    bytes.extend_from_slice(magnitude);
+    let mut words: Vec<u32> = bytes.chunks(4)
+        .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+    // The magnitude in base 10^9 digits, least significant first.
+    let mut digits = Vec::new();
+    while !words.is_empty() {
+        let mut remainder = 0;
+        for word in words.iter_mut() {
+            let n = remainder << 32 | *word as u64;
+            *word = (n / BASE) as u32;
+            remainder = n % BASE;
+        }
+        digits.push(remainder as u32);
+        let zeros = words.iter().take_while(|&&word| word == 0).count();
+        words.drain(..zeros);
+    }
+    if negative {
+        // The value is `-(magnitude + 1)`.
+        let mut carry = true;
+        for digit in digits.iter_mut() {
+            *digit += 1;
+            carry = *digit as u64 == BASE;
+            if !carry {
+                break;
+            }
+            *digit = 0;
+        }
+        if carry || digits.is_empty() {
+            digits.push(1);
+        }
+    }
+    let mut result = String::from(if negative { "-" } else { "" });
+    let mut digits = digits.iter().rev();
+    result += &digits.next().map_or(String::from("0"), ToString::to_string);
+    for digit in digits {
+        result += &format!("{:09}", digit);
+    }
+    result
+}
+
+/// The magnitude of an integer, where the magnitude of a negative integer
+/// `n` is `-1 - n`.
+enum Magnitude<'a> {
+    Small(u64),
+    Big(&'a [u8]),
+}
+
+impl Magnitude<'_> {
+    fn to_u128(&self) -> Result<u128> {
+        match self {
+            Magnitude::Small(n) => Ok(*n as u128),
+            Magnitude::Big(bytes) => {
+                let bytes = trim_magnitude(bytes);
+                if bytes.len() > 16 {
+                    bail!(CBORError::OutOfRange);
+                }
+                Ok(bytes.iter().fold(0u128, |n, &byte| n << 8 | byte as u128))
+            },
+        }
+    }
+
+    #[cfg(feature = "num-bigint")]
+    fn to_biguint(&self) -> num_bigint::BigUint {
+        match self {
+            Magnitude::Small(n) => (*n).into(),
+            Magnitude::Big(bytes) => num_bigint::BigUint::from_bytes_be(bytes),
+        }
+    }
+}
+
+/// Returns whether `cbor`, which must be an integer or a bignum, is negative,
+/// along with its magnitude.
+fn integer_parts(cbor: &CBOR) -> Result<(bool, Magnitude<'_>)> {
+    match cbor.as_case() {
+        CBORCase::Unsigned(n) => Ok((false, Magnitude::Small(*n))),
+        CBORCase::Negative(n) => Ok((true, Magnitude::Small(*n))),
+        CBORCase::Tagged(tag, item) if is_bignum_tag(tag.value()) => match item.as_case() {
+            CBORCase::ByteString(bytes) => Ok((tag.value() == TAG_NEGATIVE_BIGNUM, Magnitude::Big(bytes.data()))),
+            _ => bail!(CBORError::WrongType),
+        },
+        _ => bail!(CBORError::WrongType),
+    }
+}
+
+/// Returns whether `cbor`, which must be an integer or a bignum, is negative,
+/// along with its magnitude.
+fn integer_ref_parts<'a>(cbor: &CBORRef<'a>) -> Result<(bool, Magnitude<'a>)> {
+    match cbor.as_case() {
+        CBORRefCase::Unsigned(n) => Ok((false, Magnitude::Small(*n))),
+        CBORRefCase::Negative(n) => Ok((true, Magnitude::Small(*n))),
+        CBORRefCase::Tagged(tag, item) if is_bignum_tag(tag.value()) => match item.as_case() {
+            CBORRefCase::ByteString(bytes) => Ok((tag.value() == TAG_NEGATIVE_BIGNUM, Magnitude::Big(bytes))),
+            _ => bail!(CBORError::WrongType),
+        },
+        _ => bail!(CBORError::WrongType),
+    }
+}
+
+fn u128_from_parts((negative, magnitude): (bool, Magnitude<'_>)) -> Result<u128> {
+    if negative {
+        bail!(CBORError::OutOfRange);
+    }
+    magnitude.to_u128()
+}
+
+fn i128_from_parts((negative, magnitude): (bool, Magnitude<'_>)) -> Result<i128> {
+    let magnitude = magnitude.to_u128()?;
+    if magnitude > i128::MAX as u128 {
+        bail!(CBORError::OutOfRange);
+    }
+    Ok(if negative { -1 - magnitude as i128 } else { magnitude as i128 })
+}
+
+impl From<u128> for CBOR {
+    fn from(value: u128) -> Self {
+        integer_from_magnitude(false, &value.to_be_bytes())
+    }
+}
+
+impl From<i128> for CBOR {
+    fn from(value: i128) -> Self {
+        if value < 0 {
+            integer_from_magnitude(true, &((-1 - value) as u128).to_be_bytes())
+        } else {
+            integer_from_magnitude(false, &(value as u128).to_be_bytes())
+        }
+    }
+}
+
+impl TryFrom<CBOR> for u128 {
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        u128_from_parts(integer_parts(&cbor)?)
+    }
+}
+
+impl TryFrom<CBOR> for i128 {
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        i128_from_parts(integer_parts(&cbor)?)
+    }
+}
+
+impl<'a> TryFrom<CBORRef<'a>> for u128 {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        u128_from_parts(integer_ref_parts(&cbor)?)
+    }
+}
+
+impl<'a> TryFrom<CBORRef<'a>> for i128 {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        i128_from_parts(integer_ref_parts(&cbor)?)
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+mod num_bigint_impls {
+    use anyhow::{bail, Error, Result};
+    use num_bigint::{BigInt, BigUint, Sign};
+
+    use super::{integer_from_magnitude, integer_parts, integer_ref_parts, Magnitude};
+    use crate::{CBORError, CBORRef, CBOR};
+
+    fn biguint_from_parts((negative, magnitude): (bool, Magnitude<'_>)) -> Result<BigUint> {
+        if negative {
+            bail!(CBORError::OutOfRange);
+        }
+        Ok(magnitude.to_biguint())
+    }
+
+    fn bigint_from_parts((negative, magnitude): (bool, Magnitude<'_>)) -> BigInt {
+        let magnitude = BigInt::from(magnitude.to_biguint());
+        if negative { -1 - magnitude } else { magnitude }
+    }
+
+    impl From<BigUint> for CBOR {
+        fn from(value: BigUint) -> Self {
+            integer_from_magnitude(false, &value.to_bytes_be())
+        }
+    }
+
+    impl From<BigInt> for CBOR {
+        fn from(value: BigInt) -> Self {
+            match value.sign() {
+                Sign::Minus => integer_from_magnitude(true, &(value.magnitude() - 1u32).to_bytes_be()),
+                _ => integer_from_magnitude(false, &value.magnitude().to_bytes_be()),
+            }
+        }
+    }
+
+    impl TryFrom<CBOR> for BigUint {
+        type Error = Error;
+
+        fn try_from(cbor: CBOR) -> Result<Self> {
+            biguint_from_parts(integer_parts(&cbor)?)
+        }
+    }
+
+    impl TryFrom<CBOR> for BigInt {
+        type Error = Error;
+
+        fn try_from(cbor: CBOR) -> Result<Self> {
+            Ok(bigint_from_parts(integer_parts(&cbor)?))
+        }
+    }
+
+    impl<'a> TryFrom<CBORRef<'a>> for BigUint {
+        type Error = Error;
+
+        fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+            biguint_from_parts(integer_ref_parts(&cbor)?)
+        }
+    }
+
+    impl<'a> TryFrom<CBORRef<'a>> for BigInt {
+        type Error = Error;
+
+        fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+            Ok(bigint_from_parts(integer_ref_parts(&cbor)?))
+        }
+    }
+}
@@ -1,6 +1,6 @@
 import_stdlib!();
 
-use crate::{CBOR, Simple, CBORError, CBORCase};
+use crate::{CBOR, Simple, CBORError, CBORCase, CBORRef, CBORRefCase};
 
 use anyhow::{bail, Error, Result};
 
@@ -24,3 +24,15 @@ impl TryFrom<CBOR> for bool {
         }
     }
 }
+
+impl<'a> TryFrom<CBORRef<'a>> for bool {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        match cbor.as_case() {
+            CBORRefCase::Simple(Simple::False) => Ok(false),
+            CBORRefCase::Simple(Simple::True) => Ok(true),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
@@ -2,11 +2,25 @@ import_stdlib!();
 
 use anyhow::Error;
 
-use crate::CBOR;
+use crate::{varint::{Header, MajorType}, CBORRef, CBOR};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct ByteString(Vec<u8>);
 
+impl PartialOrd for ByteString {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Byte strings are ordered lexicographically by their dCBOR encodings, so
+/// shorter byte strings come before longer ones.
+impl Ord for ByteString {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (self.0.len(), &self.0).cmp(&(other.0.len(), &other.0))
+    }
+}
+
 impl ByteString {
     pub fn new(data: impl Into<Vec<u8>>) -> Self {
         Self(data.into())
@@ -24,6 +38,11 @@ impl ByteString {
         self.0.is_empty()
     }
 
+    /// Returns the length of the encoding of this byte string as CBOR.
+    pub fn encoded_len(&self) -> usize {
+        Header::new(MajorType::ByteString, self.0.len() as u64).as_slice().len() + self.0.len()
+    }
+
     pub fn extend(&mut self, other: impl Into<Vec<u8>>) {
         self.0.extend(other.into())
     }
@@ -84,6 +103,22 @@ impl TryFrom<CBOR> for ByteString {
     }
 }
 
+impl<'a> TryFrom<CBORRef<'a>> for ByteString {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self, Self::Error> {
+        Ok(cbor.try_into_byte_string()?.into())
+    }
+}
+
+impl<'a> TryFrom<CBORRef<'a>> for &'a [u8] {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self, Self::Error> {
+        cbor.try_into_byte_string()
+    }
+}
+
 impl<const N: usize> From<[u8; N]> for ByteString {
     fn from(value: [u8; N]) -> Self {
         Self(value.to_vec())
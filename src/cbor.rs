@@ -1,38 +1,239 @@
 import_stdlib!();
 
 use anyhow::{bail, Result};
-use unicode_normalization::UnicodeNormalization;
 
-use crate::{decode::decode_cbor, error::CBORError, tag::Tag, varint::{EncodeVarInt, MajorType}, Map, Simple, ByteString};
+use crate::{decode::{decode_cbor, decode_cbor_with_options, decode_cbor_with_validator}, error::CBORError, lenient::{decode_cbor_lenient, decode_cbor_lenient_with_options}, parse_hex, tag::Tag, validate, encode::{encode, encoded_len, hash_encoding, EncodeTask}, order::{compare, Part}, Map, Simple, ByteString, DecodeOptions, DecodeValidator, DigestAlgorithm, EncodingProfile, Transformation};
 
 use super::string_util::flanked;
 
 #[cfg(feature = "multithreaded")]
-use sync::Arc as RefCounted;
+pub(crate) use sync::Arc as RefCounted;
 
 #[cfg(not(feature = "multithreaded"))]
-use rc::Rc as RefCounted;
+pub(crate) use rc::Rc as RefCounted;
+
+#[cfg(all(feature = "multithreaded", feature = "std"))]
+use sync::OnceLock as OnceCell;
+
+#[cfg(all(feature = "multithreaded", not(feature = "std")))]
+use crate::once_lock::OnceLock as OnceCell;
+
+#[cfg(not(feature = "multithreaded"))]
+use core::cell::OnceCell;
 
 /// A symbolic representation of CBOR data.
+///
+/// Values are immutable and cheap to clone, as clones share the same
+/// underlying node. A value that is encoded many times can be
+/// [`memoized`](Self::memoized), after which its encoding is copied rather
+/// than produced afresh.
+///
+/// Values, like [`Map`]s and [`Simple`] values, are compared, ordered, and
+/// hashed by their dCBOR encodings, though without being encoded. So text is
+/// equal to its normalized form, NaNs are equal to each other, and values are
+/// sorted as the keys of a map are.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let mut values = vec![CBOR::from("a"), CBOR::from(-1), CBOR::from([1]), CBOR::from(10), CBOR::from(1.5)];
+/// values.sort();
+/// assert_eq!(CBOR::from(values).diagnostic_flat(), r#"[10, -1, "a", [1], 1.5]"#);
+/// ```
 #[derive(Clone)]
-pub struct CBOR(RefCounted<CBORCase>);
+pub struct CBOR(RefCounted<Node>);
+
+/// The node shared by clones of a [`CBOR`].
+struct Node {
+    /// The value, which is decoded from the encoding when first needed if the
+    /// node was created from encoded data.
+    case: OnceCell<CBORCase>,
+    /// The memoized encoding and digest, which are kept out of line as most
+    /// values have neither.
+    memo: OnceCell<Box<Memo>>,
+}
+
+/// The size of the allocation holding a node, including its reference
+/// counts.
+pub(crate) const NODE_SIZE: usize = mem::size_of::<Node>() + 2 * mem::size_of::<usize>();
+
+/// What is memoized for a value.
+struct Memo {
+    /// The dCBOR encoding.
+    encoded: Box<[u8]>,
+    /// The digest of the encoding under one algorithm, once memoized.
+    digest: OnceCell<(TypeId, Box<dyn Any + Send + Sync>)>,
+}
+
+impl Memo {
+    fn new(encoded: Box<[u8]>) -> Box<Self> {
+        Box::new(Self { encoded, digest: OnceCell::new() })
+    }
+}
 
 impl CBOR {
     pub fn as_case(&self) -> &CBORCase {
-        &self.0
+        self.0.case.get_or_init(|| {
+            // Only nodes created from validated encoded data lack a case.
+            let data = self.memoized_data().unwrap();
+            decode_cbor(data).expect("validated dCBOR").into_case()
+        })
+    }
+
+    pub fn into_case(mut self) -> CBORCase {
+        match RefCounted::get_mut(&mut self.0).and_then(|node| node.case.take()) {
+            Some(case) => case,
+            None => self.as_case().clone(),
+        }
+    }
+
+    /// Creates a value from the encoded dCBOR item in `data`, which is
+    /// checked with [`validate`](crate::validate) but not decoded.
+    ///
+    /// The value is encoded as exactly `data`, including where it is part of
+    /// another value, so encoded items such as signed payloads can be
+    /// embedded in new documents without being decoded and encoded again. It
+    /// is decoded the first time its contents are needed, for example by
+    /// [`as_case`](Self::as_case), comparison, or formatting.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let payload = CBOR::from_validated_data(hex_literal::hex!("a2 0100 0200")).unwrap();
+    /// let document = CBOR::from(vec![CBOR::from("signed"), payload.clone()]);
+    /// assert_eq!(document.hex(), "82667369676e6564a201000200");
+    /// assert_eq!(payload.diagnostic_flat(), "{1: 0, 2: 0}");
+    ///
+    /// // {2: 0, 1: 0}
+    /// let error = CBOR::from_validated_data(hex_literal::hex!("a2 0200 0100")).unwrap_err();
+    /// assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+    /// ```
+    pub fn from_validated_data(data: impl Into<Vec<u8>>) -> Result<CBOR> {
+        let data = data.into();
+        validate(&data)?;
+        Ok(Self(RefCounted::new(Node { case: OnceCell::new(), memo: OnceCell::from(Memo::new(data.into_boxed_slice())) })))
+    }
+
+    /// Returns this value with its dCBOR encoding memoized, so that whenever
+    /// it is encoded again, whether directly or as part of another value, the
+    /// encoding is copied rather than produced afresh.
+    ///
+    /// The encoding is kept for as long as any clone of the value is, so this
+    /// is worthwhile only for values that are encoded many times, such as
+    /// subtrees shared by many documents.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let shared = CBOR::from([1, 2, 3]).memoized();
+    /// let documents: Vec<CBOR> = (0..3)
+    ///     .map(|i| CBOR::from(vec![CBOR::from(i), shared.clone()]))
+    ///     .collect();
+    /// assert_eq!(documents[2].to_cbor_data(), hex_literal::hex!("82 02 83010203"));
+    /// ```
+    pub fn memoized(self) -> Self {
+        self.memo();
+        self
+    }
+
+    /// Returns the digest of the dCBOR encoding of this value under the
+    /// algorithm `A`, memoizing it along with the encoding.
+    ///
+    /// Only one digest is memoized for each value, so if the digest under
+    /// another algorithm has already been memoized, the digest is computed
+    /// afresh each time.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// struct Length;
+    ///
+    /// impl DigestAlgorithm for Length {
+    ///     type Output = usize;
+    ///
+    ///     fn digest(data: &[u8]) -> usize {
+    ///         data.len()
+    ///     }
+    /// }
+    ///
+    /// struct FirstByte;
+    ///
+    /// impl DigestAlgorithm for FirstByte {
+    ///     type Output = usize;
+    ///
+    ///     fn digest(data: &[u8]) -> usize {
+    ///         data[0] as usize
+    ///     }
+    /// }
+    ///
+    /// let cbor = CBOR::from([1, 2, 3]);
+    /// assert_eq!(cbor.memoized_digest::<Length>(), 4);
+    /// // Digests under other algorithms are not confused with the memoized
+    /// // one, even though they have the same type.
+    /// assert_eq!(cbor.memoized_digest::<FirstByte>(), 0x83);
+    /// ```
+    pub fn memoized_digest<A: DigestAlgorithm>(&self) -> A::Output {
+        let memo = self.memo();
+        let (algorithm, digest) = memo.digest.get_or_init(|| (TypeId::of::<A>(), Box::new(A::digest(&memo.encoded))));
+        match digest.downcast_ref::<A::Output>() {
+            Some(digest) if *algorithm == TypeId::of::<A>() => digest.clone(),
+            _ => A::digest(&memo.encoded),
+        }
+    }
+
+    /// Returns the memoized dCBOR encoding of this value, if any.
+    pub(crate) fn memoized_data(&self) -> Option<&[u8]> {
+        self.0.memo.get().map(|memo| &*memo.encoded)
+    }
+
+    /// Memoizes the dCBOR encoding of this value, unless it has already been
+    /// memoized, and returns what is memoized.
+    fn memo(&self) -> &Memo {
+        self.0.memo.get_or_init(|| {
+            let mut buf = Vec::new();
+            encode(EncodeTask::Item(self), EncodingProfile::Dcbor, &mut buf);
+            Memo::new(buf.into())
+        })
     }
 
-    pub fn into_case(self) -> CBORCase {
-        match RefCounted::try_unwrap(self.0) {
-            Ok(b) => b,
-            Err(ref_counted) => (*ref_counted).clone(),
+    /// Returns `true` if this value and `other` are clones of the same node.
+    pub(crate) fn is_same_node(&self, other: &CBOR) -> bool {
+        RefCounted::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Drops nested values with an explicit work stack, so that arbitrarily deep
+/// structures cannot overflow the call stack.
+impl Drop for CBOR {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        take_children(&mut self.0, &mut stack);
+        while let Some(mut cbor) = stack.pop() {
+            take_children(&mut cbor.0, &mut stack);
         }
     }
 }
 
+/// Moves the children of a uniquely-owned node onto `stack`, leaving the node
+/// without children.
+fn take_children(node: &mut RefCounted<Node>, stack: &mut Vec<CBOR>) {
+    let Some(node) = RefCounted::get_mut(node) else {
+        return;
+    };
+    let Some(case) = node.case.get_mut() else {
+        return;
+    };
+    match case {
+        CBORCase::Array(items) => stack.append(items),
+        CBORCase::Map(map) => map.drain_into(stack),
+        CBORCase::Tagged(_, _) => {
+            if let CBORCase::Tagged(_, item) = mem::replace(case, CBORCase::Unsigned(0)) {
+                stack.push(item);
+            }
+        },
+        _ => {},
+    }
+}
+
 impl From<CBORCase> for CBOR {
     fn from(case: CBORCase) -> Self {
-        Self(RefCounted::new(case))
+        Self(RefCounted::new(Node { case: OnceCell::from(case), memo: OnceCell::new() }))
     }
 }
 
@@ -65,45 +266,119 @@ impl CBOR {
         decode_cbor(data)
     }
 
+    /// Decodes the given data into CBOR symbolic representation, enforcing the
+    /// limits in `options`.
+    ///
+    /// Use this when decoding data from untrusted sources.
+    pub fn try_from_data_with_options(data: impl AsRef<[u8]>, options: &DecodeOptions) -> Result<CBOR> {
+        decode_cbor_with_options(data, options)
+    }
+
+    /// Decodes the given data into CBOR symbolic representation, enforcing the
+    /// limits in `options` and passing each item to `validator` before it is
+    /// built.
+    ///
+    /// See [`DecodeValidator`] for an example.
+    pub fn try_from_data_with_validator(data: impl AsRef<[u8]>, options: &DecodeOptions, validator: &mut impl DecodeValidator) -> Result<CBOR> {
+        decode_cbor_with_validator(data, options, validator)
+    }
+
+    /// Decodes any well-formed CBOR, converting it into deterministic CBOR.
+    ///
+    /// Indefinite lengths, non-minimal headers, unsorted or repeated map
+    /// entries, non-NFC text, wider-than-needed floats, and `undefined` are
+    /// all accepted and canonicalized, and each change made is returned in
+    /// the report. Decoding fails only if the data is not well-formed or
+    /// cannot be represented in dCBOR, for example because two map keys are
+    /// the same once canonicalized but their values differ.
+    ///
+    /// Each transformation records the path to the item it changed, so the
+    /// report for deeply nested data can be much larger than the data itself.
+    /// Use [`try_from_data_lenient_with_options`](Self::try_from_data_lenient_with_options)
+    /// to limit the depth of untrusted input.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// // {_ 2: 1.0, 1: undefined}
+    /// let data = hex_literal::hex!("bf 02 f93c00 01 f7 ff");
+    /// let (cbor, report) = CBOR::try_from_data_lenient(data).unwrap();
+    /// assert_eq!(cbor.diagnostic(), "{1: null, 2: 1}");
+    /// assert_eq!(report.len(), 4);
+    /// ```
+    pub fn try_from_data_lenient(data: impl AsRef<[u8]>) -> Result<(CBOR, Vec<Transformation>)> {
+        decode_cbor_lenient(data)
+    }
+
+    /// Decodes any well-formed CBOR, converting it into deterministic CBOR
+    /// and enforcing the limits in `options`.
+    ///
+    /// The limits apply to the converted value, so for example the string
+    /// length limit applies to the total length of an indefinite-length
+    /// string.
+    pub fn try_from_data_lenient_with_options(data: impl AsRef<[u8]>, options: &DecodeOptions) -> Result<(CBOR, Vec<Transformation>)> {
+        decode_cbor_lenient_with_options(data, options)
+    }
+
     /// Decodes the given data into CBOR symbolic representation given as a hexadecimal string.
     ///
-    /// Panics if the string is not well-formed hexadecimal with no spaces or
-    /// other characters.
+    /// The string may be plain hexadecimal or an annotated dump such as that
+    /// produced by [`hex_annotated`](Self::hex_annotated), as described for
+    /// [`parse_hex`](crate::parse_hex).
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let cbor = CBOR::try_from_hex("
+    ///     82          # array(2)
+    ///        01       # unsigned(1)
+    ///        63       # text(3)
+    ///           616263    # \"abc\"
+    /// ").unwrap();
+    /// assert_eq!(cbor.diagnostic(), r#"[1, "abc"]"#);
+    /// assert!(CBOR::try_from_hex("8").is_err());
+    /// ```
     pub fn try_from_hex(hex: &str) -> Result<CBOR> {
-        let data = hex::decode(hex).unwrap();
+        let data = parse_hex(hex)?;
         Self::try_from_data(data)
     }
 
+    /// Encodes this value as dCBOR.
+    ///
+    /// A [`Simple::Value`], which dCBOR does not allow, is encoded as it is
+    /// under the other profiles, so the result is not valid dCBOR and
+    /// [`try_from_data`](Self::try_from_data) rejects it. Such values can
+    /// only be round-tripped with
+    /// [`to_cbor_data_with_profile`](Self::to_cbor_data_with_profile) under
+    /// another profile.
     pub fn to_cbor_data(&self) -> Vec<u8> {
-        match self.as_case() {
-            CBORCase::Unsigned(x) => x.encode_varint(MajorType::Unsigned),
-            CBORCase::Negative(x) => x.encode_varint(MajorType::Negative),
-            CBORCase::ByteString(x) => {
-                let mut buf = x.len().encode_varint(MajorType::ByteString);
-                buf.extend(x);
-                buf
-            },
-            CBORCase::Text(x) => {
-                let nfc = x.nfc().collect::<String>();
-                let mut buf = nfc.len().encode_varint(MajorType::Text);
-                buf.extend(nfc.as_bytes());
-                buf
-            },
-            CBORCase::Array(x) => {
-                let mut buf = x.len().encode_varint(MajorType::Array);
-                for item in x {
-                    buf.extend(item.to_cbor_data());
-                }
-                buf
-            },
-            CBORCase::Map(x) => x.cbor_data(),
-            CBORCase::Tagged(tag, item) => {
-                let mut buf = tag.value().encode_varint(MajorType::Tagged);
-                buf.extend(item.to_cbor_data());
-                buf
-            },
-            CBORCase::Simple(x) => x.cbor_data(),
-        }
+        self.to_cbor_data_with_profile(EncodingProfile::Dcbor)
+    }
+
+    /// Encodes this value following the rules of `profile`.
+    pub fn to_cbor_data_with_profile(&self, profile: EncodingProfile) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode(EncodeTask::Item(self), profile, &mut buf);
+        buf
+    }
+
+    /// Returns the length of the encoding returned by
+    /// [`to_cbor_data`](Self::to_cbor_data), without producing it.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// // The text is normalized, and the float is encoded in two bytes.
+    /// let cbor = CBOR::from(vec![CBOR::from("e\u{301}"), CBOR::from(1.5)]);
+    /// assert_eq!(cbor.encoded_len(), 7);
+    /// assert_eq!(cbor.encoded_len(), cbor.to_cbor_data().len());
+    /// ```
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_profile(EncodingProfile::Dcbor)
+    }
+
+    /// Returns the length of the encoding returned by
+    /// [`to_cbor_data_with_profile`](Self::to_cbor_data_with_profile), without
+    /// producing it.
+    pub fn encoded_len_with_profile(&self, profile: EncodingProfile) -> usize {
+        encoded_len(EncodeTask::Item(self), profile)
     }
 }
 
@@ -120,7 +395,43 @@ impl CBOR {
         Self::to_byte_string(hex::decode(hex.as_ref()).unwrap())
     }
 
+    /// Create a new CBOR value representing a text string, stored exactly as
+    /// given.
+    ///
+    /// Unlike [`CBOR::from`], this does not normalize the text to Unicode
+    /// Normalization Form C, so it is for callers that have already checked
+    /// that the text is normalized, or that need the text exactly as given,
+    /// for example to encode it under
+    /// [`EncodingProfile::CoreDeterministic`]. Text that is not normalized
+    /// is still normalized when encoded as dCBOR, so compares equal to its
+    /// normalized form, but is otherwise kept as given.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let raw = CBOR::to_raw_text("e\u{301}");
+    /// assert!(matches!(raw.as_case(), CBORCase::Text(text) if text == "e\u{301}"));
+    /// assert_eq!(raw.to_cbor_data(), CBOR::from("e\u{301}").to_cbor_data());
+    /// assert_eq!(raw.to_cbor_data_with_profile(EncodingProfile::CoreDeterministic), hex_literal::hex!("6365cc81"));
+    /// ```
+    pub fn to_raw_text(text: impl Into<String>) -> CBOR {
+        CBORCase::Text(text.into()).into()
+    }
+
     /// Create a new CBOR value representing a tagged value.
+    ///
+    /// The item is not checked against the tag, so a bignum (tag 2 or 3)
+    /// created this way whose item is not a byte string holding an integer
+    /// too large to be a plain integer, without leading zero bytes, is
+    /// encoded as data that the decoder rejects. Bignums are best created
+    /// from `u128`, `i128`, or `BigInt` values, which become plain integers
+    /// where they fit.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let one = CBOR::to_tagged_value(2, CBOR::to_byte_string([1]));
+    /// assert!(CBOR::try_from_data(one.to_cbor_data()).is_err());
+    /// assert_eq!(CBOR::from(1u128).to_cbor_data(), [0x01]);
+    /// ```
     pub fn to_tagged_value(tag: impl Into<Tag>, item: impl Into<CBOR>) -> CBOR {
         CBORCase::Tagged(tag.into(), item.into()).into()
     }
@@ -226,17 +537,27 @@ impl CBOR {
 
 impl PartialEq for CBOR {
     fn eq(&self, other: &Self) -> bool {
-        match (self.as_case(), other.as_case()) {
-            (CBORCase::Unsigned(l0), CBORCase::Unsigned(r0)) => l0 == r0,
-            (CBORCase::Negative(l0), CBORCase::Negative(r0)) => l0 == r0,
-            (CBORCase::ByteString(l0), CBORCase::ByteString(r0)) => l0 == r0,
-            (CBORCase::Text(l0), CBORCase::Text(r0)) => l0 == r0,
-            (CBORCase::Array(l0), CBORCase::Array(r0)) => l0 == r0,
-            (CBORCase::Map(l0), CBORCase::Map(r0)) => l0 == r0,
-            (CBORCase::Tagged(l0, l1), CBORCase::Tagged(r0, r1)) => l0 == r0 && l1 == r1,
-            (CBORCase::Simple(l0), CBORCase::Simple(r0)) => l0 == r0,
-            _ => false,
-        }
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for CBOR { }
+
+impl PartialOrd for CBOR {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CBOR {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        compare(Part::Item(self), Part::Item(other))
+    }
+}
+
+impl hash::Hash for CBOR {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        hash_encoding(EncodeTask::Item(self), state);
     }
 }
 
@@ -252,43 +573,115 @@ fn format_string(s: &str) -> String {
     flanked(&result, r#"""#, r#"""#)
 }
 
-fn format_array(a: &[CBOR]) -> String {
-    let s: Vec<String> = a.iter().map(|x| format!("{}", x)).collect();
-    flanked(&s.join(", "), "[", "]")
+/// A pending unit of work for the non-recursive `Debug` and `Display`
+/// implementations.
+enum FormatTask<'a> {
+    Item(&'a CBOR),
+    Str(&'static str),
+    String(String),
+}
+
+/// Writes `root` to `f` using an explicit work stack rather than recursion.
+///
+/// `format_item` writes the scalar content of an item, or pushes the tasks
+/// needed to write a composite item.
+fn format_iteratively<'a>(
+    root: &'a CBOR,
+    f: &mut fmt::Formatter<'_>,
+    format_item: impl Fn(&'a CBOR, &mut Vec<FormatTask<'a>>) -> Option<String>,
+) -> fmt::Result {
+    let mut stack = vec![FormatTask::Item(root)];
+    while let Some(task) = stack.pop() {
+        match task {
+            FormatTask::Item(cbor) => {
+                if let Some(s) = format_item(cbor, &mut stack) {
+                    f.write_str(&s)?;
+                }
+            },
+            FormatTask::Str(s) => f.write_str(s)?,
+            FormatTask::String(s) => f.write_str(&s)?,
+        }
+    }
+    Ok(())
 }
 
-fn format_map(m: &Map) -> String {
-    let s: Vec<String> = m.iter().map(|x| format!("{}: {}", x.0, x.1)).collect();
-    flanked(&s.join(", "), "{", "}")
+/// Pushes the tasks for a delimited, comma-separated sequence of tasks.
+fn push_sequence<'a>(stack: &mut Vec<FormatTask<'a>>, begin: &'static str, end: &'static str, elements: Vec<Vec<FormatTask<'a>>>) {
+    stack.push(FormatTask::Str(end));
+    let len = elements.len();
+    for (index, element) in elements.into_iter().enumerate().rev() {
+        if index != len - 1 {
+            stack.push(FormatTask::Str(", "));
+        }
+        stack.extend(element.into_iter().rev());
+    }
+    stack.push(FormatTask::Str(begin));
 }
 
 impl fmt::Debug for CBOR {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.as_case() {
-            CBORCase::Unsigned(x) => f.debug_tuple("unsigned").field(x).finish(),
-            CBORCase::Negative(x) => f.debug_tuple("negative").field(&(-1 - (*x as i128))).finish(),
-            CBORCase::ByteString(x) => f.write_fmt(format_args!("bytes({})", hex::encode(x))),
-            CBORCase::Text(x) => f.debug_tuple("text").field(x).finish(),
-            CBORCase::Array(x) => f.debug_tuple("array").field(x).finish(),
-            CBORCase::Map(x) => f.debug_tuple("map").field(x).finish(),
-            CBORCase::Tagged(tag, item) => f.write_fmt(format_args!("tagged({}, {:?})", tag, item)),
-            CBORCase::Simple(x) => f.write_fmt(format_args!("simple({})", x.name())),
-        }
+        format_iteratively(self, f, |cbor, stack| {
+            match cbor.as_case() {
+                CBORCase::Unsigned(x) => Some(format!("unsigned({:?})", x)),
+                CBORCase::Negative(x) => Some(format!("negative({:?})", -1 - (*x as i128))),
+                CBORCase::ByteString(x) => Some(format!("bytes({})", hex::encode(x))),
+                CBORCase::Text(x) => Some(format!("text({:?})", x)),
+                CBORCase::Array(x) => {
+                    let elements = x.iter().map(|item| vec![FormatTask::Item(item)]).collect();
+                    push_sequence(stack, "array([", "])", elements);
+                    None
+                },
+                CBORCase::Map(x) => {
+                    let elements = x.encoded_iter().zip(x.iter()).map(|((key_data, _), (key, value))| vec![
+                        FormatTask::String(format!("0x{}: (", hex::encode(key_data))),
+                        FormatTask::Item(key),
+                        FormatTask::Str(", "),
+                        FormatTask::Item(value),
+                        FormatTask::Str(")"),
+                    ]).collect();
+                    push_sequence(stack, "map({", "})", elements);
+                    None
+                },
+                CBORCase::Tagged(tag, item) => {
+                    stack.push(FormatTask::Str(")"));
+                    stack.push(FormatTask::Item(item));
+                    Some(format!("tagged({}, ", tag))
+                },
+                CBORCase::Simple(x) => Some(format!("simple({})", x.name())),
+            }
+        })
     }
 }
 
 impl fmt::Display for CBOR {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match self.as_case() {
-            CBORCase::Unsigned(x) => format!("{}", x),
-            CBORCase::Negative(x) => format!("{}", -1 - (*x as i128)),
-            CBORCase::ByteString(x) => format!("h'{}'", hex::encode(x)),
-            CBORCase::Text(x) => format_string(x),
-            CBORCase::Array(x) => format_array(x),
-            CBORCase::Map(x) => format_map(x),
-            CBORCase::Tagged(tag, item) => format!("{}({})", tag, item),
-            CBORCase::Simple(x) => format!("{}", x),
-        };
-        f.write_str(&s)
+        format_iteratively(self, f, |cbor, stack| {
+            match cbor.as_case() {
+                CBORCase::Unsigned(x) => Some(format!("{}", x)),
+                CBORCase::Negative(x) => Some(format!("{}", -1 - (*x as i128))),
+                CBORCase::ByteString(x) => Some(format!("h'{}'", hex::encode(x))),
+                CBORCase::Text(x) => Some(format_string(x)),
+                CBORCase::Array(x) => {
+                    let elements = x.iter().map(|item| vec![FormatTask::Item(item)]).collect();
+                    push_sequence(stack, "[", "]", elements);
+                    None
+                },
+                CBORCase::Map(x) => {
+                    let elements = x.iter().map(|(key, value)| vec![
+                        FormatTask::Item(key),
+                        FormatTask::Str(": "),
+                        FormatTask::Item(value),
+                    ]).collect();
+                    push_sequence(stack, "{", "}", elements);
+                    None
+                },
+                CBORCase::Tagged(tag, item) => {
+                    stack.push(FormatTask::Str(")"));
+                    stack.push(FormatTask::Item(item));
+                    Some(format!("{}(", tag))
+                },
+                CBORCase::Simple(x) => Some(format!("{}", x)),
+            }
+        })
     }
 }
@@ -0,0 +1,386 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{cbor::RefCounted, decode::decode_cbor, CBORError, Event, Events, Simple, Tag, TagValue, CBOR};
+
+/// A borrowed representation of CBOR data, whose byte strings and text
+/// strings are slices of the buffer it was decoded from.
+///
+/// A `CBORRef` is validated against the same deterministic encoding rules as
+/// [`CBOR`], and offers the same accessors and `TryFrom` conversions. Each
+/// item also retains its encoded form, so two items are equal exactly when
+/// their encodings are, and conversion to an owned [`CBOR`] is available on
+/// demand.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// // {1: "hello", 2: h'0102'}
+/// let data = hex_literal::hex!("a2016568656c6c6f02420102");
+/// let cbor = CBORRef::try_from_data(&data).unwrap();
+/// let map = cbor.clone().try_into_map().unwrap();
+/// let text: &str = map.extract(1).unwrap();
+/// assert_eq!(text, "hello");
+/// // The text is a slice of the input.
+/// assert!(data.as_ptr_range().contains(&text.as_ptr()));
+/// assert_eq!(cbor.to_cbor().diagnostic_flat(), r#"{1: "hello", 2: h'0102'}"#);
+/// ```
+#[derive(Clone)]
+pub struct CBORRef<'a> {
+    encoded: &'a [u8],
+    case: RefCounted<CBORRefCase<'a>>,
+}
+
+/// The cases of a [`CBORRef`], corresponding to those of a
+/// [`CBORCase`](crate::CBORCase).
+#[derive(Debug, Clone)]
+pub enum CBORRefCase<'a> {
+    /// Unsigned integer (major type 0).
+    Unsigned(u64),
+    /// Negative integer (major type 1).
+    Negative(u64),
+    /// Byte string (major type 2).
+    ByteString(&'a [u8]),
+    /// UTF-8 string (major type 3).
+    Text(&'a str),
+    /// Array (major type 4).
+    Array(Vec<CBORRef<'a>>),
+    /// Map (major type 5).
+    Map(MapRef<'a>),
+    /// Tagged value (major type 6).
+    Tagged(Tag, CBORRef<'a>),
+    /// Simple value (major type 7).
+    Simple(Simple),
+}
+
+impl<'a> CBORRef<'a> {
+    fn new(encoded: &'a [u8], case: CBORRefCase<'a>) -> Self {
+        Self { encoded, case: RefCounted::new(case) }
+    }
+
+    pub fn as_case(&self) -> &CBORRefCase<'a> {
+        &self.case
+    }
+
+    pub fn into_case(mut self) -> CBORRefCase<'a> {
+        match RefCounted::get_mut(&mut self.case) {
+            Some(case) => mem::replace(case, CBORRefCase::Unsigned(0)),
+            None => self.as_case().clone(),
+        }
+    }
+
+    /// Returns the encoded form of this item, as a slice of the buffer it was
+    /// decoded from.
+    pub fn encoded(&self) -> &'a [u8] {
+        self.encoded
+    }
+
+    /// Returns the encoded form of this item.
+    pub fn to_cbor_data(&self) -> Vec<u8> {
+        self.encoded.to_vec()
+    }
+
+    /// Returns an owned copy of this item.
+    pub fn to_cbor(&self) -> CBOR {
+        decode_cbor(self.encoded).expect("a CBORRef is always valid dCBOR")
+    }
+}
+
+/// A container whose items are still being decoded.
+enum Frame<'a> {
+    Array { start: usize, items: Vec<CBORRef<'a>> },
+    Map { start: usize, entries: Vec<(CBORRef<'a>, CBORRef<'a>)>, key: Option<CBORRef<'a>> },
+    Tagged { start: usize, tag: TagValue },
+}
+
+impl<'a> CBORRef<'a> {
+    /// Decodes the given data into a borrowed representation.
+    ///
+    /// Returns an error if the data is not well-formed deterministic CBOR.
+    pub fn try_from_data(data: &'a [u8]) -> Result<CBORRef<'a>> {
+        let mut events = Events::new(data);
+        let mut stack: Vec<Frame<'a>> = Vec::new();
+        let mut root = None;
+        loop {
+            let start = events.position();
+            let Some(event) = events.next() else {
+                break;
+            };
+            let (start, case) = match event? {
+                Event::Unsigned(n) => (start, CBORRefCase::Unsigned(n)),
+                Event::Negative(n) => (start, CBORRefCase::Negative(n)),
+                Event::Bytes(bytes) => (start, CBORRefCase::ByteString(bytes)),
+                Event::Text(text) => (start, CBORRefCase::Text(text)),
+                Event::Simple(simple) => (start, CBORRefCase::Simple(simple)),
+                Event::Float(f) => (start, CBORRefCase::Simple(Simple::Float(f))),
+                Event::StartArray(len) => {
+                    stack.push(Frame::Array { start, items: Vec::with_capacity(len) });
+                    continue;
+                },
+                Event::StartMap(len) => {
+                    stack.push(Frame::Map { start, entries: Vec::with_capacity(len), key: None });
+                    continue;
+                },
+                Event::Tag(tag) => {
+                    stack.push(Frame::Tagged { start, tag });
+                    continue;
+                },
+                Event::End => match stack.pop() {
+                    Some(Frame::Array { start, items }) => (start, CBORRefCase::Array(items)),
+                    Some(Frame::Map { start, entries, .. }) => (start, CBORRefCase::Map(MapRef(entries))),
+                    _ => unreachable!("only arrays and maps end"),
+                },
+            };
+            let end = events.position();
+            let item = CBORRef::new(&data[start..end], case);
+            Self::fold(item, &mut stack, &mut root, data, end);
+        }
+        Ok(root.expect("a successful parse yields an item"))
+    }
+
+    /// Adds an item ending at `end` to its enclosing container, completing
+    /// any tags that enclose it.
+    fn fold(mut item: CBORRef<'a>, stack: &mut Vec<Frame<'a>>, root: &mut Option<CBORRef<'a>>, data: &'a [u8], end: usize) {
+        loop {
+            match stack.last_mut() {
+                None => {
+                    *root = Some(item);
+                    return;
+                },
+                Some(Frame::Array { items, .. }) => {
+                    items.push(item);
+                    return;
+                },
+                Some(Frame::Map { entries, key, .. }) => {
+                    match key.take() {
+                        None => *key = Some(item),
+                        Some(key) => entries.push((key, item)),
+                    }
+                    return;
+                },
+                Some(&mut Frame::Tagged { start, tag }) => {
+                    stack.pop();
+                    item = CBORRef::new(&data[start..end], CBORRefCase::Tagged(Tag::with_value(tag), item));
+                },
+            }
+        }
+    }
+}
+
+impl<'a> CBORRef<'a> {
+    /// Extract the value as a byte string.
+    ///
+    /// Returns `Ok` if the value is a byte string, `Err` otherwise.
+    pub fn try_into_byte_string(self) -> Result<&'a [u8]> {
+        match self.into_case() {
+            CBORRefCase::ByteString(b) => Ok(b),
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+
+    pub fn into_byte_string(self) -> Option<&'a [u8]> {
+        self.try_into_byte_string().ok()
+    }
+
+    /// Extract the value as a text string.
+    ///
+    /// Returns `Ok` if the value is a text string, `Err` otherwise.
+    pub fn try_into_text(self) -> Result<&'a str> {
+        match self.into_case() {
+            CBORRefCase::Text(t) => Ok(t),
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+
+    /// Extract the value as an array.
+    ///
+    /// Returns `Ok` if the value is an array, `Err` otherwise.
+    pub fn try_into_array(self) -> Result<Vec<CBORRef<'a>>> {
+        match self.into_case() {
+            CBORRefCase::Array(a) => Ok(a),
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+
+    /// Extract the value as a map.
+    ///
+    /// Returns `Ok` if the value is a map, `Err` otherwise.
+    pub fn try_into_map(self) -> Result<MapRef<'a>> {
+        match self.into_case() {
+            CBORRefCase::Map(m) => Ok(m),
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+
+    /// Extract the value as a tagged value.
+    ///
+    /// Returns `Ok` if the value is a tagged value, `Err` otherwise.
+    pub fn try_into_tagged_value(self) -> Result<(Tag, CBORRef<'a>)> {
+        match self.into_case() {
+            CBORRefCase::Tagged(tag, value) => Ok((tag, value)),
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+
+    /// Extract the value as an expected tagged value.
+    ///
+    /// Returns `Ok` if the value is a tagged value with the expected tag, `Err`
+    /// otherwise.
+    pub fn try_into_expected_tagged_value(self, expected_tag: impl Into<Tag>) -> Result<CBORRef<'a>> {
+        let (tag, value) = self.try_into_tagged_value()?;
+        let expected_tag = expected_tag.into();
+        if tag == expected_tag {
+            Ok(value)
+        } else {
+            bail!(CBORError::WrongTag(expected_tag, tag))
+        }
+    }
+
+    /// Extract the value as a simple value.
+    ///
+    /// Returns `Ok` if the value is a simple value, `Err` otherwise.
+    pub fn try_into_simple_value(self) -> Result<Simple> {
+        match self.into_case() {
+            CBORRefCase::Simple(s) => Ok(s),
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+}
+
+/// Drops nested values with an explicit work stack, so that arbitrarily deep
+/// structures cannot overflow the call stack.
+impl Drop for CBORRef<'_> {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        take_children(&mut self.case, &mut stack);
+        while let Some(mut cbor) = stack.pop() {
+            take_children(&mut cbor.case, &mut stack);
+        }
+    }
+}
+
+/// Moves the children of a uniquely-owned node onto `stack`, leaving the node
+/// without children.
+fn take_children<'a>(case: &mut RefCounted<CBORRefCase<'a>>, stack: &mut Vec<CBORRef<'a>>) {
+    let Some(case) = RefCounted::get_mut(case) else {
+        return;
+    };
+    match case {
+        CBORRefCase::Array(items) => stack.append(items),
+        CBORRefCase::Map(map) => {
+            for (key, value) in map.0.drain(..) {
+                stack.push(key);
+                stack.push(value);
+            }
+        },
+        CBORRefCase::Tagged(_, _) => {
+            if let CBORRefCase::Tagged(_, item) = mem::replace(case, CBORRefCase::Unsigned(0)) {
+                stack.push(item);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Items are equal when their encodings are, which for deterministic CBOR is
+/// when they have the same value.
+impl PartialEq for CBORRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.encoded == other.encoded
+    }
+}
+
+impl Eq for CBORRef<'_> { }
+
+impl hash::Hash for CBORRef<'_> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.encoded.hash(state);
+    }
+}
+
+impl fmt::Debug for CBORRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_cbor(), f)
+    }
+}
+
+impl fmt::Display for CBORRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_cbor(), f)
+    }
+}
+
+impl<'a> From<CBORRef<'a>> for CBOR {
+    fn from(value: CBORRef<'a>) -> Self {
+        value.to_cbor()
+    }
+}
+
+impl<'a> From<&CBORRef<'a>> for CBOR {
+    fn from(value: &CBORRef<'a>) -> Self {
+        value.to_cbor()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for CBORRef<'a> {
+    type Error = anyhow::Error;
+
+    fn try_from(data: &'a [u8]) -> Result<Self> {
+        Self::try_from_data(data)
+    }
+}
+
+/// A borrowed CBOR map, whose entries are in the order of their keys'
+/// encodings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapRef<'a>(Vec<(CBORRef<'a>, CBORRef<'a>)>);
+
+impl<'a> MapRef<'a> {
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Gets an iterator over the entries of the map, sorted by key.
+    ///
+    /// Key sorting order is lexicographic by the key's binary-encoded CBOR.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&CBORRef<'a>, &CBORRef<'a>)> + ExactSizeIterator {
+        self.0.iter().map(|(key, value)| (key, value))
+    }
+
+    /// Returns the entries of the map, sorted by key.
+    pub(crate) fn into_entries(self) -> impl Iterator<Item = (CBORRef<'a>, CBORRef<'a>)> {
+        self.0.into_iter()
+    }
+
+    /// Get a value from the map, given a key.
+    ///
+    /// Returns `Some` if the key is present in the map, `None` otherwise.
+    pub fn get<K, V>(&self, key: K) -> Option<V>
+    where
+        K: Into<CBOR>, V: TryFrom<CBORRef<'a>>
+    {
+        let key_data = key.into().to_cbor_data();
+        match self.0.binary_search_by(|(key, _)| key.encoded.cmp(key_data.as_slice())) {
+            Ok(index) => V::try_from(self.0[index].1.clone()).ok(),
+            Err(_) => None
+        }
+    }
+
+    /// Get a value from the map, given a key.
+    ///
+    /// Returns `Ok` if the key is present in the map, `Err` otherwise.
+    pub fn extract<K, V>(&self, key: K) -> Result<V>
+    where
+        K: Into<CBOR>, V: TryFrom<CBORRef<'a>>
+    {
+        match self.get(key) {
+            Some(value) => Ok(value),
+            None => bail!(CBORError::MissingMapKey)
+        }
+    }
+}
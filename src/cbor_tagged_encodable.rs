@@ -1,6 +1,6 @@
 import_stdlib!();
 
-use crate::{CBOR, CBORTagged, CBORCase};
+use crate::{varint::{Header, MajorType}, CBOR, CBORTagged, CBORCase};
 
 /// A type that can be encoded to CBOR with a specific tag.
 ///
@@ -19,4 +19,11 @@ pub trait CBORTaggedEncodable: CBORTagged {
     fn tagged_cbor_data(&self) -> Vec<u8> {
         self.tagged_cbor().to_cbor_data()
     }
+
+    /// Returns the length of the tagged value in CBOR binary representation,
+    /// without producing it.
+    fn tagged_encoded_len(&self) -> usize {
+        let tag = Header::new(MajorType::Tagged, Self::cbor_tags()[0].value());
+        tag.as_slice().len() + self.untagged_cbor().encoded_len()
+    }
 }
@@ -6,7 +6,7 @@ use chrono::{DateTime, Utc, TimeZone, SecondsFormat, NaiveDate, NaiveDateTime, T
 
 use anyhow::{bail, Error, Result};
 
-use crate::{CBORTaggedEncodable, Tag, CBOR, CBORTaggedDecodable, CBORTagged};
+use crate::{CBORTaggedEncodable, Tag, CBOR, CBORRef, CBORTaggedDecodable, CBORTagged};
 
 /// A CBOR-friendly representation of a date and time.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -161,6 +161,14 @@ impl TryFrom<CBOR> for Date {
     }
 }
 
+impl<'a> TryFrom<CBORRef<'a>> for Date {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        Self::try_from(cbor.to_cbor())
+    }
+}
+
 impl CBORTagged for Date {
     fn cbor_tags() -> Vec<Tag> {
         vec![Tag::with_value(1)]
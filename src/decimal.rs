@@ -0,0 +1,333 @@
+import_stdlib!();
+
+use anyhow::{bail, Error, Result};
+
+use crate::{CBORError, CBORRef, CBORTagged, CBORTaggedDecodable, CBORTaggedEncodable, Tag, CBOR, TAG_BIGFLOAT, TAG_DECIMAL_FRACTION};
+
+/// The most zeros written out when formatting a number, beyond which it is
+/// written with an exponent instead.
+const MAX_FORMATTED_ZEROS: u64 = 20;
+
+/// A decimal number `mantissa * 10^exponent`, encoded with tag 4.
+///
+/// Values are kept in a canonical form, in which the mantissa has no factors
+/// of ten and zero has an exponent of zero, so that equal values are always
+/// encoded the same way. Decoding rejects values not in this form with
+/// [`CBORError::NonCanonicalNumeric`], and values whose mantissa does not fit
+/// in an `i128` or whose exponent does not fit in an `i64` with
+/// [`CBORError::OutOfRange`].
+///
+/// ```
+/// # use dcbor::{prelude::*, DecimalFraction};
+/// let amount = DecimalFraction::new(12340, -3);
+/// assert_eq!((amount.mantissa(), amount.exponent()), (1234, -2));
+/// assert_eq!(amount.to_string(), "12.34");
+/// assert_eq!(CBOR::from(amount).diagnostic_flat(), "4([-2, 1234])");
+///
+/// // 4([-3, 12340])
+/// let error = DecimalFraction::from_tagged_cbor_data(hex_literal::hex!("c4 82 22 193034")).unwrap_err();
+/// assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::NonCanonicalNumeric)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecimalFraction {
+    mantissa: i128,
+    exponent: i64,
+}
+
+impl DecimalFraction {
+    /// Creates the number `mantissa * 10^exponent`, in its canonical form.
+    pub fn new(mantissa: i128, exponent: i64) -> Self {
+        if mantissa == 0 {
+            return Self { mantissa, exponent: 0 };
+        }
+        let (mut mantissa, mut exponent) = (mantissa, exponent);
+        while mantissa % 10 == 0 && exponent < i64::MAX {
+            mantissa /= 10;
+            exponent += 1;
+        }
+        Self { mantissa, exponent }
+    }
+
+    /// Returns the mantissa of the canonical form.
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// Returns the exponent of the canonical form.
+    pub fn exponent(&self) -> i64 {
+        self.exponent
+    }
+}
+
+impl From<i64> for DecimalFraction {
+    fn from(value: i64) -> Self {
+        Self::new(value as i128, 0)
+    }
+}
+
+impl From<DecimalFraction> for CBOR {
+    fn from(value: DecimalFraction) -> Self {
+        value.tagged_cbor()
+    }
+}
+
+impl TryFrom<CBOR> for DecimalFraction {
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        Self::from_tagged_cbor(cbor)
+    }
+}
+
+impl<'a> TryFrom<CBORRef<'a>> for DecimalFraction {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        Self::try_from(cbor.to_cbor())
+    }
+}
+
+impl CBORTagged for DecimalFraction {
+    fn cbor_tags() -> Vec<Tag> {
+        vec![Tag::with_value(TAG_DECIMAL_FRACTION)]
+    }
+}
+
+impl CBORTaggedEncodable for DecimalFraction {
+    fn untagged_cbor(&self) -> CBOR {
+        exponent_mantissa_cbor(self.exponent, self.mantissa)
+    }
+}
+
+impl CBORTaggedDecodable for DecimalFraction {
+    fn from_untagged_cbor(cbor: CBOR) -> Result<Self> {
+        let (exponent, mantissa) = exponent_mantissa_from_cbor(cbor)?;
+        let value = Self::new(mantissa, exponent);
+        if (value.mantissa, value.exponent) != (mantissa, exponent) {
+            bail!(CBORError::NonCanonicalNumeric);
+        }
+        Ok(value)
+    }
+}
+
+/// Formats the number in positional notation, such as `12.34` or `1200`,
+/// unless that would take more than twenty zeros, in which case it is
+/// formatted with an exponent, such as `12e-100`.
+impl fmt::Display for DecimalFraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let exponent = self.exponent;
+        // The number of digits before the decimal point.
+        let point = digits.len() as i64 + exponent.min(0);
+        if exponent >= 0 {
+            if exponent as u64 <= MAX_FORMATTED_ZEROS {
+                return write!(f, "{}{}{}", sign, digits, "0".repeat(exponent as usize));
+            }
+        } else if point > 0 {
+            let (whole, fraction) = digits.split_at(point as usize);
+            return write!(f, "{}{}.{}", sign, whole, fraction);
+        } else if point.unsigned_abs() <= MAX_FORMATTED_ZEROS {
+            return write!(f, "{}0.{}{}", sign, "0".repeat(point.unsigned_abs() as usize), digits);
+        }
+        write!(f, "{}{}e{}", sign, digits, exponent)
+    }
+}
+
+/// A binary floating point number `mantissa * 2^exponent`, encoded with tag
+/// 5.
+///
+/// Values are kept in a canonical form, in which the mantissa is odd and zero
+/// has an exponent of zero, so that equal values are always encoded the same
+/// way. Decoding rejects values not in this form with
+/// [`CBORError::NonCanonicalNumeric`], and values whose mantissa does not fit
+/// in an `i128` or whose exponent does not fit in an `i64` with
+/// [`CBORError::OutOfRange`].
+///
+/// ```
+/// # use dcbor::{prelude::*, BigFloat};
+/// let value = BigFloat::new(12, -3);
+/// assert_eq!((value.mantissa(), value.exponent()), (3, -1));
+/// assert_eq!(value.to_string(), "1.5");
+/// assert_eq!(CBOR::from(value).diagnostic_flat(), "5([-1, 3])");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BigFloat {
+    mantissa: i128,
+    exponent: i64,
+}
+
+impl BigFloat {
+    /// Creates the number `mantissa * 2^exponent`, in its canonical form.
+    pub fn new(mantissa: i128, exponent: i64) -> Self {
+        if mantissa == 0 {
+            return Self { mantissa, exponent: 0 };
+        }
+        let shift = (mantissa.trailing_zeros() as i64).min(i64::MAX - exponent.max(0));
+        Self { mantissa: mantissa >> shift, exponent: exponent + shift }
+    }
+
+    /// Returns the mantissa of the canonical form.
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// Returns the exponent of the canonical form.
+    pub fn exponent(&self) -> i64 {
+        self.exponent
+    }
+
+    /// Returns the same number as a decimal fraction, if its mantissa fits
+    /// in an `i128`.
+    fn to_decimal_fraction(self) -> Option<DecimalFraction> {
+        let magnitude = self.mantissa.unsigned_abs();
+        let (magnitude, exponent) = if self.exponent >= 0 {
+            // m * 2^e
+            let shift = u32::try_from(self.exponent).ok().filter(|&shift| shift <= magnitude.leading_zeros())?;
+            (magnitude << shift, 0)
+        } else {
+            // m * 2^-k = m * 5^k * 10^-k
+            let k = u32::try_from(self.exponent.unsigned_abs()).ok()?;
+            (magnitude.checked_mul(5u128.checked_pow(k)?)?, self.exponent)
+        };
+        let mantissa = i128::try_from(magnitude).ok()?;
+        Some(DecimalFraction::new(if self.mantissa < 0 { -mantissa } else { mantissa }, exponent))
+    }
+}
+
+impl From<i64> for BigFloat {
+    fn from(value: i64) -> Self {
+        Self::new(value as i128, 0)
+    }
+}
+
+impl From<BigFloat> for CBOR {
+    fn from(value: BigFloat) -> Self {
+        value.tagged_cbor()
+    }
+}
+
+impl TryFrom<CBOR> for BigFloat {
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        Self::from_tagged_cbor(cbor)
+    }
+}
+
+impl<'a> TryFrom<CBORRef<'a>> for BigFloat {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        Self::try_from(cbor.to_cbor())
+    }
+}
+
+impl CBORTagged for BigFloat {
+    fn cbor_tags() -> Vec<Tag> {
+        vec![Tag::with_value(TAG_BIGFLOAT)]
+    }
+}
+
+impl CBORTaggedEncodable for BigFloat {
+    fn untagged_cbor(&self) -> CBOR {
+        exponent_mantissa_cbor(self.exponent, self.mantissa)
+    }
+}
+
+impl CBORTaggedDecodable for BigFloat {
+    fn from_untagged_cbor(cbor: CBOR) -> Result<Self> {
+        let (exponent, mantissa) = exponent_mantissa_from_cbor(cbor)?;
+        let value = Self::new(mantissa, exponent);
+        if (value.mantissa, value.exponent) != (mantissa, exponent) {
+            bail!(CBORError::NonCanonicalNumeric);
+        }
+        Ok(value)
+    }
+}
+
+/// Formats the number exactly in decimal, such as `1.5`, unless its decimal
+/// mantissa would not fit in an `i128`, in which case it is formatted as a
+/// hexadecimal floating point number, such as `0x3p-200`.
+impl fmt::Display for BigFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_decimal_fraction() {
+            Some(decimal) => write!(f, "{}", decimal),
+            None => {
+                let sign = if self.mantissa < 0 { "-" } else { "" };
+                write!(f, "{}0x{:x}p{}", sign, self.mantissa.unsigned_abs(), self.exponent)
+            },
+        }
+    }
+}
+
+/// Returns the untagged encoding of a decimal fraction or bigfloat.
+fn exponent_mantissa_cbor(exponent: i64, mantissa: i128) -> CBOR {
+    vec![CBOR::from(exponent), CBOR::from(mantissa)].into()
+}
+
+/// Returns the exponent and mantissa of a decimal fraction or bigfloat from
+/// its untagged encoding. The exponent must be a plain integer, while the
+/// mantissa may also be a bignum.
+fn exponent_mantissa_from_cbor(cbor: CBOR) -> Result<(i64, i128)> {
+    let items = cbor.try_into_array()?;
+    let [exponent, mantissa] = <[CBOR; 2]>::try_from(items).map_err(|_| CBORError::WrongType)?;
+    Ok((exponent.try_into()?, mantissa.try_into()?))
+}
+
+#[cfg(feature = "rust_decimal")]
+mod rust_decimal_impls {
+    use anyhow::{Error, Result};
+    use rust_decimal::Decimal;
+
+    use super::DecimalFraction;
+    use crate::{CBORError, CBORRef, CBOR};
+
+    impl From<Decimal> for DecimalFraction {
+        fn from(value: Decimal) -> Self {
+            Self::new(value.mantissa(), -(value.scale() as i64))
+        }
+    }
+
+    /// Fails with [`CBORError::OutOfRange`] if the number cannot be
+    /// represented exactly.
+    impl TryFrom<DecimalFraction> for Decimal {
+        type Error = Error;
+
+        fn try_from(value: DecimalFraction) -> Result<Self> {
+            let (mantissa, scale) = if value.exponent() >= 0 {
+                let mantissa = u32::try_from(value.exponent()).ok()
+                    .and_then(|exponent| 10i128.checked_pow(exponent))
+                    .and_then(|factor| value.mantissa().checked_mul(factor))
+                    .ok_or(CBORError::OutOfRange)?;
+                (mantissa, 0)
+            } else {
+                let scale = u32::try_from(value.exponent().unsigned_abs()).map_err(|_| CBORError::OutOfRange)?;
+                (value.mantissa(), scale)
+            };
+            Ok(Decimal::try_from_i128_with_scale(mantissa, scale).map_err(|_| CBORError::OutOfRange)?)
+        }
+    }
+
+    impl From<Decimal> for CBOR {
+        fn from(value: Decimal) -> Self {
+            DecimalFraction::from(value).into()
+        }
+    }
+
+    impl TryFrom<CBOR> for Decimal {
+        type Error = Error;
+
+        fn try_from(cbor: CBOR) -> Result<Self> {
+            DecimalFraction::try_from(cbor)?.try_into()
+        }
+    }
+
+    impl<'a> TryFrom<CBORRef<'a>> for Decimal {
+        type Error = Error;
+
+        fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+            DecimalFraction::try_from(cbor)?.try_into()
+        }
+    }
+}
@@ -4,7 +4,7 @@ use anyhow::{bail, Result, Error};
 use half::f16;
 use unicode_normalization::is_nfc;
 
-use crate::{CBOR, Map, error::CBORError, float::{validate_canonical_f16, validate_canonical_f32, validate_canonical_f64}, CBORCase};
+use crate::{CBOR, Map, cbor::NODE_SIZE, bignum::{is_bignum_tag, validate_bignum}, error::CBORError, float::{preferred_f64_header, validate_canonical_f16, validate_canonical_f32, validate_canonical_f64}, CBORCase, CBORPath, DecodeError, DecodeOptions, DecodeValidator, EncodingProfile, Event, PathElement, Simple, SimpleValue, TagValue};
 
 use super::varint::MajorType;
 
@@ -12,15 +12,164 @@ use super::varint::MajorType;
 ///
 /// Returns an error if the data is not well-formed deterministic CBOR.
 pub fn decode_cbor(data: impl AsRef<[u8]>) -> Result<CBOR> {
-    let data = data.as_ref();
-    let (cbor, len) = decode_cbor_internal(data)?;
+    decode_cbor_with_options(data, &DecodeOptions::default())
+}
+
+/// Decode CBOR binary representation to symbolic representation, enforcing
+/// the given limits.
+///
+/// Returns an error if the data is not well-formed deterministic CBOR, or if
+/// it exceeds any of the limits.
+pub fn decode_cbor_with_options(data: impl AsRef<[u8]>, options: &DecodeOptions) -> Result<CBOR> {
+    decode_slice(data.as_ref(), &mut DecodeLimits::new(options))
+}
+
+/// Decode CBOR binary representation to symbolic representation, enforcing
+/// the given limits and passing each item to `validator`.
+pub fn decode_cbor_with_validator(data: impl AsRef<[u8]>, options: &DecodeOptions, validator: &mut dyn DecodeValidator) -> Result<CBOR> {
+    decode_slice(data.as_ref(), &mut DecodeLimits::with_validator(options, validator))
+}
+
+fn decode_slice(data: &[u8], limits: &mut DecodeLimits<'_>) -> Result<CBOR> {
+    let mut source = SliceSource { data, pos: 0 };
+    let cbor = decode_cbor_internal(&mut source, limits)?;
+    let len = source.pos;
     let remaining = data.len() - len;
     if remaining > 0 {
-        bail!(CBORError::UnusedData(remaining));
+        return Err(located(CBORError::UnusedData(remaining).into(), len, CBORPath::default()));
     }
     Ok(cbor)
 }
 
+/// A source of encoded bytes for the decoder.
+pub(crate) trait Source {
+    /// Returns the number of bytes consumed so far.
+    fn position(&self) -> usize;
+
+    /// Returns the number of bytes remaining, if known.
+    fn remaining(&self) -> Option<usize>;
+
+    /// Fills `buf` with the next bytes, failing with `Underrun` if there are
+    /// not enough.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()>;
+
+    /// Returns the next `len` bytes, failing with `Underrun` if there are not
+    /// enough.
+    fn read_content(&mut self, len: usize) -> Result<Vec<u8>>;
+
+    /// Returns the encoded form of `key`, which has just been decoded starting
+    /// at `start` following the rules of `profile`.
+    fn key_data(&self, start: usize, key: &CBOR, profile: EncodingProfile) -> Vec<u8>;
+}
+
+pub(crate) struct SliceSource<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) pos: usize,
+}
+
+impl Source for SliceSource<'_> {
+    fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        Some(self.data.len() - self.pos)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        buf.copy_from_slice(parse_bytes(&self.data[self.pos..], buf.len())?);
+        self.pos += buf.len();
+        Ok(())
+    }
+
+    fn read_content(&mut self, len: usize) -> Result<Vec<u8>> {
+        let content = parse_bytes(&self.data[self.pos..], len)?.to_vec();
+        self.pos += len;
+        Ok(content)
+    }
+
+    fn key_data(&self, start: usize, _key: &CBOR, _profile: EncodingProfile) -> Vec<u8> {
+        self.data[start..self.pos].to_vec()
+    }
+}
+
+/// Tracks the resources consumed so far against the caller's limits, and
+/// holds the caller's validator, if any.
+pub(crate) struct DecodeLimits<'a> {
+    options: &'a DecodeOptions,
+    validator: Option<&'a mut dyn DecodeValidator>,
+    nodes: usize,
+    allocated: usize,
+}
+
+impl<'a> DecodeLimits<'a> {
+    pub(crate) fn new(options: &'a DecodeOptions) -> Self {
+        Self { options, validator: None, nodes: 0, allocated: 0 }
+    }
+
+    pub(crate) fn with_validator(options: &'a DecodeOptions, validator: &'a mut dyn DecodeValidator) -> Self {
+        Self { options, validator: Some(validator), nodes: 0, allocated: 0 }
+    }
+
+    pub(crate) fn profile(&self) -> EncodingProfile {
+        self.options.profile
+    }
+
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<()> {
+        if depth > self.options.max_depth {
+            bail!(CBORError::DepthLimitExceeded(self.options.max_depth));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_container_len(&self, len: u64) -> Result<()> {
+        if len > self.options.max_container_len as u64 {
+            bail!(CBORError::ContainerLenLimitExceeded(self.options.max_container_len));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_string_len(&self, len: u64) -> Result<()> {
+        if len > self.options.max_string_len as u64 {
+            bail!(CBORError::StringLenLimitExceeded(self.options.max_string_len));
+        }
+        Ok(())
+    }
+
+    /// Charges one item and `bytes` of additional allocation.
+    pub(crate) fn charge(&mut self, bytes: usize) -> Result<()> {
+        self.nodes += 1;
+        if self.nodes > self.options.max_nodes {
+            bail!(CBORError::NodeLimitExceeded(self.options.max_nodes));
+        }
+        self.charge_bytes(NODE_SIZE.saturating_add(bytes))
+    }
+
+    pub(crate) fn charge_bytes(&mut self, bytes: usize) -> Result<()> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        if self.allocated > self.options.max_allocated_bytes {
+            bail!(CBORError::AllocationLimitExceeded(self.options.max_allocated_bytes));
+        }
+        Ok(())
+    }
+
+    /// Passes the item at the path returned by `path` to the validator, if
+    /// there is one. The path is only computed if it is needed.
+    fn validate(&mut self, path: impl FnOnce() -> CBORPath, event: &Event<'_>) -> Result<()> {
+        let Some(validator) = self.validator.as_mut() else {
+            return Ok(());
+        };
+        validator.validate(&path(), event).map_err(|error| {
+            if error.is::<CBORError>() {
+                error
+            } else {
+                let message = error.to_string();
+                error.context(CBORError::Rejected(message))
+            }
+        })
+    }
+}
+
 fn parse_header(header: u8) -> (MajorType, u8) {
     let major_type = match header >> 5 {
         0 => MajorType::Unsigned,
@@ -37,9 +186,29 @@ fn parse_header(header: u8) -> (MajorType, u8) {
     (major_type, header_value)
 }
 
-fn parse_header_varint(data: &[u8]) -> Result<(MajorType, u64, usize)> {
+/// Reads a minimally encoded header from `source`, returning its major type,
+/// its value, and its length.
+pub(crate) fn read_header(source: &mut impl Source) -> Result<(MajorType, u64, usize)> {
+    let mut data = [0u8; 9];
+    source.read_exact(&mut data[..1])?;
+    let varint_len = match data[0] & 31 {
+        0..=23 => 1,
+        24 => 2,
+        25 => 3,
+        26 => 5,
+        27 => 9,
+        v => bail!(CBORError::UnsupportedHeaderValue(v)),
+    };
+    source.read_exact(&mut data[1..varint_len])?;
+    Ok(parse_header_varint(&data[..varint_len])?)
+}
+
+/// Parses the header at the start of `data`, returning its major type, its
+/// value, and its length, without requiring the value to be encoded in its
+/// minimal form.
+pub(crate) fn parse_header_lenient(data: &[u8]) -> Result<(MajorType, u64, usize), CBORError> {
     if data.is_empty() {
-        bail!(CBORError::Underrun)
+        return Err(CBORError::Underrun);
     }
     let header = data[0];
     let (major_type, header_value) = parse_header(header);
@@ -47,35 +216,28 @@ fn parse_header_varint(data: &[u8]) -> Result<(MajorType, u64, usize)> {
     let (value, varint_len) = match header_value {
         0..=23 => (header_value as u64, 1),
         24 => {
-            if data_remaining < 1 { bail!(CBORError::Underrun); }
+            if data_remaining < 1 { return Err(CBORError::Underrun); }
             let val = data[1] as u64;
-            if val < 24 { bail!(CBORError::NonCanonicalNumeric) }
             (val, 2)
         },
         25 => {
-            if data_remaining < 2 { bail!(CBORError::Underrun); }
+            if data_remaining < 2 { return Err(CBORError::Underrun); }
             let val =
                 ((data[1] as u64) << 8) |
                 (data[2] as u64);
-            if val <= u8::MAX as u64 && header != 0xf9 {
-                bail!(CBORError::NonCanonicalNumeric)
-            }
             (val, 3)
         },
         26 => {
-            if data_remaining < 4 { bail!(CBORError::Underrun); }
+            if data_remaining < 4 { return Err(CBORError::Underrun); }
             let val =
                 ((data[1] as u64) << 24) |
                 ((data[2] as u64) << 16) |
                 ((data[3] as u64) << 8) |
                 (data[4] as u64);
-            if val <= u16::MAX as u64 && header != 0xfa {
-                bail!(CBORError::NonCanonicalNumeric)
-            }
             (val, 5)
         },
         27 => {
-            if data_remaining < 8 { bail!(CBORError::Underrun); }
+            if data_remaining < 8 { return Err(CBORError::Underrun); }
             let val =
                 ((data[1] as u64) << 56) |
                 ((data[2] as u64) << 48) |
@@ -85,100 +247,361 @@ fn parse_header_varint(data: &[u8]) -> Result<(MajorType, u64, usize)> {
                 ((data[6] as u64) << 16) |
                 ((data[7] as u64) << 8) |
                 (data[8] as u64);
-            if val <= u32::MAX as u64 && header != 0xfb {
-                bail!(CBORError::NonCanonicalNumeric)
-            }
             (val, 9)
         },
-        v => bail!(CBORError::UnsupportedHeaderValue(v))
+        v => return Err(CBORError::UnsupportedHeaderValue(v)),
     };
     Ok((major_type, value, varint_len))
 }
 
-fn parse_bytes(data: &[u8], len: usize) -> Result<&[u8]> {
+/// Parses the minimally encoded header at the start of `data`, returning its
+/// major type, its value, and its length.
+pub(crate) fn parse_header_varint(data: &[u8]) -> Result<(MajorType, u64, usize), CBORError> {
+    let (major_type, value, varint_len) = parse_header_lenient(data)?;
+    if !is_minimal_header(data[0], value, varint_len) {
+        return Err(CBORError::NonCanonicalNumeric);
+    }
+    Ok((major_type, value, varint_len))
+}
+
+/// Returns `true` if a header's value is encoded in the fewest possible
+/// bytes. Floating point values are exempt, as their width is validated
+/// separately.
+pub(crate) fn is_minimal_header(header: u8, value: u64, varint_len: usize) -> bool {
+    match varint_len {
+        2 => value >= 24,
+        3 => value > u8::MAX as u64 || header == 0xf9,
+        5 => value > u16::MAX as u64 || header == 0xfa,
+        9 => value > u32::MAX as u64 || header == 0xfb,
+        _ => true,
+    }
+}
+
+pub(crate) fn parse_bytes(data: &[u8], len: usize) -> Result<&[u8], CBORError> {
     if data.len() < len {
-        bail!(CBORError::Underrun);
+        return Err(CBORError::Underrun);
     }
     Ok(&data[0..len])
 }
 
-fn decode_cbor_internal(data: &[u8]) -> Result<(CBOR, usize)> {
-    if data.is_empty() {
-        bail!(CBORError::Underrun)
+/// A container whose items are still being decoded.
+enum Frame {
+    Array { items: Vec<CBOR>, len: usize },
+    /// The key is held with its dCBOR encoding and, if it differs, its
+    /// encoding under the profile it was decoded with. Under core
+    /// deterministic encoding, the encoding of the previous key is also kept
+    /// to check the order of the keys as they were read.
+    Map { map: Map, remaining: u64, entry_start: usize, key: Option<(Vec<u8>, Option<Vec<u8>>, CBOR)>, previous_key: Option<Vec<u8>> },
+    Tagged(TagValue),
+}
+
+impl Frame {
+    /// Returns the path element leading to the next item in this container.
+    fn path_element(&self) -> PathElement {
+        match self {
+            Frame::Array { items, .. } => PathElement::Index(items.len()),
+            Frame::Map { map, key: None, .. } => PathElement::KeyAt(map.len()),
+            Frame::Map { key: Some((key_data, ..)), .. } => PathElement::Key(key_data.clone()),
+            Frame::Tagged(tag) => PathElement::Tag(*tag),
+        }
+    }
+}
+
+fn path_for(stack: &[Frame]) -> CBORPath {
+    CBORPath::new(stack.iter().map(Frame::path_element).collect::<Vec<_>>())
+}
+
+/// Attaches the location at which decoding failed to a decoding error.
+pub(crate) fn located(error: Error, offset: usize, path: CBORPath) -> Error {
+    match error.downcast_ref::<CBORError>() {
+        Some(cbor_error) => {
+            let decode_error = DecodeError::new(cbor_error.clone(), offset, path);
+            error.context(decode_error)
+        },
+        None => error,
+    }
+}
+
+/// Attaches the location of the key of the map entry at `index` to a decoding
+/// error, where the map is the innermost container on `stack`.
+fn key_located(error: Error, offset: usize, index: usize, stack: &[Frame]) -> Error {
+    let mut path = path_for(&stack[..stack.len() - 1]);
+    path.push(PathElement::KeyAt(index));
+    located(error, offset, path)
+}
+
+/// Decodes a single item from `source`.
+///
+/// Nested items are tracked on an explicit stack rather than by recursion, so
+/// arbitrarily deep input cannot overflow the call stack.
+pub(crate) fn decode_cbor_internal(source: &mut impl Source, limits: &mut DecodeLimits<'_>) -> Result<CBOR> {
+    let mut stack: Vec<Frame> = Vec::new();
+    loop {
+        let pos = source.position();
+        let decoded = decode_item(source, &stack, limits)
+            .map_err(|error| located(error, pos, path_for(&stack)))?;
+        let mut item = match decoded {
+            Decoded::Item(item) => item,
+            Decoded::Array(len) => {
+                stack.push(Frame::Array { items: Vec::new(), len });
+                continue;
+            },
+            Decoded::Map(len) => {
+                stack.push(Frame::Map { map: Map::new(), remaining: len, entry_start: source.position(), key: None, previous_key: None });
+                continue;
+            },
+            Decoded::Tagged(tag) => {
+                stack.push(Frame::Tagged(tag));
+                continue;
+            },
+        };
+        // Fold the completed item into its enclosing containers, completing
+        // each container in turn whose last item this was.
+        loop {
+            match stack.last_mut() {
+                None => return Ok(item),
+                Some(Frame::Array { items, len }) => {
+                    items.push(item);
+                    if items.len() < *len {
+                        break;
+                    }
+                    item = mem::take(items).into();
+                },
+                Some(Frame::Map { map, remaining, entry_start, key, previous_key }) => {
+                    let profile = limits.profile();
+                    match key.take() {
+                        None => {
+                            // Keys are told apart by their encodings under
+                            // the profile, so that keys such as `1` and `1.0`
+                            // that are the same under dCBOR are not taken
+                            // for duplicates.
+                            let profile_key_data = match profile {
+                                EncodingProfile::Dcbor => None,
+                                EncodingProfile::CoreDeterministic => {
+                                    let read_key = source.key_data(*entry_start, &item, profile);
+                                    if let Some(previous_key) = previous_key {
+                                        let error = match (*previous_key).cmp(&read_key) {
+                                            cmp::Ordering::Less => None,
+                                            cmp::Ordering::Equal => Some(CBORError::DuplicateMapKey),
+                                            cmp::Ordering::Greater => Some(CBORError::MisorderedMapKey),
+                                        };
+                                        if let Some(error) = error {
+                                            let (offset, index) = (*entry_start, map.len());
+                                            return Err(key_located(error.into(), offset, index, &stack));
+                                        }
+                                    }
+                                    *previous_key = Some(read_key.clone());
+                                    Some(read_key)
+                                },
+                                EncodingProfile::Preferred => Some(item.to_cbor_data_with_profile(profile)),
+                            };
+                            let key_data = match profile {
+                                EncodingProfile::Dcbor => source.key_data(*entry_start, &item, profile),
+                                _ => item.to_cbor_data(),
+                            };
+                            let profile_key_data = profile_key_data.filter(|profile_key_data| *profile_key_data != key_data);
+                            let allocated = key_data.len() + profile_key_data.as_ref().map_or(0, Vec::len);
+                            if let Err(error) = limits.charge_bytes(allocated) {
+                                let (offset, index) = (*entry_start, map.len());
+                                return Err(key_located(error, offset, index, &stack));
+                            }
+                            *key = Some((key_data, profile_key_data, item));
+                            break;
+                        },
+                        Some((key_data, profile_key_data, key)) => {
+                            let inserted = match profile {
+                                EncodingProfile::Dcbor => map.insert_next(key_data, key, item),
+                                _ => map.insert_unique(key_data, profile_key_data, key, item),
+                            };
+                            if let Err(error) = inserted {
+                                let (offset, index) = (*entry_start, map.len());
+                                return Err(key_located(error, offset, index, &stack));
+                            }
+                            *remaining -= 1;
+                            *entry_start = source.position();
+                            if *remaining > 0 {
+                                break;
+                            }
+                            item = mem::take(map).into();
+                        },
+                    }
+                },
+                Some(Frame::Tagged(tag)) => {
+                    item = CBOR::to_tagged_value(*tag, item);
+                },
+            }
+            stack.pop();
+        }
+    }
+}
+
+/// The result of decoding a single header.
+enum Decoded {
+    /// A complete item.
+    Item(CBOR),
+    /// The start of a non-empty array with the given length.
+    Array(usize),
+    /// The start of a non-empty map with the given number of entries.
+    Map(u64),
+    /// The start of a tagged item with the given tag.
+    Tagged(TagValue),
+}
+
+fn decode_item(source: &mut impl Source, stack: &[Frame], limits: &mut DecodeLimits<'_>) -> Result<Decoded> {
+    limits.check_depth(stack.len())?;
+    let (major_type, value, header_varint_len) = read_header(source)?;
+    // The content of a bignum must be a byte string.
+    let is_bignum = matches!(stack.last(), Some(Frame::Tagged(tag)) if is_bignum_tag(*tag));
+    if is_bignum && !matches!(major_type, MajorType::ByteString) {
+        bail!(CBORError::WrongType);
     }
-    let (major_type, value, header_varint_len) = parse_header_varint(data)?;
+    let remaining = source.remaining().map_or(u64::MAX, |remaining| remaining as u64);
     match major_type {
-        MajorType::Unsigned => Ok((CBORCase::Unsigned(value).into(), header_varint_len)),
-        MajorType::Negative => Ok((CBORCase::Negative(value).into(), header_varint_len)),
+        MajorType::ByteString | MajorType::Text => {
+            limits.check_string_len(value)?;
+            if value > remaining {
+                bail!(CBORError::Underrun);
+            }
+            limits.charge(value as usize)?;
+        },
+        MajorType::Array | MajorType::Map => {
+            limits.check_container_len(value)?;
+            limits.charge(0)?;
+        },
+        _ => limits.charge(0)?,
+    }
+    let path = || path_for(stack);
+    let item = match major_type {
+        MajorType::Unsigned => {
+            limits.validate(path, &Event::Unsigned(value))?;
+            CBORCase::Unsigned(value).into()
+        },
+        MajorType::Negative => {
+            limits.validate(path, &Event::Negative(value))?;
+            CBORCase::Negative(value).into()
+        },
         MajorType::ByteString => {
-            let data_len = value as usize;
-            let bytes = parse_bytes(&data[header_varint_len..], data_len)?.to_vec().into();
-            Ok((CBORCase::ByteString(bytes).into(), header_varint_len + data_len))
+            let bytes = source.read_content(value as usize)?;
+            if is_bignum {
+                validate_bignum(&bytes)?;
+            }
+            limits.validate(path, &Event::Bytes(&bytes))?;
+            CBORCase::ByteString(bytes.into()).into()
         },
         MajorType::Text => {
-            let data_len = value as usize;
-            let buf = parse_bytes(&data[header_varint_len..], data_len)?;
-            let string = str::from_utf8(buf).map_err(Error::msg)?;
-            if !is_nfc(string) {
-                bail!(CBORError::NonCanonicalString)
-            }
-            Ok((string.into(), header_varint_len + data_len))
+            let buf = source.read_content(value as usize)?;
+            let text = match limits.profile() {
+                EncodingProfile::Dcbor => validate_text(&buf)?,
+                _ => str::from_utf8(&buf).map_err(CBORError::from)?,
+            };
+            limits.validate(path, &Event::Text(text))?;
+            // Text is kept as it was encoded, which under dCBOR is already
+            // normalized.
+            CBOR::to_raw_text(text)
         },
         MajorType::Array => {
-            let mut pos = header_varint_len;
-            let mut items = Vec::new();
-            for _ in 0..value {
-                let (item, item_len) = decode_cbor_internal(&data[pos..])?;
-                items.push(item);
-                pos += item_len;
+            // Every item occupies at least one byte, so a longer array
+            // cannot fit in the remaining data.
+            if value > remaining {
+                bail!(CBORError::Underrun);
+            }
+            limits.validate(path, &Event::StartArray(value as usize))?;
+            if value == 0 {
+                Vec::<CBOR>::new().into()
+            } else {
+                return Ok(Decoded::Array(value as usize));
             }
-            Ok((items.into(), pos))
         },
         MajorType::Map => {
-            let mut pos = header_varint_len;
-            let mut map = Map::new();
-            for _ in 0..value {
-                let (key, key_len) = decode_cbor_internal(&data[pos..])?;
-                pos += key_len;
-                let (value, value_len) = decode_cbor_internal(&data[pos..])?;
-                pos += value_len;
-                map.insert_next(key, value)?;
+            // Every entry occupies at least two bytes.
+            if value > remaining / 2 {
+                bail!(CBORError::Underrun);
+            }
+            limits.validate(path, &Event::StartMap(value as usize))?;
+            if value == 0 {
+                Map::new().into()
+            } else {
+                return Ok(Decoded::Map(value));
             }
-            Ok((map.into(), pos))
         },
         MajorType::Tagged => {
-            let (item, item_len) = decode_cbor_internal(&data[header_varint_len..])?;
-            let tagged = CBOR::to_tagged_value(value, item);
-            Ok((tagged, header_varint_len + item_len))
+            limits.validate(path, &Event::Tag(value))?;
+            return Ok(Decoded::Tagged(value));
         },
         MajorType::Simple => {
-            match header_varint_len {
-                3 => {
-                    let f = f16::from_bits(value as u16);
-                    validate_canonical_f16(f)?;
-                    Ok((f.into(), header_varint_len))
-                },
-                5 => {
-                    let f = f32::from_bits(value as u32);
-                    validate_canonical_f32(f)?;
-                    Ok((f.into(), header_varint_len))
-                },
-                9 => {
-                    let f = f64::from_bits(value);
-                    validate_canonical_f64(f)?;
-                    Ok((f.into(), header_varint_len))
-                },
-                _ => {
-                    match value {
-                        20 => Ok((CBOR::r#false(), header_varint_len)),
-                        21 => Ok((CBOR::r#true(), header_varint_len)),
-                        22 => Ok((CBOR::null(), header_varint_len)),
-                        _ => {
-                            bail!(CBORError::InvalidSimpleValue)
-                        },
-                    }
-                }
+            let simple = decode_simple_with_profile(value, header_varint_len, limits.profile())?;
+            let event = match simple {
+                Simple::Float(f) => Event::Float(f),
+                ref simple => Event::Simple(simple.clone()),
+            };
+            limits.validate(path, &event)?;
+            CBORCase::Simple(simple).into()
+        },
+    };
+    Ok(Decoded::Item(item))
+}
+
+/// Returns the text in `buf`, which must be valid UTF-8 in NFC form.
+pub(crate) fn validate_text(buf: &[u8]) -> Result<&str, CBORError> {
+    let string = str::from_utf8(buf).map_err(CBORError::from)?;
+    if !is_nfc(string) {
+        return Err(CBORError::NonCanonicalString);
+    }
+    Ok(string)
+}
+
+/// Decodes the simple value or floating point number with the given header
+/// value and length, which must be canonical.
+pub(crate) fn decode_simple(value: u64, header_varint_len: usize) -> Result<Simple, CBORError> {
+    let simple = match header_varint_len {
+        3 => {
+            let f = f16::from_bits(value as u16);
+            validate_canonical_f16(f)?;
+            Simple::Float(f.to_f64())
+        },
+        5 => {
+            let f = f32::from_bits(value as u32);
+            validate_canonical_f32(f)?;
+            Simple::Float(f as f64)
+        },
+        9 => {
+            let f = f64::from_bits(value);
+            validate_canonical_f64(f)?;
+            Simple::Float(f)
+        },
+        _ => {
+            match value {
+                20 => Simple::False,
+                21 => Simple::True,
+                22 => Simple::Null,
+                _ => return Err(CBORError::InvalidSimpleValue),
             }
         }
+    };
+    Ok(simple)
+}
+
+/// Decodes the simple value or floating point number with the given header
+/// value and length, which must follow the rules of `profile`.
+fn decode_simple_with_profile(value: u64, header_varint_len: usize, profile: EncodingProfile) -> Result<Simple, CBORError> {
+    if profile == EncodingProfile::Dcbor {
+        return decode_simple(value, header_varint_len);
+    }
+    let f = match header_varint_len {
+        3 => f16::from_bits(value as u16).to_f64(),
+        5 => f32::from_bits(value as u32) as f64,
+        9 => f64::from_bits(value),
+        _ => {
+            return match value {
+                20 => Ok(Simple::False),
+                21 => Ok(Simple::True),
+                22 => Ok(Simple::Null),
+                _ => SimpleValue::new(value as u8).map(Simple::Value),
+            }
+        },
+    };
+    if preferred_f64_header(f).as_slice().len() != header_varint_len {
+        return Err(CBORError::NonCanonicalNumeric);
     }
+    Ok(Simple::Float(f))
 }
@@ -0,0 +1,73 @@
+use crate::EncodingProfile;
+
+/// Limits applied while decoding CBOR from untrusted sources, and the encoding
+/// profile the data must follow.
+///
+/// The default options impose no limits and require dCBOR, matching the
+/// behavior of [`CBOR::try_from_data`](crate::CBOR::try_from_data). Exceeding
+/// any limit causes decoding to fail with the corresponding
+/// [`CBORError`](crate::CBORError) variant before the offending item is
+/// allocated.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let options = DecodeOptions {
+///     max_depth: 1,
+///     ..Default::default()
+/// };
+/// assert!(CBOR::try_from_data_with_options(hex_literal::hex!("8101"), &options).is_ok());
+/// assert!(CBOR::try_from_data_with_options(hex_literal::hex!("818101"), &options).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// The maximum nesting depth of any item.
+    ///
+    /// The top-level item has depth zero, and each enclosing array, map, or
+    /// tag adds one to the depth of the items it contains.
+    pub max_depth: usize,
+
+    /// The maximum number of elements in an array or entries in a map.
+    pub max_container_len: usize,
+
+    /// The maximum length in bytes of a byte string or text string.
+    pub max_string_len: usize,
+
+    /// The maximum total number of items in the decoded document.
+    ///
+    /// Every integer, string, simple value, array, map, and tag counts as one
+    /// item.
+    pub max_nodes: usize,
+
+    /// The maximum total number of bytes the decoded document may allocate.
+    ///
+    /// Each decoded item is charged the size of its in-memory node plus the
+    /// length of its string content. Map keys are charged again for their
+    /// encoded form, which the map retains for ordering.
+    pub max_allocated_bytes: usize,
+
+    /// The rules the data must follow.
+    ///
+    /// The lenient decoder ignores this, as it converts any well-formed CBOR
+    /// to dCBOR.
+    pub profile: EncodingProfile,
+}
+
+impl DecodeOptions {
+    /// Returns options that impose no limits.
+    pub const fn new() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_container_len: usize::MAX,
+            max_string_len: usize::MAX,
+            max_nodes: usize::MAX,
+            max_allocated_bytes: usize::MAX,
+            profile: EncodingProfile::Dcbor,
+        }
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
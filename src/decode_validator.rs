@@ -0,0 +1,61 @@
+import_stdlib!();
+
+use anyhow::Result;
+
+use crate::{CBORPath, Event};
+
+/// A check of decoded items against the rules of an application protocol,
+/// beyond those of the encoding profile.
+///
+/// The decoder calls [`validate`](Self::validate) for each item once it has
+/// been read and found to follow the encoding profile, but before it is built
+/// into the decoded tree, so that data the protocol forbids is rejected as
+/// soon as it is read. Arrays and maps are reported by their start, with their
+/// length, before any of their items, and are not reported again at their
+/// end. Tags are reported before the items they apply to. Keys and values of
+/// maps are reported with paths ending in [`KeyAt`](crate::PathElement::KeyAt)
+/// and [`Key`](crate::PathElement::Key) respectively.
+///
+/// An error returned by the validator ends decoding, and is located like any
+/// other decoding error. An error that is not a [`CBORError`](crate::CBORError)
+/// is reported as [`CBORError::Rejected`](crate::CBORError::Rejected) with the
+/// original error's message, while remaining available through
+/// `downcast_ref`.
+///
+/// Closures taking the path and the event implement this trait.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// // Map keys must be integers or text, and floats are not allowed.
+/// let mut validator = |path: &CBORPath, event: &Event<'_>| {
+///     let is_key = matches!(path.elements().last(), Some(PathElement::KeyAt(_)));
+///     match event {
+///         Event::Float(_) => anyhow::bail!("floats are not allowed"),
+///         Event::Unsigned(_) | Event::Negative(_) | Event::Text(_) => Ok(()),
+///         _ if is_key => anyhow::bail!("keys must be integers or text"),
+///         _ => Ok(()),
+///     }
+/// };
+/// let options = DecodeOptions::default();
+/// // {1: [2]}
+/// let cbor = CBOR::try_from_data_with_validator(hex_literal::hex!("a1 01 8102"), &options, &mut validator).unwrap();
+/// assert_eq!(cbor.diagnostic_flat(), "{1: [2]}");
+/// // {1: [2.5]}
+/// let error = CBOR::try_from_data_with_validator(hex_literal::hex!("a1 01 81f94100"), &options, &mut validator).unwrap_err();
+/// assert_eq!(error.to_string(), "the CBOR was rejected by a validator: floats are not allowed");
+/// assert_eq!(error.downcast_ref::<DecodeError>().unwrap().path().to_string(), "{1}.[0]");
+/// ```
+pub trait DecodeValidator {
+    /// Checks the item at `path` described by `event`, returning an error if
+    /// it is not allowed.
+    fn validate(&mut self, path: &CBORPath, event: &Event<'_>) -> Result<()>;
+}
+
+impl<F> DecodeValidator for F
+where
+    F: FnMut(&CBORPath, &Event<'_>) -> Result<()>,
+{
+    fn validate(&mut self, path: &CBORPath, event: &Event<'_>) -> Result<()> {
+        self(path, event)
+    }
+}
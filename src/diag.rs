@@ -1,8 +1,6 @@
 import_stdlib!();
 
-use crate::{tags_store::TagsStoreTrait, with_tags, CBORCase, CBOR};
-
-use super::string_util::flanked;
+use crate::{tags_store::TagsStoreTrait, with_tags, CBORCase, Tag, CBOR};
 
 /// Affordances for viewing CBOR in diagnostic notation.
 impl CBOR {
@@ -11,7 +9,7 @@ impl CBOR {
     /// Optionally annotates the output, e.g. formatting dates and adding names
     /// of known tags.
     pub fn diagnostic_opt(&self, annotate: bool, summarize: bool, flat: bool, tags: Option<&dyn TagsStoreTrait>) -> String {
-        self.diag_item(annotate, summarize, tags).format(annotate, flat)
+        self.diag_item(annotate, summarize, tags).format(flat)
     }
 
     /// Returns a representation of this CBOR in diagnostic notation.
@@ -43,184 +41,235 @@ impl CBOR {
     }
 
     fn diag_item(&self, annotate: bool, summarize: bool, tags: Option<&dyn TagsStoreTrait>) -> DiagItem {
-        match self.as_case() {
-            CBORCase::Unsigned(_) | CBORCase::Negative(_) | CBORCase::ByteString(_) |
-            CBORCase::Text(_) | CBORCase::Simple(_) => DiagItem::Item(format!("{}", self)),
-
-            CBORCase::Array(a) => {
-                let begin = "[".to_string();
-                let end = "]".to_string();
-                let items = a.iter().map(|x| x.diag_item(annotate, summarize, tags)).collect();
-                let is_pairs = false;
-                let comment = None;
-                DiagItem::Group(begin, end, items, is_pairs, comment)
-            },
-            CBORCase::Map(m) => {
-                let begin = "{".to_string();
-                let end = "}".to_string();
-                let items = m.iter().flat_map(|(key, value)| vec![
-                    key.diag_item(annotate, summarize, tags),
-                    value.diag_item(annotate, summarize, tags)
-                ]).collect();
-                let is_pairs = true;
-                let comment = None;
-                DiagItem::Group(begin, end, items, is_pairs, comment)
-            },
-            CBORCase::Tagged(tag, item) => {
-                if summarize {
-                    if let Some(tags) = tags {
-                        if let Some(summarizer) = tags.summarizer(tag.value()) {
-                            match summarizer(item.clone()) {
-                                Ok(summary) => return DiagItem::Item(summary),
-                                Err(error) => return DiagItem::Item(format!("<error: {}>", error)),
+        enum Task<'a> {
+            Visit(&'a CBOR),
+            Array(usize),
+            Map(usize),
+            Tagged(&'a Tag),
+        }
+
+        // Items are visited in pre-order, and each container is assembled
+        // from the results of its children once they have all been visited.
+        let mut tasks = vec![Task::Visit(self)];
+        let mut results: Vec<DiagItem> = Vec::new();
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Visit(cbor) => match cbor.as_case() {
+                    CBORCase::Unsigned(_) | CBORCase::Negative(_) | CBORCase::ByteString(_) |
+                    CBORCase::Text(_) | CBORCase::Simple(_) => results.push(DiagItem::Item(format!("{}", cbor))),
+
+                    CBORCase::Array(a) => {
+                        tasks.push(Task::Array(a.len()));
+                        tasks.extend(a.iter().rev().map(Task::Visit));
+                    },
+                    CBORCase::Map(m) => {
+                        tasks.push(Task::Map(m.len() * 2));
+                        for (key, value) in m.iter().rev() {
+                            tasks.push(Task::Visit(value));
+                            tasks.push(Task::Visit(key));
+                        }
+                    },
+                    CBORCase::Tagged(tag, item) => {
+                        if summarize {
+                            if let Some(tags) = tags {
+                                if let Some(summarizer) = tags.summarizer(tag.value()) {
+                                    match summarizer(item.clone()) {
+                                        Ok(summary) => results.push(DiagItem::Item(summary)),
+                                        Err(error) => results.push(DiagItem::Item(format!("<error: {}>", error))),
+                                    }
+                                    continue;
+                                }
                             }
                         }
-                    }
-                }
-                let diag_item = item.diag_item(annotate, summarize, tags);
-                let begin = tag.value().to_string() + "(";
-                let end = ")".to_string();
-                let items = vec![diag_item];
-                let is_pairs = false;
-                let comment = if annotate {
-                    tags.as_ref().and_then(|x| x.assigned_name_for_tag(tag))
-                } else {
-                    None
-                };
-                DiagItem::Group(begin, end, items, is_pairs, comment)
-            },
+                        tasks.push(Task::Tagged(tag));
+                        tasks.push(Task::Visit(item));
+                    },
+                },
+                Task::Array(len) => {
+                    let items = results.split_off(results.len() - len);
+                    let begin = "[".to_string();
+                    let end = "]".to_string();
+                    let is_pairs = false;
+                    let comment = None;
+                    results.push(DiagItem::Group(DiagGroup::new(begin, end, items, is_pairs, comment)));
+                },
+                Task::Map(len) => {
+                    let items = results.split_off(results.len() - len);
+                    let begin = "{".to_string();
+                    let end = "}".to_string();
+                    let is_pairs = true;
+                    let comment = None;
+                    results.push(DiagItem::Group(DiagGroup::new(begin, end, items, is_pairs, comment)));
+                },
+                Task::Tagged(tag) => {
+                    let diag_item = results.pop().unwrap();
+                    let begin = tag.value().to_string() + "(";
+                    let end = ")".to_string();
+                    let items = vec![diag_item];
+                    let is_pairs = false;
+                    let comment = if annotate {
+                        tags.as_ref().and_then(|x| x.assigned_name_for_tag(tag))
+                    } else {
+                        None
+                    };
+                    results.push(DiagItem::Group(DiagGroup::new(begin, end, items, is_pairs, comment)));
+                },
+            }
         }
+        results.pop().unwrap()
     }
 }
 
 #[derive(Debug)]
 enum DiagItem {
     Item(String),
-    Group(String, String, Vec<DiagItem>, bool, Option<String>),
+    Group(DiagGroup),
 }
 
-impl DiagItem {
-    fn format(&self, annotate: bool, flat: bool) -> String {
-        self.format_opt(0, "", annotate, flat)
+#[derive(Debug)]
+struct DiagGroup {
+    begin: String,
+    end: String,
+    items: Vec<DiagItem>,
+    is_pairs: bool,
+    comment: Option<String>,
+    /// The total length of all the strings in the group's items, computed
+    /// once at construction so that formatting need not recurse.
+    strings_len: usize,
+}
+
+impl DiagGroup {
+    fn new(begin: String, end: String, items: Vec<DiagItem>, is_pairs: bool, comment: Option<String>) -> Self {
+        let strings_len = items.iter().map(|item| item.total_strings_len()).sum();
+        Self { begin, end, items, is_pairs, comment, strings_len }
     }
 
-    fn format_opt(&self, level: usize, separator: &str, annotate: bool, flat: bool) -> String {
-        match self {
-            DiagItem::Item(string) => {
-                self.format_line(level, flat, string, separator, None)
-            },
-            DiagItem::Group(_, _, _, _, _) => {
-                if !flat && (self.contains_group() || self.total_strings_len() > 20 || self.greatest_strings_len() > 20) {
-                    self.multiline_composition(level, separator, annotate)
-                } else {
-                    self.single_line_composition(level, separator, flat)
-                }
-            },
-        }
+    fn greatest_strings_len(&self) -> usize {
+        self.items.iter().fold(0, |acc, item| { acc.max(item.total_strings_len()) })
     }
 
-    fn format_line(&self, level: usize, flat: bool, string: &str, separator: &str, comment: Option<&str>) -> String {
-        let indent = if flat { "".to_string() } else { " ".repeat(level * 4) };
-        let result = format!("{}{}{}", indent, string, separator);
-        if let Some(comment) = comment {
-            format!("{}   / {} /", result, comment)
+    fn contains_group(&self) -> bool {
+        self.items.iter().any(|x| x.is_group())
+    }
+
+    fn is_multiline(&self, flat: bool) -> bool {
+        !flat && (self.contains_group() || self.strings_len > 20 || self.greatest_strings_len() > 20)
+    }
+
+    fn item_separator(&self, index: usize) -> &'static str {
+        if index == self.items.len() - 1 {
+            ""
+        } else if self.is_pairs && index & 1 == 0 {
+            ":"
         } else {
-            result
+            ","
         }
     }
 
     fn single_line_composition(&self, level: usize, separator: &str, flat: bool) -> String {
-        let string: String;
-        let comment: Option<&str>;
-        match self {
-            DiagItem::Item(s) => {
-                string = s.clone();
-                comment = None;
-            },
-            DiagItem::Group(begin, end, items, is_pairs, comm) => {
-                let components: Vec<String> = items.iter().map(|item| {
-                    match item {
-                        DiagItem::Item(string) => string.clone(),
-                        DiagItem::Group(_, _, _, _, _) => {
-                            item.single_line_composition(level + 1, separator, flat)
+        enum Task<'a> {
+            Group(&'a DiagGroup, usize),
+            Str(&'a str),
+            String(String),
+        }
+
+        let mut result = String::new();
+        let mut tasks = vec![Task::Group(self, level)];
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Str(s) => result += s,
+                Task::String(s) => result += &s,
+                Task::Group(group, level) => {
+                    result += &DiagItem::indent(level, flat);
+                    result += &group.begin;
+                    if let Some(comment) = &group.comment {
+                        tasks.push(Task::String(format!("   / {} /", comment)));
+                    }
+                    tasks.push(Task::Str(separator));
+                    tasks.push(Task::Str(&group.end));
+                    let pair_separator = if group.is_pairs { ": " } else { ", " };
+                    let len = group.items.len();
+                    for (index, item) in group.items.iter().enumerate().rev() {
+                        if index != len - 1 {
+                            tasks.push(Task::Str(if index & 1 != 0 { ", " } else { pair_separator }));
+                        }
+                        match item {
+                            DiagItem::Item(string) => tasks.push(Task::Str(string)),
+                            DiagItem::Group(group) => tasks.push(Task::Group(group, level + 1)),
                         }
                     }
-                }).collect();
-                let pair_separator = if *is_pairs { ": " } else { ", " };
-                string = flanked(&Self::joined(&components, ", ", Some(pair_separator)), begin, end);
-                comment = comm.as_ref().map(|x| x.as_str());
-            },
-        };
-        self.format_line(level, flat, &string, separator, comment)
+                },
+            }
+        }
+        result
     }
+}
 
-    fn multiline_composition(&self, level: usize, separator: &str, annotate: bool) -> String {
-        match self {
-            DiagItem::Item(string) => string.to_owned(),
-            DiagItem::Group(begin, end, items, is_pairs, comment) => {
-                let mut lines: Vec<String> = vec![];
-                lines.push(self.format_line(level, false, begin, "", comment.as_ref().map(|x| x.as_str())));
-                for (index, item) in items.iter().enumerate() {
-                    let separator = if index == items.len() - 1 {
-                        ""
-                    } else if *is_pairs && index & 1 == 0 {
-                        ":"
+impl DiagItem {
+    fn format(&self, flat: bool) -> String {
+        enum Task<'a> {
+            Format(&'a DiagItem, usize, &'static str),
+            Line(String),
+        }
+
+        let mut lines: Vec<String> = vec![];
+        let mut tasks = vec![Task::Format(self, 0, "")];
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Line(line) => lines.push(line),
+                Task::Format(DiagItem::Item(string), level, separator) => {
+                    lines.push(Self::format_line(level, flat, string, separator, None));
+                },
+                Task::Format(DiagItem::Group(group), level, separator) => {
+                    if group.is_multiline(flat) {
+                        lines.push(Self::format_line(level, false, &group.begin, "", group.comment.as_deref()));
+                        tasks.push(Task::Line(Self::format_line(level, false, &group.end, separator, None)));
+                        for (index, item) in group.items.iter().enumerate().rev() {
+                            tasks.push(Task::Format(item, level + 1, group.item_separator(index)));
+                        }
                     } else {
-                        ","
-                    };
-                    lines.push(item.format_opt(level + 1, separator, annotate, false));
-                }
-                lines.push(self.format_line(level, false, end, separator, None));
-                lines.join("\n")
-            },
+                        lines.push(group.single_line_composition(level, separator, flat));
+                    }
+                },
+            }
         }
+        lines.join("\n")
     }
 
-    fn total_strings_len(&self) -> usize {
-        match self {
-            DiagItem::Item(string) => string.len(),
-            DiagItem::Group(_, _, items, _, _) => {
-                items.iter().fold(0, |acc, item| { acc + item.total_strings_len() })
-            },
+    fn indent(level: usize, flat: bool) -> String {
+        if flat { "".to_string() } else { " ".repeat(level * 4) }
+    }
+
+    fn format_line(level: usize, flat: bool, string: &str, separator: &str, comment: Option<&str>) -> String {
+        let result = format!("{}{}{}", Self::indent(level, flat), string, separator);
+        if let Some(comment) = comment {
+            format!("{}   / {} /", result, comment)
+        } else {
+            result
         }
     }
 
-    fn greatest_strings_len(&self) -> usize {
+    fn total_strings_len(&self) -> usize {
         match self {
             DiagItem::Item(string) => string.len(),
-            DiagItem::Group(_, _, items, _, _) => {
-                items.iter().fold(0, |acc, item| { acc.max(item.total_strings_len()) })
-            },
+            DiagItem::Group(group) => group.strings_len,
         }
     }
 
     fn is_group(&self) -> bool {
-        matches!(self, DiagItem::Group(_, _, _, _, _))
-    }
-
-    fn contains_group(&self) -> bool {
-        match self {
-            DiagItem::Item(_) => false,
-            DiagItem::Group(_, _, items, _, _) => {
-                items.iter().any(|x| x.is_group())
-            },
-        }
+        matches!(self, DiagItem::Group(_))
     }
+}
 
-    fn joined(elements: &[String], item_separator: &str, pair_separator: Option<&str>) -> String {
-        let pair_separator = pair_separator.unwrap_or(item_separator);
-        let mut result = String::new();
-        let len = elements.len();
-        for (index, item) in elements.iter().enumerate() {
-            result += item;
-            if index != len - 1 {
-                if index & 1 != 0 {
-                    result += item_separator;
-                } else {
-                    result += pair_separator;
-                }
+/// Drops nested groups with an explicit work stack, so that arbitrarily deep
+/// structures cannot overflow the call stack.
+impl Drop for DiagGroup {
+    fn drop(&mut self) {
+        let mut stack = mem::take(&mut self.items);
+        while let Some(item) = stack.pop() {
+            if let DiagItem::Group(mut group) = item {
+                stack.append(&mut group.items);
             }
         }
-        result
     }
 }
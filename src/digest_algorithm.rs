@@ -0,0 +1,32 @@
+import_stdlib!();
+
+/// An algorithm for computing digests of encoded data, such as a
+/// cryptographic hash, whose results can be memoized by
+/// [`CBOR::memoized_digest`](crate::CBOR::memoized_digest).
+///
+/// Memoized digests are told apart by the type implementing this trait, so
+/// each algorithm needs a type of its own, even where several produce digests
+/// of the same type.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// struct ByteSum;
+///
+/// impl DigestAlgorithm for ByteSum {
+///     type Output = u32;
+///
+///     fn digest(data: &[u8]) -> u32 {
+///         data.iter().map(|&b| b as u32).sum()
+///     }
+/// }
+///
+/// let cbor = CBOR::from([1, 2, 3]);
+/// assert_eq!(cbor.memoized_digest::<ByteSum>(), 0x83 + 1 + 2 + 3);
+/// ```
+pub trait DigestAlgorithm: 'static {
+    /// The type of the digests produced.
+    type Output: Clone + Send + Sync + 'static;
+
+    /// Returns the digest of `data`.
+    fn digest(data: &[u8]) -> Self::Output;
+}
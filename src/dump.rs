@@ -1,6 +1,8 @@
 import_stdlib!();
 
-use crate::{tags_store::TagsStoreTrait, with_tags, CBORCase, CBOR};
+use half::f16;
+
+use crate::{decode::parse_header_lenient, tags_store::TagsStoreTrait, with_tags, CBORCase, CBORError, DecodeError, Tag, CBOR};
 
 use super::{string_util::{sanitized, flanked}, varint::{EncodeVarInt, MajorType}};
 
@@ -20,14 +22,7 @@ impl CBOR {
         if !annotate {
             return self.hex()
         }
-        let items = self.dump_items(0, tags);
-        let note_column = items.iter().fold(0, |largest, item| {
-            largest.max(item.format_first_column().len())
-        });
-        // Round up to nearest multiple of 4
-        let note_column = ((note_column + 4) & !3) - 1;
-        let lines: Vec<_> = items.iter().map(|x| x.format(note_column)).collect();
-        lines.join("\n")
+        format_dump_items(&self.dump_items(0, tags))
     }
 
     /// Returns the encoded hexadecimal representation of this CBOR, with annotations.
@@ -38,84 +33,275 @@ impl CBOR {
     }
 
     fn dump_items(&self, level: usize, tags: Option<&dyn TagsStoreTrait>) -> Vec<DumpItem> {
-        match self.as_case() {
-            CBORCase::Unsigned(n) => vec!(DumpItem::new(level, vec!(self.to_cbor_data()), Some(format!("unsigned({})", n)))),
-            CBORCase::Negative(n) => vec!(DumpItem::new(level, vec!(self.to_cbor_data()), Some(format!("negative({})", -1 - (*n as i128))))),
-            CBORCase::ByteString(d) => {
-                let mut items = vec![
-                    DumpItem::new(level, vec!(d.len().encode_varint(MajorType::ByteString)), Some(format!("bytes({})", d.len())))
-                ];
-                if !d.is_empty() {
-                    let mut note: Option<String> = None;
-                    if let Ok(a) = str::from_utf8(d) {
-                        if let Some(b) = sanitized(a) {
-                            note = Some(flanked(&b, "\"", "\""));
+        // Items are visited in pre-order using an explicit stack, so
+        // arbitrarily deep structures cannot overflow the call stack.
+        let mut items = Vec::new();
+        let mut stack = vec![(self, level)];
+        while let Some((cbor, level)) = stack.pop() {
+            match cbor.as_case() {
+                CBORCase::Unsigned(n) => items.push(DumpItem::new(level, vec!(cbor.to_cbor_data()), Some(format!("unsigned({})", n)))),
+                CBORCase::Negative(n) => items.push(DumpItem::new(level, vec!(cbor.to_cbor_data()), Some(format!("negative({})", -1 - (*n as i128))))),
+                CBORCase::ByteString(d) => {
+                    items.push(DumpItem::new(level, vec!(d.len().encode_varint(MajorType::ByteString)), Some(format!("bytes({})", d.len()))));
+                    if !d.is_empty() {
+                        let mut note: Option<String> = None;
+                        if let Ok(a) = str::from_utf8(d) {
+                            if let Some(b) = sanitized(a) {
+                                note = Some(flanked(&b, "\"", "\""));
+                            }
+                        }
+                        items.push(DumpItem::new(level + 1, vec!(d.to_vec()), note));
+                    }
+                },
+                CBORCase::Text(s) => {
+                    let header = s.len().encode_varint(MajorType::Text);
+                    let header_data = vec![vec!(header[0]), header[1..].to_vec()];
+                    let utf8_data = s.as_bytes().to_vec();
+                    items.push(DumpItem::new(level, header_data, Some(format!("text({})", utf8_data.len()))));
+                    items.push(DumpItem::new(level + 1, vec![utf8_data], Some(flanked(s, "\"", "\""))));
+                },
+                CBORCase::Simple(v) => {
+                    let data = v.cbor_data();
+                    let note = format!("{}", v);
+                    items.push(DumpItem::new(level, vec![data], Some(note)));
+                },
+                CBORCase::Tagged(tag, item) => {
+                    let header = tag.value().encode_varint(MajorType::Tagged);
+                    let header_data = vec![vec!(header[0]), header[1..].to_vec()];
+                    let mut note_components: Vec<String> = vec![format!("tag({})", tag.value())];
+                    if let Some(tags) = tags {
+                        if let Some(name) = tags.assigned_name_for_tag(tag) {
+                            note_components.push(name);
                         }
                     }
-                    items.push(DumpItem::new(level + 1, vec!(d.to_vec()), note));
+                    let tag_note = note_components.join(" ");
+                    items.push(DumpItem::new(level, header_data, Some(tag_note)));
+                    stack.push((item, level + 1));
+                },
+                CBORCase::Array(array) => {
+                    let header = array.len().encode_varint(MajorType::Array);
+                    let header_data = vec![vec!(header[0]), header[1..].to_vec()];
+                    items.push(DumpItem::new(level, header_data, Some(format!("array({})", array.len()))));
+                    stack.extend(array.iter().rev().map(|x| (x, level + 1)));
+                },
+                CBORCase::Map(m) => {
+                    let header = m.len().encode_varint(MajorType::Map);
+                    let header_data = vec![vec!(header[0]), header[1..].to_vec()];
+                    items.push(DumpItem::new(level, header_data, Some(format!("map({})", m.len()))));
+                    for (key, value) in m.iter().rev() {
+                        stack.push((value, level + 1));
+                        stack.push((key, level + 1));
+                    }
+                },
+            }
+        }
+        items
+    }
+}
+
+/// Affordances for viewing where in the encoded data decoding failed.
+impl DecodeError {
+    /// Returns the annotated hexadecimal representation of the data that
+    /// failed to decode, with the line containing the failing item marked
+    /// with the error.
+    ///
+    /// The data is annotated as far as its structure allows, so this is
+    /// useful even for data that is not valid dCBOR.
+    pub fn hex_annotated(&self, data: impl AsRef<[u8]>) -> String {
+        with_tags!(|tags: &dyn TagsStoreTrait| {
+            self.hex_opt(data, Some(tags))
+        })
+    }
+
+    /// Returns the annotated hexadecimal representation of the data that
+    /// failed to decode, with the line containing the failing item marked
+    /// with the error.
+    ///
+    /// Optionally adds names of known tags.
+    pub fn hex_opt(&self, data: impl AsRef<[u8]>, tags: Option<&dyn TagsStoreTrait>) -> String {
+        let mark = (self.offset(), self.to_string());
+        hex_annotated_data(data.as_ref(), &[mark], tags)
+    }
+}
+
+/// Returns the annotated hexadecimal representation of arbitrary encoded
+/// data, which need not be valid dCBOR, appending each of the given messages
+/// to the note of the line containing its byte offset.
+///
+/// Headers are annotated without regard to whether they are minimally
+/// encoded. Any data that cannot be parsed, such as a truncated item, is
+/// shown on a final line of its own.
+pub(crate) fn hex_annotated_data(data: &[u8], marks: &[(usize, String)], tags: Option<&dyn TagsStoreTrait>) -> String {
+    let mut items = Vec::new();
+    let mut ranges = Vec::new();
+    // The number of items still expected by each enclosing container.
+    let mut remaining: Vec<u64> = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let level = remaining.len();
+        let start = pos;
+        let Ok((major_type, value, header_len)) = parse_header_lenient(&data[pos..]) else {
+            break;
+        };
+        let header = &data[pos..pos + header_len];
+        let split_header = vec![header[..1].to_vec(), header[1..].to_vec()];
+        let is_string = matches!(major_type, MajorType::ByteString | MajorType::Text);
+        let is_text = matches!(major_type, MajorType::Text);
+        let mut children = 0;
+        match major_type {
+            MajorType::Unsigned => items.push(DumpItem::new(level, vec![header.to_vec()], Some(format!("unsigned({})", value)))),
+            MajorType::Negative => items.push(DumpItem::new(level, vec![header.to_vec()], Some(format!("negative({})", -1 - (value as i128))))),
+            MajorType::ByteString | MajorType::Text => {
+                let content_start = pos + header_len;
+                if value > (data.len() - content_start) as u64 {
+                    break;
+                }
+                let content = &data[content_start..content_start + value as usize];
+                if !is_text {
+                    items.push(DumpItem::new(level, vec![header.to_vec()], Some(format!("bytes({})", value))));
+                } else {
+                    items.push(DumpItem::new(level, split_header, Some(format!("text({})", value))));
                 }
-                items
+                ranges.push(start..content_start);
+                if !content.is_empty() {
+                    let note = if is_text {
+                        Some(flanked(&String::from_utf8_lossy(content), "\"", "\""))
+                    } else {
+                        str::from_utf8(content).ok()
+                            .and_then(sanitized)
+                            .map(|s| flanked(&s, "\"", "\""))
+                    };
+                    items.push(DumpItem::new(level + 1, vec![content.to_vec()], note));
+                    ranges.push(content_start..content_start + content.len());
+                }
+                pos = content_start + content.len();
             },
-            CBORCase::Text(s) => {
-                let header = s.len().encode_varint(MajorType::Text);
-                let header_data = vec![vec!(header[0]), header[1..].to_vec()];
-                let utf8_data = s.as_bytes().to_vec();
-                vec![
-                    DumpItem::new(level, header_data, Some(format!("text({})", utf8_data.len()))),
-                    DumpItem::new(level + 1, vec![utf8_data], Some(flanked(s, "\"", "\"")))
-                ]
+            MajorType::Array => {
+                items.push(DumpItem::new(level, split_header, Some(format!("array({})", value))));
+                children = value;
             },
-            CBORCase::Simple(v) => {
-                let data = v.cbor_data();
-                let note = format!("{}", v);
-                vec![
-                    DumpItem::new(level, vec![data], Some(note))
-                ]
+            MajorType::Map => {
+                items.push(DumpItem::new(level, split_header, Some(format!("map({})", value))));
+                children = value.saturating_mul(2);
             },
-            CBORCase::Tagged(tag, item) => {
-                let header = tag.value().encode_varint(MajorType::Tagged);
-                let header_data = vec![vec!(header[0]), header[1..].to_vec()];
-                let mut note_components: Vec<String> = vec![format!("tag({})", tag.value())];
-                if let Some(tags) = tags {
-                    if let Some(name) = tags.assigned_name_for_tag(tag) {
-                        note_components.push(name);
-                    }
+            MajorType::Tagged => {
+                let mut note_components = vec![format!("tag({})", value)];
+                if let Some(name) = tags.and_then(|tags| tags.assigned_name_for_tag(&Tag::with_value(value))) {
+                    note_components.push(name);
                 }
-                let tag_note = note_components.join(" ");
-                vec![
-                    vec![
-                        DumpItem::new(level, header_data, Some(tag_note))
-                    ],
-                    item.dump_items(level + 1, tags)
-                ].into_iter().flatten().collect()
+                items.push(DumpItem::new(level, split_header, Some(note_components.join(" "))));
+                children = 1;
             },
-            CBORCase::Array(array) => {
-                let header = array.len().encode_varint(MajorType::Array);
-                let header_data = vec![vec!(header[0]), header[1..].to_vec()];
-                vec![
-                    vec![
-                        DumpItem::new(level, header_data, Some(format!("array({})", array.len())))
-                    ],
-                    array.iter().flat_map(|x| x.dump_items(level + 1, tags)).collect()
-                ].into_iter().flatten().collect()
-            },
-            CBORCase::Map(m) => {
-                let header = m.len().encode_varint(MajorType::Map);
-                let header_data = vec![vec!(header[0]), header[1..].to_vec()];
-                vec![
-                    vec![
-                        DumpItem::new(level, header_data, Some(format!("map({})", m.len())))
-                    ],
-                    m.iter().flat_map(|x| {
-                        vec![
-                            x.0.dump_items(level + 1, tags),
-                            x.1.dump_items(level + 1, tags)
-                        ].into_iter().flatten().collect::<Vec<DumpItem>>()
-                    }).collect()
-                ].into_iter().flatten().collect()
+            MajorType::Simple => {
+                let note = match (header[0], value) {
+                    (0xf9, v) => format!("{:?}", f16::from_bits(v as u16).to_f64()),
+                    (0xfa, v) => format!("{:?}", f32::from_bits(v as u32) as f64),
+                    (0xfb, v) => format!("{:?}", f64::from_bits(v)),
+                    (_, 20) => "false".to_string(),
+                    (_, 21) => "true".to_string(),
+                    (_, 22) => "null".to_string(),
+                    (_, 23) => "undefined".to_string(),
+                    (_, v) => format!("simple({})", v),
+                };
+                items.push(DumpItem::new(level, vec![header.to_vec()], Some(note)));
             },
         }
+        if !is_string {
+            pos += header_len;
+            ranges.push(start..pos);
+        }
+        if children > 0 {
+            remaining.push(children);
+        } else {
+            // Completing an item may complete its enclosing containers.
+            while let Some(count) = remaining.last_mut() {
+                *count -= 1;
+                if *count > 0 {
+                    break;
+                }
+                remaining.pop();
+            }
+        }
+    }
+    if pos < data.len() {
+        items.push(DumpItem::new(remaining.len(), vec![data[pos..].to_vec()], Some("malformed or truncated".to_string())));
+        ranges.push(pos..data.len());
     }
+    for (offset, message) in marks {
+        let index = ranges.iter()
+            .rposition(|range| range.start <= *offset)
+            .unwrap_or(0);
+        if let Some(item) = items.get_mut(index) {
+            item.note = Some(match &item.note {
+                Some(note) => format!("{} <-- {}", note, message),
+                None => format!("<-- {}", message),
+            });
+        }
+    }
+    format_dump_items(&items)
+}
+
+/// Parses hexadecimal text, such as an annotated dump produced by
+/// [`CBOR::hex_annotated`], into bytes.
+///
+/// Everything from a `#` to the end of its line is a comment and is ignored.
+/// The remaining digits may be broken up by whitespace, including line
+/// breaks and indentation, but only between whole bytes. Any other character,
+/// or a byte with only one digit, is reported as
+/// [`CBORError::InvalidHex`] with its 1-based line and column.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let data = dcbor::parse_hex("
+///     d9 0100     # tag(256)
+///        01       # unsigned(1)
+/// ").unwrap();
+/// assert_eq!(data, [0xd9, 0x01, 0x00, 0x01]);
+/// let error = dcbor::parse_hex("a1 0 1").unwrap_err();
+/// assert!(matches!(error, CBORError::InvalidHex(1, 4)));
+/// ```
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, CBORError> {
+    let mut data = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let content = line.split('#').next().unwrap_or_default();
+        let invalid = |index: usize| {
+            CBORError::InvalidHex(line_index + 1, content[..index].chars().count() + 1)
+        };
+        let mut token_start = None;
+        for (index, c) in content.char_indices().chain([(content.len(), ' ')]) {
+            if !c.is_whitespace() {
+                if !c.is_ascii_hexdigit() {
+                    return Err(invalid(index));
+                }
+                token_start.get_or_insert(index);
+            } else if let Some(start) = token_start.take() {
+                let token = &content.as_bytes()[start..index];
+                if token.len() % 2 != 0 {
+                    return Err(invalid(index - 1));
+                }
+                data.extend(token.chunks(2).map(|pair| hex_value(pair[0]) << 4 | hex_value(pair[1])));
+            }
+        }
+    }
+    Ok(data)
+}
+
+fn hex_value(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        _ => digit - b'A' + 10,
+    }
+}
+
+fn format_dump_items(items: &[DumpItem]) -> String {
+    let note_column = items.iter().fold(0, |largest, item| {
+        largest.max(item.format_first_column().len())
+    });
+    // Round up to nearest multiple of 4
+    let note_column = ((note_column + 4) & !3) - 1;
+    let lines: Vec<_> = items.iter().map(|x| x.format(note_column)).collect();
+    lines.join("\n")
 }
 
 #[derive(Debug)]
@@ -0,0 +1,207 @@
+import_stdlib!();
+
+#[cfg(feature = "std")]
+use std::io;
+
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::{float::preferred_f64_header, varint::{Header, MajorType}, CBORCase, EncodingProfile, Map, Simple, CBOR};
+
+/// A destination to which the encoder writes encoded data as it is produced.
+pub(crate) trait EncodeSink {
+    type Error;
+
+    fn put(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Puts the encoding of `text`, which is not in Unicode Normalization
+    /// Form C, once normalized.
+    fn put_nfc_text(&mut self, text: &str) -> Result<(), Self::Error> {
+        let nfc = text.nfc().collect::<String>();
+        self.put(Header::new(MajorType::Text, nfc.len() as u64).as_slice())?;
+        self.put(nfc.as_bytes())
+    }
+}
+
+impl EncodeSink for Vec<u8> {
+    type Error = core::convert::Infallible;
+
+    fn put(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+/// Counts the bytes of an encoding without storing them.
+pub(crate) struct LenSink(pub(crate) usize);
+
+impl EncodeSink for LenSink {
+    type Error = core::convert::Infallible;
+
+    fn put(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.0 += data.len();
+        Ok(())
+    }
+
+    fn put_nfc_text(&mut self, text: &str) -> Result<(), Self::Error> {
+        let len = text.nfc().map(char::len_utf8).sum::<usize>();
+        self.0 += Header::new(MajorType::Text, len as u64).as_slice().len() + len;
+        Ok(())
+    }
+}
+
+/// Feeds an encoding to a [`Hasher`](hash::Hasher) in blocks of a fixed
+/// size, so that the hash does not depend on how the encoding was divided as
+/// it was put.
+pub(crate) struct HashSink<'a, H: ?Sized> {
+    hasher: &'a mut H,
+    block: [u8; 64],
+    len: usize,
+}
+
+impl<'a, H: hash::Hasher + ?Sized> HashSink<'a, H> {
+    pub(crate) fn new(hasher: &'a mut H) -> Self {
+        Self { hasher, block: [0; 64], len: 0 }
+    }
+
+    /// Feeds the rest of the encoding to the hasher.
+    pub(crate) fn finish(self) {
+        self.hasher.write(&self.block[..self.len]);
+    }
+}
+
+impl<H: hash::Hasher + ?Sized> EncodeSink for HashSink<'_, H> {
+    type Error = core::convert::Infallible;
+
+    fn put(&mut self, mut data: &[u8]) -> Result<(), Self::Error> {
+        while !data.is_empty() {
+            let n = data.len().min(self.block.len() - self.len);
+            self.block[self.len..self.len + n].copy_from_slice(&data[..n]);
+            self.len += n;
+            data = &data[n..];
+            if self.len == self.block.len() {
+                self.hasher.write(&self.block);
+                self.len = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Feeds the dCBOR encoding of `root` to `hasher`.
+pub(crate) fn hash_encoding<H: hash::Hasher + ?Sized>(root: EncodeTask<'_>, hasher: &mut H) {
+    let mut sink = HashSink::new(hasher);
+    let Ok(()) = encode_to(root, EncodingProfile::Dcbor, &mut sink);
+    sink.finish();
+}
+
+/// Adapts an `io::Write` as a destination for the encoder.
+#[cfg(feature = "std")]
+pub(crate) struct WriteSink<'a, W: ?Sized>(pub(crate) &'a mut W);
+
+#[cfg(feature = "std")]
+impl<W: io::Write + ?Sized> EncodeSink for WriteSink<'_, W> {
+    type Error = io::Error;
+
+    fn put(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.0.write_all(data)
+    }
+}
+
+/// A pending unit of work for the non-recursive encoder.
+pub(crate) enum EncodeTask<'a> {
+    Item(&'a CBOR),
+    Map(&'a Map),
+    Data(&'a [u8]),
+    OwnedData(Vec<u8>),
+}
+
+/// Appends the encoding of `root` under `profile` to `buf`.
+pub(crate) fn encode(root: EncodeTask<'_>, profile: EncodingProfile, buf: &mut Vec<u8>) {
+    let Ok(()) = encode_to(root, profile, buf);
+}
+
+/// Returns the length of the encoding of `root` under `profile`.
+pub(crate) fn encoded_len(root: EncodeTask<'_>, profile: EncodingProfile) -> usize {
+    let mut sink = LenSink(0);
+    let Ok(()) = encode_to(root, profile, &mut sink);
+    sink.0
+}
+
+/// Writes the encoding of `root` under `profile` to `sink`, using an explicit
+/// work stack rather than recursion.
+///
+/// Each header and each string is written directly as it is reached, so
+/// nothing is copied from one buffer to another, and the only allocations are
+/// for the work stack, for text that must be normalized, and, under profiles
+/// other than dCBOR, for the encodings of map keys.
+///
+/// Under dCBOR, items with memoized encodings are copied from them.
+pub(crate) fn encode_to<S: EncodeSink>(root: EncodeTask<'_>, profile: EncodingProfile, sink: &mut S) -> Result<(), S::Error> {
+    let mut stack = vec![root];
+    while let Some(task) = stack.pop() {
+        let cbor = match task {
+            EncodeTask::Item(cbor) if profile == EncodingProfile::Dcbor => {
+                if let Some(data) = cbor.memoized_data() {
+                    sink.put(data)?;
+                    continue;
+                }
+                cbor
+            },
+            EncodeTask::Item(cbor) => cbor,
+            EncodeTask::Map(map) => {
+                sink.put(Header::new(MajorType::Map, map.len() as u64).as_slice())?;
+                if profile == EncodingProfile::Dcbor {
+                    for (key_data, value) in map.encoded_iter().rev() {
+                        stack.push(EncodeTask::Item(value));
+                        stack.push(EncodeTask::Data(key_data));
+                    }
+                } else {
+                    // The map is ordered by the keys' dCBOR encodings, which
+                    // may differ from their encodings under this profile.
+                    let mut entries: Vec<_> = map.iter()
+                        .map(|(key, value)| (key.to_cbor_data_with_profile(profile), value))
+                        .collect();
+                    entries.sort_by(|a, b| a.0.cmp(&b.0));
+                    for (key_data, value) in entries.into_iter().rev() {
+                        stack.push(EncodeTask::Item(value));
+                        stack.push(EncodeTask::OwnedData(key_data));
+                    }
+                }
+                continue;
+            },
+            EncodeTask::Data(data) => {
+                sink.put(data)?;
+                continue;
+            },
+            EncodeTask::OwnedData(data) => {
+                sink.put(&data)?;
+                continue;
+            },
+        };
+        match cbor.as_case() {
+            CBORCase::Unsigned(x) => sink.put(Header::new(MajorType::Unsigned, *x).as_slice())?,
+            CBORCase::Negative(x) => sink.put(Header::new(MajorType::Negative, *x).as_slice())?,
+            CBORCase::ByteString(x) => {
+                sink.put(Header::new(MajorType::ByteString, x.len() as u64).as_slice())?;
+                sink.put(x.data())?;
+            },
+            CBORCase::Text(x) if profile == EncodingProfile::Dcbor && !is_nfc(x) => sink.put_nfc_text(x)?,
+            CBORCase::Text(x) => {
+                sink.put(Header::new(MajorType::Text, x.len() as u64).as_slice())?;
+                sink.put(x.as_bytes())?;
+            },
+            CBORCase::Array(x) => {
+                sink.put(Header::new(MajorType::Array, x.len() as u64).as_slice())?;
+                stack.extend(x.iter().rev().map(EncodeTask::Item));
+            },
+            CBORCase::Map(x) => stack.push(EncodeTask::Map(x)),
+            CBORCase::Tagged(tag, item) => {
+                sink.put(Header::new(MajorType::Tagged, tag.value()).as_slice())?;
+                stack.push(EncodeTask::Item(item));
+            },
+            CBORCase::Simple(Simple::Float(x)) if profile != EncodingProfile::Dcbor => sink.put(preferred_f64_header(*x).as_slice())?,
+            CBORCase::Simple(x) => sink.put(x.header().as_slice())?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,270 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{decode::{decode_cbor, parse_header_lenient}, validate::{skip_item, validate}, varint::MajorType, CBORError, Tag, CBOR};
+
+/// A view of a single dCBOR item in its encoded form, supporting random access
+/// without decoding.
+///
+/// Navigating into an array or map skips over the encodings of the items
+/// before the one selected, by reading only their headers, and only the item
+/// finally selected need be decoded with [`to_cbor`](Self::to_cbor). Map
+/// lookups compare encoded keys, stopping as soon as they pass the key
+/// sought, since dCBOR map keys are sorted by their encodings. For repeated
+/// lookups into the same array or map, build an [`EncodedIndex`] with
+/// [`index`](Self::index).
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// // [1, {"a": [true], "b": 2}]
+/// let data = hex_literal::hex!("82 01 a2 6161 81f5 6162 02");
+/// let encoded = EncodedCBOR::try_from_data(&data).unwrap();
+/// let map = encoded.array_item(1).unwrap().unwrap();
+/// let value = map.map_value("b").unwrap().unwrap();
+/// assert_eq!(value.data(), [0x02]);
+/// assert_eq!(value.to_cbor(), CBOR::from(2));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EncodedCBOR<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> EncodedCBOR<'a> {
+    /// Creates a view of the item encoded in `data`.
+    ///
+    /// The data must be a single item of valid dCBOR, which is checked with
+    /// [`validate`](crate::validate), without decoding it.
+    pub fn try_from_data(data: &'a [u8]) -> Result<Self> {
+        validate(data)?;
+        Ok(Self { data })
+    }
+
+    /// Returns the encoding of this item.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Decodes this item.
+    pub fn to_cbor(&self) -> CBOR {
+        decode_cbor(self.data).expect("the data was validated")
+    }
+
+    /// Returns the number of elements, if this is an array.
+    pub fn array_len(&self) -> Result<usize> {
+        match self.header() {
+            (MajorType::Array, len, _) => Ok(len as usize),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+
+    /// Returns the number of entries, if this is a map.
+    pub fn map_len(&self) -> Result<usize> {
+        match self.header() {
+            (MajorType::Map, len, _) => Ok(len as usize),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+
+    /// Returns an iterator over the elements of this array.
+    pub fn array_items(&self) -> Result<EncodedItems<'a>> {
+        let len = self.array_len()?;
+        Ok(EncodedItems { data: self.content(), remaining: len })
+    }
+
+    /// Returns an iterator over the entries of this map, in the order of
+    /// their keys' encodings.
+    pub fn map_entries(&self) -> Result<EncodedEntries<'a>> {
+        let len = self.map_len()?;
+        Ok(EncodedEntries(EncodedItems { data: self.content(), remaining: len * 2 }))
+    }
+
+    /// Returns the element at `index` of this array, or `None` if the array
+    /// is not that long.
+    pub fn array_item(&self, index: usize) -> Result<Option<EncodedCBOR<'a>>> {
+        Ok(self.array_items()?.nth(index))
+    }
+
+    /// Returns the value associated with `key` in this map, or `None` if the
+    /// key is not present.
+    pub fn map_value(&self, key: impl Into<CBOR>) -> Result<Option<EncodedCBOR<'a>>> {
+        let key_data = key.into().to_cbor_data();
+        for (key, value) in self.map_entries()? {
+            match key.data.cmp(key_data.as_slice()) {
+                cmp::Ordering::Less => {},
+                cmp::Ordering::Equal => return Ok(Some(value)),
+                cmp::Ordering::Greater => break,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the tag and content of this tagged value.
+    pub fn tagged_value(&self) -> Result<(Tag, EncodedCBOR<'a>)> {
+        match self.header() {
+            (MajorType::Tagged, tag, _) => Ok((Tag::with_value(tag), EncodedCBOR { data: self.content() })),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+
+    /// Builds an index of the elements of this array or the entries of this
+    /// map, so that they can be found without skipping over those before
+    /// them.
+    pub fn index(&self) -> Result<EncodedIndex<'a>> {
+        let (major_type, len, _) = self.header();
+        let (is_map, count) = match major_type {
+            MajorType::Array => (false, len as usize),
+            MajorType::Map => (true, len as usize * 2),
+            _ => bail!(CBORError::WrongType),
+        };
+        let start = self.data.len() - self.content().len();
+        let mut offsets = Vec::with_capacity(count + 1);
+        offsets.push(start);
+        let items = EncodedItems { data: self.content(), remaining: count };
+        let mut end = start;
+        for item in items {
+            end += item.data.len();
+            offsets.push(end);
+        }
+        Ok(EncodedIndex { item: *self, is_map, offsets })
+    }
+
+    fn header(&self) -> (MajorType, u64, usize) {
+        parse_header_lenient(self.data).expect("the data was validated")
+    }
+
+    /// Returns the encoding of the items within this one.
+    fn content(&self) -> &'a [u8] {
+        &self.data[self.header().2..]
+    }
+}
+
+/// An iterator over the elements of an encoded array.
+#[derive(Debug, Clone)]
+pub struct EncodedItems<'a> {
+    data: &'a [u8],
+    remaining: usize,
+}
+
+impl<'a> Iterator for EncodedItems<'a> {
+    type Item = EncodedCBOR<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let len = skip_item(self.data).expect("the data was validated");
+        let (item, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(EncodedCBOR { data: item })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for EncodedItems<'_> {}
+
+/// An iterator over the entries of an encoded map.
+#[derive(Debug, Clone)]
+pub struct EncodedEntries<'a>(EncodedItems<'a>);
+
+impl<'a> Iterator for EncodedEntries<'a> {
+    type Item = (EncodedCBOR<'a>, EncodedCBOR<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.0.next()?, self.0.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.remaining / 2;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for EncodedEntries<'_> {}
+
+/// The offsets of the elements of an encoded array or the entries of an
+/// encoded map, for fast repeated lookups.
+///
+/// Elements are found by position in constant time, and map values by binary
+/// search on their keys' encodings.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let cbor = CBOR::from((0..1000).collect::<Vec<u32>>());
+/// let data = cbor.to_cbor_data();
+/// let index = EncodedCBOR::try_from_data(&data).unwrap().index().unwrap();
+/// assert_eq!(index.len(), 1000);
+/// assert_eq!(index.array_item(500).unwrap().unwrap().to_cbor(), CBOR::from(500));
+/// assert!(index.map_value(1).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedIndex<'a> {
+    item: EncodedCBOR<'a>,
+    is_map: bool,
+    offsets: Vec<usize>,
+}
+
+impl<'a> EncodedIndex<'a> {
+    /// Returns the indexed array or map.
+    pub fn item(&self) -> EncodedCBOR<'a> {
+        self.item
+    }
+
+    /// Returns the number of elements in the array or entries in the map.
+    pub fn len(&self) -> usize {
+        let count = self.offsets.len() - 1;
+        if self.is_map { count / 2 } else { count }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the element at `index` of the array, or `None` if the array is
+    /// not that long.
+    pub fn array_item(&self, index: usize) -> Result<Option<EncodedCBOR<'a>>> {
+        if self.is_map {
+            bail!(CBORError::WrongType);
+        }
+        Ok((index < self.len()).then(|| self.child(index)))
+    }
+
+    /// Returns the entry at `index` of the map, in the order of the keys'
+    /// encodings, or `None` if the map is not that long.
+    pub fn map_entry(&self, index: usize) -> Result<Option<(EncodedCBOR<'a>, EncodedCBOR<'a>)>> {
+        if !self.is_map {
+            bail!(CBORError::WrongType);
+        }
+        Ok((index < self.len()).then(|| (self.child(index * 2), self.child(index * 2 + 1))))
+    }
+
+    /// Returns the value associated with `key` in the map, or `None` if the
+    /// key is not present.
+    pub fn map_value(&self, key: impl Into<CBOR>) -> Result<Option<EncodedCBOR<'a>>> {
+        if !self.is_map {
+            bail!(CBORError::WrongType);
+        }
+        let key_data = key.into().to_cbor_data();
+        let mut low = 0;
+        let mut high = self.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.child(mid * 2).data.cmp(key_data.as_slice()) {
+                cmp::Ordering::Less => low = mid + 1,
+                cmp::Ordering::Equal => return Ok(Some(self.child(mid * 2 + 1))),
+                cmp::Ordering::Greater => high = mid,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the child item at `index`, counting keys and values
+    /// separately for maps.
+    fn child(&self, index: usize) -> EncodedCBOR<'a> {
+        EncodedCBOR { data: &self.item.data[self.offsets[index]..self.offsets[index + 1]] }
+    }
+}
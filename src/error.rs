@@ -1,9 +1,9 @@
 import_stdlib!();
 
-use crate::tag::Tag;
+use crate::{tag::Tag, CBORPath};
 
 /// An error encountered while decoding or parsing CBOR.
-#[derive(Debug, ThisError)]
+#[derive(Debug, Clone, ThisError)]
 pub enum CBORError {
     #[error("early end of CBOR data")]
     Underrun,
@@ -32,6 +32,12 @@ pub enum CBORError {
     #[error("the decoded CBOR map has a duplicate key")]
     DuplicateMapKey,
 
+    #[error("a CBOR map has keys that are equal once canonicalized but have different values")]
+    ConflictingMapKey,
+
+    #[error("an indefinite-length CBOR item was malformed")]
+    MalformedIndefiniteLength,
+
     #[error("missing CBOR map key")]
     MissingMapKey,
 
@@ -43,6 +49,36 @@ pub enum CBORError {
 
     #[error("expected CBOR tag {0}, but got {1}")]
     WrongTag(Tag, Tag),
+
+    #[error("no decoder is registered for CBOR tag {0}")]
+    UnregisteredTag(Tag),
+
+    #[error("the CBOR nesting depth exceeds the limit of {0}")]
+    DepthLimitExceeded(usize),
+
+    #[error("a CBOR array or map has more elements than the limit of {0}")]
+    ContainerLenLimitExceeded(usize),
+
+    #[error("a CBOR string is longer than the limit of {0} bytes")]
+    StringLenLimitExceeded(usize),
+
+    #[error("the CBOR contains more items than the limit of {0}")]
+    NodeLimitExceeded(usize),
+
+    #[error("decoding the CBOR would allocate more than the limit of {0} bytes")]
+    AllocationLimitExceeded(usize),
+
+    #[error("an encoded CBOR item is longer than the limit of {0} bytes")]
+    ItemLenLimitExceeded(usize),
+
+    #[error("the written CBOR ends with {0} arrays, maps, or tags still waiting for items")]
+    IncompleteContainer(usize),
+
+    #[error("the CBOR was rejected by a validator: {0}")]
+    Rejected(String),
+
+    #[error("invalid hexadecimal at line {0}, column {1}")]
+    InvalidHex(usize, usize),
 }
 
 impl From<str::Utf8Error> for CBORError {
@@ -50,3 +86,61 @@ impl From<str::Utf8Error> for CBORError {
         CBORError::InvalidString(err)
     }
 }
+
+/// The location within the encoded data at which decoding failed.
+///
+/// Errors returned by the decoder carry this as context, so it can be
+/// recovered with `downcast_ref::<DecodeError>()`, while
+/// `downcast_ref::<CBORError>()` continues to return the underlying error. It
+/// displays as the underlying error.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let error = CBOR::try_from_hex("82a2626e6101000000").unwrap_err();
+/// let decode_error = error.downcast_ref::<DecodeError>().unwrap();
+/// assert_eq!(decode_error.offset(), 6);
+/// assert_eq!(decode_error.path().to_string(), "[0].key(1)");
+/// assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+/// ```
+#[derive(Debug, Clone, ThisError)]
+#[error("{error}")]
+pub struct DecodeError {
+    error: CBORError,
+    offset: usize,
+    path: CBORPath,
+}
+
+impl DecodeError {
+    pub fn new(error: CBORError, offset: usize, path: CBORPath) -> Self {
+        Self { error, offset, path }
+    }
+
+    /// Returns the underlying error.
+    pub fn error(&self) -> &CBORError {
+        &self.error
+    }
+
+    /// Returns the absolute byte offset of the header of the item at which
+    /// decoding failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the structural path of the item at which decoding failed.
+    pub fn path(&self) -> &CBORPath {
+        &self.path
+    }
+
+    /// Adds `base` to the offset, for data that was decoded starting `base`
+    /// bytes into a longer stream.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn rebase(&mut self, base: usize) {
+        self.offset += base;
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::error::Error for CBORError { }
+
+#[cfg(not(feature = "std"))]
+impl core::error::Error for DecodeError { }
@@ -0,0 +1,248 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{bignum::{is_bignum_tag, validate_bignum}, decode::{decode_simple, located, parse_bytes, read_header, validate_text, SliceSource}, varint::MajorType, CBORError, CBORPath, PathElement, Simple, TagValue};
+
+/// An event produced by [`Events`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    /// An unsigned integer.
+    Unsigned(u64),
+    /// A negative integer `n`, reported as `-1 - n` as it is encoded.
+    Negative(u64),
+    /// A byte string.
+    Bytes(&'a [u8]),
+    /// A text string.
+    Text(&'a str),
+    /// The start of an array with the given number of elements.
+    StartArray(usize),
+    /// The start of a map with the given number of entries, which follow as
+    /// alternating keys and values.
+    StartMap(usize),
+    /// A tag, which applies to the single item that follows it.
+    Tag(TagValue),
+    /// A simple value other than a floating point number, which in dCBOR is
+    /// `false`, `true`, or `null`.
+    Simple(Simple),
+    /// A floating point number.
+    Float(f64),
+    /// The end of the innermost array or map.
+    End,
+}
+
+/// A container whose items are still being reported.
+enum Frame {
+    Array { len: usize, index: usize },
+    Map { len: usize, index: usize, entry_start: usize, key: Option<ops::Range<usize>>, previous_key: Option<ops::Range<usize>> },
+    Tagged(TagValue),
+}
+
+impl Frame {
+    fn is_complete(&self) -> bool {
+        match self {
+            Frame::Array { len, index } | Frame::Map { len, index, .. } => index == len,
+            Frame::Tagged(_) => false,
+        }
+    }
+}
+
+/// A pull parser that reports the structure of encoded dCBOR as a sequence
+/// of [`Event`]s, without building a [`CBOR`](crate::CBOR) tree.
+///
+/// The data is held to the same deterministic encoding rules as
+/// [`CBOR::try_from_data`](crate::CBOR::try_from_data), with each violation
+/// reported as an error when the parser reaches it. Events before the error
+/// have already been reported, so callers that need an all-or-nothing result
+/// should not act on them until the iterator has finished. Iteration ends
+/// after the first error.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// // [{"n": 1}, {"n": 2}]
+/// let data = hex_literal::hex!("82a1616e01a1616e02");
+/// let mut sum = 0;
+/// let mut is_n = false;
+/// for event in Events::new(&data) {
+///     match event.unwrap() {
+///         Event::Text(key) => is_n = key == "n",
+///         Event::Unsigned(n) if is_n => sum += n,
+///         _ => {},
+///     }
+/// }
+/// assert_eq!(sum, 3);
+/// ```
+pub struct Events<'a> {
+    data: &'a [u8],
+    pos: usize,
+    stack: Vec<Frame>,
+    started: bool,
+    finished: bool,
+}
+
+impl<'a> Events<'a> {
+    /// Creates a parser for the single dCBOR item encoded in `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0, stack: Vec::new(), started: false, finished: false }
+    }
+
+    /// Returns the byte offset at which the next event begins, or after an
+    /// error, the offset of the item at which it occurred.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the number of arrays, maps, and tags enclosing the next event.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn path(&self) -> CBORPath {
+        CBORPath::new(self.stack.iter().map(|frame| {
+            match frame {
+                Frame::Array { index, .. } => PathElement::Index(*index),
+                Frame::Map { key: None, index, .. } => PathElement::KeyAt(*index),
+                Frame::Map { key: Some(key), .. } => PathElement::Key(self.data[key.clone()].to_vec()),
+                Frame::Tagged(tag) => PathElement::Tag(*tag),
+            }
+        }).collect::<Vec<_>>())
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event<'a>>> {
+        if self.stack.last().is_some_and(Frame::is_complete) {
+            self.stack.pop();
+            self.complete_item()?;
+            return Ok(Some(Event::End));
+        }
+        if self.stack.is_empty() && self.started {
+            let remaining = self.data.len() - self.pos;
+            if remaining > 0 {
+                bail!(CBORError::UnusedData(remaining));
+            }
+            return Ok(None);
+        }
+        self.started = true;
+        let mut source = SliceSource { data: self.data, pos: self.pos };
+        let (major_type, value, header_varint_len) = read_header(&mut source)?;
+        // The content of a bignum must be a byte string.
+        let is_bignum = matches!(self.stack.last(), Some(Frame::Tagged(tag)) if is_bignum_tag(*tag));
+        if is_bignum && !matches!(major_type, MajorType::ByteString) {
+            bail!(CBORError::WrongType);
+        }
+        let remaining = (self.data.len() - source.pos) as u64;
+        let event = match major_type {
+            MajorType::Unsigned => Event::Unsigned(value),
+            MajorType::Negative => Event::Negative(value),
+            MajorType::ByteString | MajorType::Text => {
+                if value > remaining {
+                    bail!(CBORError::Underrun);
+                }
+                let content = parse_bytes(&self.data[source.pos..], value as usize)?;
+                source.pos += content.len();
+                if matches!(major_type, MajorType::ByteString) {
+                    if is_bignum {
+                        validate_bignum(content)?;
+                    }
+                    Event::Bytes(content)
+                } else {
+                    Event::Text(validate_text(content)?)
+                }
+            },
+            MajorType::Array => {
+                // Every item occupies at least one byte.
+                if value > remaining {
+                    bail!(CBORError::Underrun);
+                }
+                self.pos = source.pos;
+                self.stack.push(Frame::Array { len: value as usize, index: 0 });
+                return Ok(Some(Event::StartArray(value as usize)));
+            },
+            MajorType::Map => {
+                // Every entry occupies at least two bytes.
+                if value > remaining / 2 {
+                    bail!(CBORError::Underrun);
+                }
+                self.pos = source.pos;
+                self.stack.push(Frame::Map { len: value as usize, index: 0, entry_start: self.pos, key: None, previous_key: None });
+                return Ok(Some(Event::StartMap(value as usize)));
+            },
+            MajorType::Tagged => {
+                self.pos = source.pos;
+                self.stack.push(Frame::Tagged(value));
+                return Ok(Some(Event::Tag(value)));
+            },
+            MajorType::Simple => match decode_simple(value, header_varint_len)? {
+                Simple::Float(f) => Event::Float(f),
+                simple => Event::Simple(simple),
+            },
+        };
+        self.pos = source.pos;
+        self.complete_item()?;
+        Ok(Some(event))
+    }
+
+    /// Records that the item ending at the current position is complete,
+    /// completing any tags that enclose it.
+    fn complete_item(&mut self) -> Result<()> {
+        let pos = self.pos;
+        loop {
+            match self.stack.last_mut() {
+                None => return Ok(()),
+                Some(Frame::Array { index, .. }) => {
+                    *index += 1;
+                    return Ok(());
+                },
+                Some(Frame::Map { index, entry_start, key, previous_key, .. }) => {
+                    match key.take() {
+                        None => {
+                            let key_range = *entry_start..pos;
+                            if let Some(previous_key) = previous_key {
+                                let previous = &self.data[previous_key.clone()];
+                                let current = &self.data[key_range.clone()];
+                                if previous >= current {
+                                    // Report the error at the start of the key.
+                                    self.pos = *entry_start;
+                                    if previous == current {
+                                        bail!(CBORError::DuplicateMapKey);
+                                    }
+                                    bail!(CBORError::MisorderedMapKey);
+                                }
+                            }
+                            *key = Some(key_range);
+                        },
+                        Some(key_range) => {
+                            *previous_key = Some(key_range);
+                            *index += 1;
+                            *entry_start = pos;
+                        },
+                    }
+                    return Ok(());
+                },
+                Some(Frame::Tagged(_)) => {
+                    self.stack.pop();
+                },
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<Event<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.next_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            },
+            Err(error) => {
+                self.finished = true;
+                Some(Err(located(error, self.pos, self.path())))
+            },
+        }
+    }
+}
@@ -3,11 +3,14 @@ import_stdlib!();
 use half::f16;
 use anyhow::{bail, Result, Error};
 
-use crate::{int::From64, CBORCase, CBORError, ExactFrom, Simple, CBOR};
+use crate::{CBORCase, CBORError, CBORRef, CBORRefCase, ExactFrom, Simple, CBOR};
 
-use super::varint::{EncodeVarInt, MajorType};
+use super::varint::{Header, MajorType};
 
-static CBOR_NAN: [u8; 3] = [0xf9, 0x7e, 0x00];
+/// Returns the header of the only NaN allowed in dCBOR, `f97e00`.
+fn nan_header() -> Header {
+    Header::with_len(MajorType::Simple, 0x7e00, 3)
+}
 
 impl From<f64> for CBOR {
     fn from(value: f64) -> Self {
@@ -26,36 +29,51 @@ impl From<f64> for CBOR {
     }
 }
 
-pub fn f64_cbor_data(value: f64) -> Vec<u8> {
+pub(crate) fn f64_header(value: f64) -> Header {
     let n = value;
     let f = n as f32;
     if f as f64 == n {
-        return f32_cbor_data(f);
+        return f32_header(f);
     }
     if n < 0.0f64 {
         if let Some(n) = i128::exact_from_f64(n) {
             if let Some(i) = u64::exact_from_i128(-1 - n) {
-                let cbor: CBOR = CBORCase::Negative(i).into();
-                return cbor.to_cbor_data();
+                return Header::new(MajorType::Negative, i);
             }
         }
     }
     if let Some(i) = u64::exact_from_f64(n) {
-        return i.cbor_data();
+        return Header::new(MajorType::Unsigned, i);
     }
     if value.is_nan() {
-        return CBOR_NAN.to_vec();
+        return nan_header();
+    }
+    Header::with_len(MajorType::Simple, n.to_bits(), 9)
+}
+
+/// Returns the shortest encoding of `value` as a floating point number that
+/// preserves it exactly, including the sign of a zero and the payload of a
+/// NaN, as required by preferred serialization.
+pub(crate) fn preferred_f64_header(value: f64) -> Header {
+    let bits = value.to_bits();
+    let half = f16::from_f64(value);
+    if half.to_f64().to_bits() == bits {
+        return Header::with_len(MajorType::Simple, half.to_bits() as u64, 3);
+    }
+    let single = value as f32;
+    if (single as f64).to_bits() == bits {
+        return Header::with_len(MajorType::Simple, single.to_bits() as u64, 5);
     }
-    n.to_bits().encode_int(MajorType::Simple)
+    Header::with_len(MajorType::Simple, bits, 9)
 }
 
-pub(crate) fn validate_canonical_f64(n: f64) -> Result<()> {
+pub(crate) fn validate_canonical_f64(n: f64) -> Result<(), CBORError> {
     if
         n == n as f32 as f64 ||
         n == n as i64 as f64 ||
         n.is_nan()
     {
-        bail!(CBORError::NonCanonicalNumeric);
+        return Err(CBORError::NonCanonicalNumeric);
     }
     Ok(())
 }
@@ -100,34 +118,33 @@ impl From<f32> for CBOR {
     }
 }
 
-pub fn f32_cbor_data(value: f32) -> Vec<u8> {
+pub(crate) fn f32_header(value: f32) -> Header {
     let n = value;
     let f = f16::from_f32(n);
     if f.to_f32() == n {
-        return f16_cbor_data(f);
+        return f16_header(f);
     }
     if n < 0.0f32 {
         if let Some(i) = u64::exact_from_f32(-1f32 - n) {
-            let cbor: CBOR = CBORCase::Negative(i).into();
-            return cbor.to_cbor_data();
+            return Header::new(MajorType::Negative, i);
         }
     }
     if let Some(i) = u32::exact_from_f32(n) {
-        return i.cbor_data();
+        return Header::new(MajorType::Unsigned, i as u64);
     }
     if value.is_nan() {
-        return CBOR_NAN.to_vec();
+        return nan_header();
     }
-    n.to_bits().encode_int(MajorType::Simple)
+    Header::with_len(MajorType::Simple, n.to_bits() as u64, 5)
 }
 
-pub(crate) fn validate_canonical_f32(n: f32) -> Result<()> {
+pub(crate) fn validate_canonical_f32(n: f32) -> Result<(), CBORError> {
     if
         n == f16::from_f32(n).to_f32() ||
         n == n as i32 as f32 ||
         n.is_nan()
     {
-        bail!(CBORError::NonCanonicalNumeric);
+        return Err(CBORError::NonCanonicalNumeric);
     }
     Ok(())
 }
@@ -178,21 +195,20 @@ impl From<f16> for CBOR {
     }
 }
 
-pub fn f16_cbor_data(value: f16) -> Vec<u8> {
+pub(crate) fn f16_header(value: f16) -> Header {
     let n = value.to_f64();
     if n < 0.0 {
         if let Some(i) = u64::exact_from_f64(-1f64 - n) {
-            let cbor: CBOR = CBORCase::Negative(i).into();
-            return cbor.to_cbor_data();
+            return Header::new(MajorType::Negative, i);
         }
     }
     if let Some(i) = u16::exact_from_f64(n) {
-        return i.cbor_data();
+        return Header::new(MajorType::Unsigned, i as u64);
     }
     if value.is_nan() {
-        return CBOR_NAN.to_vec();
+        return nan_header();
     }
-    value.to_bits().encode_int(MajorType::Simple)
+    Header::with_len(MajorType::Simple, value.to_bits() as u64, 3)
 }
 
 impl TryFrom<CBOR> for f16 {
@@ -230,13 +246,32 @@ impl TryFrom<CBOR> for f16 {
     }
 }
 
-pub(crate) fn validate_canonical_f16(n: f16) -> Result<()> {
+pub(crate) fn validate_canonical_f16(n: f16) -> Result<(), CBORError> {
     let f = n.to_f64();
     if
         f == f as i64 as f64 ||
         n.is_nan() && n.to_bits() != 0x7e00
     {
-        bail!(CBORError::NonCanonicalNumeric);
+        return Err(CBORError::NonCanonicalNumeric);
     }
     Ok(())
 }
+
+macro_rules! impl_try_from_cbor_ref {
+    ($type: ty) => {
+        impl<'a> TryFrom<CBORRef<'a>> for $type {
+            type Error = Error;
+
+            fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+                match cbor.as_case() {
+                    CBORRefCase::Unsigned(_) | CBORRefCase::Negative(_) | CBORRefCase::Simple(_) => cbor.to_cbor().try_into(),
+                    _ => bail!(CBORError::WrongType)
+                }
+            }
+        }
+    };
+}
+
+impl_try_from_cbor_ref!(f64);
+impl_try_from_cbor_ref!(f32);
+impl_try_from_cbor_ref!(f16);
@@ -1,25 +1,14 @@
 import_stdlib!();
 
-use crate::{CBOR, CBORError};
+use crate::{CBORError, CBORRef, CBORRefCase, CBOR};
 
-use super::{CBORCase, varint::{EncodeVarInt, MajorType}};
+use super::CBORCase;
 
 use anyhow::{bail, Error, Result};
 
 macro_rules! impl_cbor {
     ($type: ty) => {
-        impl From64 for $type {
-            fn cbor_data(&self) -> Vec<u8> {
-                #[allow(unused_comparisons)]
-                if *self < 0 {
-                    let n = (-1 - (*self as i128)) as u64;
-                    n.encode_varint(MajorType::Negative)
-                } else {
-                    let n = *self as u64;
-                    n.encode_varint(MajorType::Unsigned)
-                }
-            }
-        }
+        impl From64 for $type {}
 
         impl From<$type> for CBOR {
             fn from(value: $type) -> Self {
@@ -46,6 +35,21 @@ macro_rules! impl_cbor {
                 }
             }
         }
+
+        impl<'a> TryFrom<CBORRef<'a>> for $type {
+            type Error = Error;
+
+            fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+                match cbor.as_case() {
+                    CBORRefCase::Unsigned(n) => Self::from_u64(*n, <$type>::MAX as u64, |x| x as $type),
+                    CBORRefCase::Negative(n) => {
+                        let a = Self::from_u64(*n, <$type>::MAX as u64, |x| x as $type)? as i128;
+                        Ok((-1 - a) as $type)
+                    }
+                    _ => bail!(CBORError::WrongType),
+                }
+            }
+        }
     };
 }
 
@@ -60,8 +64,6 @@ impl_cbor!(i32);
 impl_cbor!(i64);
 
 pub trait From64 {
-    fn cbor_data(&self) -> Vec<u8>;
-
     fn from_u64<F>(n: u64, max: u64, f: F) -> Result<Self>
     where F: Fn(u64) -> Self, Self: Sized
     {
@@ -0,0 +1,495 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+use half::f16;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::{bignum::{integer_from_magnitude, is_bignum_tag, validate_bignum}, decode::{is_minimal_header, located, parse_bytes, parse_header_lenient, DecodeLimits}, varint::MajorType, CBORCase, CBORError, CBORPath, DecodeOptions, Map, PathElement, TagValue, CBOR, TAG_NEGATIVE_BIGNUM};
+
+/// A change made while converting arbitrary CBOR to deterministic CBOR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transformation {
+    /// The byte offset of the header of the item that was changed.
+    pub offset: usize,
+    /// The path of the item that was changed.
+    pub path: CBORPath,
+    /// The change that was made.
+    pub kind: TransformationKind,
+}
+
+impl fmt::Display for Transformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{} at offset {}", self.kind, self.offset)
+        } else {
+            write!(f, "{} at offset {} ({})", self.kind, self.offset, self.path)
+        }
+    }
+}
+
+/// The kinds of change made while converting arbitrary CBOR to deterministic
+/// CBOR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransformationKind {
+    /// An integer, length, or tag was encoded in more bytes than needed.
+    NonMinimalHeader,
+    /// An indefinite-length string, array, or map was given a definite
+    /// length.
+    IndefiniteLength,
+    /// The keys of a map were sorted.
+    MapKeysSorted,
+    /// A map entry was removed because it repeated an earlier entry.
+    DuplicateMapEntryRemoved,
+    /// A text string was converted to Unicode Normalization Form C.
+    TextNormalized,
+    /// A floating point number was encoded in the shortest form that
+    /// preserves its value, as an integer if it has no fractional part.
+    FloatReduced,
+    /// The simple value `undefined` was replaced with `null`.
+    UndefinedReplaced,
+    /// A bignum was stripped of its leading zero bytes, or replaced with a
+    /// plain integer because it fits in 64 bits.
+    BignumReduced,
+}
+
+impl fmt::Display for TransformationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TransformationKind::NonMinimalHeader => "shortened a non-minimal header",
+            TransformationKind::IndefiniteLength => "replaced an indefinite length",
+            TransformationKind::MapKeysSorted => "sorted map keys",
+            TransformationKind::DuplicateMapEntryRemoved => "removed a duplicate map entry",
+            TransformationKind::TextNormalized => "normalized text to NFC",
+            TransformationKind::FloatReduced => "reduced a floating point number",
+            TransformationKind::UndefinedReplaced => "replaced undefined with null",
+            TransformationKind::BignumReduced => "reduced a bignum",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Decode any well-formed CBOR, converting it to deterministic CBOR.
+pub(crate) fn decode_cbor_lenient(data: impl AsRef<[u8]>) -> Result<(CBOR, Vec<Transformation>)> {
+    decode_cbor_lenient_with_options(data, &DecodeOptions::default())
+}
+
+/// Decode any well-formed CBOR, converting it to deterministic CBOR and
+/// enforcing the given limits.
+pub(crate) fn decode_cbor_lenient_with_options(data: impl AsRef<[u8]>, options: &DecodeOptions) -> Result<(CBOR, Vec<Transformation>)> {
+    let data = data.as_ref();
+    let mut ingest = Ingest {
+        data,
+        pos: 0,
+        stack: Vec::new(),
+        limits: DecodeLimits::new(options),
+        transformations: Vec::new(),
+    };
+    let cbor = ingest.run()?;
+    let remaining = data.len() - ingest.pos;
+    if remaining > 0 {
+        return Err(located(CBORError::UnusedData(remaining).into(), ingest.pos, CBORPath::default()));
+    }
+    Ok((cbor, ingest.transformations))
+}
+
+/// A container or indefinite-length string whose items are still being
+/// decoded.
+enum Frame {
+    Array { items: Vec<CBOR>, len: Option<usize> },
+    Map {
+        start: usize,
+        entries: BTreeMap<Vec<u8>, (CBOR, CBOR)>,
+        remaining: Option<u64>,
+        index: usize,
+        entry_start: usize,
+        key: Option<(Vec<u8>, CBOR)>,
+        last_key: Option<Vec<u8>>,
+        sorted: bool,
+    },
+    Tagged(TagValue),
+    Chunks { start: usize, is_text: bool, content: Vec<u8> },
+}
+
+impl Frame {
+    /// Returns the path element leading to the next item in this container.
+    fn path_element(&self) -> Option<PathElement> {
+        match self {
+            Frame::Array { items, .. } => Some(PathElement::Index(items.len())),
+            Frame::Map { index, key: None, .. } => Some(PathElement::KeyAt(*index)),
+            Frame::Map { key: Some((key_data, _)), .. } => Some(PathElement::Key(key_data.clone())),
+            Frame::Tagged(tag) => Some(PathElement::Tag(*tag)),
+            Frame::Chunks { .. } => None,
+        }
+    }
+}
+
+struct Ingest<'a> {
+    data: &'a [u8],
+    pos: usize,
+    stack: Vec<Frame>,
+    limits: DecodeLimits<'a>,
+    transformations: Vec<Transformation>,
+}
+
+impl Ingest<'_> {
+    fn path(&self) -> CBORPath {
+        CBORPath::new(self.stack.iter().filter_map(Frame::path_element).collect::<Vec<_>>())
+    }
+
+    fn report(&mut self, offset: usize, kind: TransformationKind) {
+        let path = self.path();
+        self.transformations.push(Transformation { offset, path, kind });
+    }
+
+    /// Decodes the item at the current position.
+    ///
+    /// Nested items are tracked on an explicit stack rather than by
+    /// recursion, so arbitrarily deep input cannot overflow the call stack.
+    fn run(&mut self) -> Result<CBOR> {
+        loop {
+            let start = self.pos;
+            let item = match self.next_item() {
+                Ok(Some(item)) => item,
+                Ok(None) => continue,
+                Err(error) => return Err(located(error, start, self.path())),
+            };
+            if let Some(cbor) = self.fold(item)? {
+                return Ok(cbor);
+            }
+        }
+    }
+
+    /// Decodes the header at the current position, returning the completed
+    /// item if there is one.
+    fn next_item(&mut self) -> Result<Option<CBOR>> {
+        let data = self.data;
+        let start = self.pos;
+        let Some(&header) = data.get(start) else {
+            bail!(CBORError::Underrun);
+        };
+        if header == 0xff {
+            self.pos += 1;
+            return self.close_indefinite();
+        }
+        if let Some(Frame::Chunks { is_text, .. }) = self.stack.last() {
+            // The chunks of an indefinite-length string must be definite-length
+            // strings of the same type.
+            if header >> 5 != if *is_text { 3 } else { 2 } || header & 31 == 31 {
+                bail!(CBORError::MalformedIndefiniteLength);
+            }
+        } else {
+            self.limits.check_depth(self.stack.len())?;
+        }
+        // The lengths of indefinite-length containers are checked as they
+        // grow.
+        match self.stack.last() {
+            Some(Frame::Array { items, len: None }) => self.limits.check_container_len(items.len() as u64 + 1)?,
+            Some(Frame::Map { entries, remaining: None, key: None, .. }) => self.limits.check_container_len(entries.len() as u64 + 1)?,
+            _ => {},
+        }
+        let major_type = parse_major_type(header);
+        // The content of a bignum must be a byte string.
+        if self.is_bignum_content() && !matches!(major_type, MajorType::ByteString) {
+            bail!(CBORError::WrongType);
+        }
+        if header & 31 == 31 {
+            self.report(start, TransformationKind::IndefiniteLength);
+            self.pos += 1;
+            self.limits.charge(0)?;
+            let frame = match major_type {
+                MajorType::ByteString | MajorType::Text => Frame::Chunks {
+                    start,
+                    is_text: matches!(major_type, MajorType::Text),
+                    content: Vec::new(),
+                },
+                MajorType::Array => Frame::Array { items: Vec::new(), len: None },
+                MajorType::Map => Frame::Map {
+                    start,
+                    entries: BTreeMap::new(),
+                    remaining: None,
+                    index: 0,
+                    entry_start: start + 1,
+                    key: None,
+                    last_key: None,
+                    sorted: true,
+                },
+                _ => bail!(CBORError::UnsupportedHeaderValue(31)),
+            };
+            self.stack.push(frame);
+            return Ok(None);
+        }
+        let (major_type, value, header_len) = parse_header_lenient(&data[start..])?;
+        self.pos += header_len;
+        let is_float = matches!(major_type, MajorType::Simple) && header_len > 2;
+        if !is_float && !is_minimal_header(header, value, header_len) {
+            if matches!(major_type, MajorType::Simple) {
+                // Simple values below 32 must use the one-byte form.
+                bail!(CBORError::InvalidSimpleValue);
+            }
+            self.report(start, TransformationKind::NonMinimalHeader);
+        }
+        let remaining = (data.len() - self.pos) as u64;
+        let item = match major_type {
+            MajorType::Unsigned => {
+                self.limits.charge(0)?;
+                CBORCase::Unsigned(value).into()
+            },
+            MajorType::Negative => {
+                self.limits.charge(0)?;
+                CBORCase::Negative(value).into()
+            },
+            MajorType::ByteString | MajorType::Text => {
+                if value > remaining {
+                    bail!(CBORError::Underrun);
+                }
+                let content = parse_bytes(&data[self.pos..], value as usize)?;
+                self.pos += content.len();
+                if matches!(major_type, MajorType::Text) {
+                    str::from_utf8(content).map_err(CBORError::from)?;
+                }
+                if let Some(Frame::Chunks { content: chunks, .. }) = self.stack.last_mut() {
+                    chunks.extend_from_slice(content);
+                    let len = chunks.len() as u64;
+                    self.limits.check_string_len(len)?;
+                    self.limits.charge_bytes(content.len())?;
+                    return Ok(None);
+                }
+                self.limits.check_string_len(value)?;
+                self.limits.charge(content.len())?;
+                self.string_item(matches!(major_type, MajorType::Text), content.to_vec(), start)
+            },
+            MajorType::Array => {
+                self.limits.check_container_len(value)?;
+                self.limits.charge(0)?;
+                if value == 0 {
+                    Vec::<CBOR>::new().into()
+                } else {
+                    // Every item occupies at least one byte.
+                    if value > remaining {
+                        bail!(CBORError::Underrun);
+                    }
+                    self.stack.push(Frame::Array { items: Vec::new(), len: Some(value as usize) });
+                    return Ok(None);
+                }
+            },
+            MajorType::Map => {
+                self.limits.check_container_len(value)?;
+                self.limits.charge(0)?;
+                if value == 0 {
+                    Map::new().into()
+                } else {
+                    // Every entry occupies at least two bytes.
+                    if value > remaining / 2 {
+                        bail!(CBORError::Underrun);
+                    }
+                    self.stack.push(Frame::Map {
+                        start,
+                        entries: BTreeMap::new(),
+                        remaining: Some(value),
+                        index: 0,
+                        entry_start: self.pos,
+                        key: None,
+                        last_key: None,
+                        sorted: true,
+                    });
+                    return Ok(None);
+                }
+            },
+            MajorType::Tagged => {
+                self.limits.charge(0)?;
+                self.stack.push(Frame::Tagged(value));
+                return Ok(None);
+            },
+            MajorType::Simple => {
+                self.limits.charge(0)?;
+                let item = match (header_len, value) {
+                    (3, _) => CBOR::from(f16::from_bits(value as u16).to_f64()),
+                    (5, _) => CBOR::from(f32::from_bits(value as u32) as f64),
+                    (9, _) => CBOR::from(f64::from_bits(value)),
+                    (_, 20) => CBOR::r#false(),
+                    (_, 21) => CBOR::r#true(),
+                    (_, 22) => CBOR::null(),
+                    (_, 23) => {
+                        self.report(start, TransformationKind::UndefinedReplaced);
+                        CBOR::null()
+                    },
+                    _ => bail!(CBORError::InvalidSimpleValue),
+                };
+                if is_float && item.to_cbor_data() != data[start..self.pos] {
+                    self.report(start, TransformationKind::FloatReduced);
+                }
+                item
+            },
+        };
+        Ok(Some(item))
+    }
+
+    /// Returns `true` if the next item is the content of a bignum.
+    fn is_bignum_content(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Tagged(tag)) if is_bignum_tag(*tag))
+    }
+
+    /// Returns the string with the given content, normalizing text.
+    ///
+    /// The content of a bignum that is not in its shortest form is reported
+    /// here, and reduced once it is folded into its tag.
+    fn string_item(&mut self, is_text: bool, content: Vec<u8>, start: usize) -> CBOR {
+        if !is_text {
+            if self.is_bignum_content() && validate_bignum(&content).is_err() {
+                self.report(start, TransformationKind::BignumReduced);
+            }
+            return CBOR::to_byte_string(content);
+        }
+        let string = String::from_utf8(content).expect("text is validated as it is read");
+        if is_nfc(&string) {
+            CBOR::to_raw_text(string)
+        } else {
+            self.report(start, TransformationKind::TextNormalized);
+            string.nfc().collect::<String>().into()
+        }
+    }
+
+    /// Completes the indefinite-length item on top of the stack.
+    fn close_indefinite(&mut self) -> Result<Option<CBOR>> {
+        let item = match self.stack.last_mut() {
+            Some(Frame::Array { items, len: None }) => mem::take(items).into(),
+            Some(Frame::Map { remaining: None, key: None, .. }) => {
+                let Some(Frame::Map { start, entries, sorted, .. }) = self.stack.pop() else {
+                    unreachable!()
+                };
+                return Ok(Some(self.finish_map(start, entries, sorted)?));
+            },
+            Some(Frame::Chunks { start, is_text, content }) => {
+                let (start, is_text, content) = (*start, *is_text, mem::take(content));
+                self.stack.pop();
+                return Ok(Some(self.string_item(is_text, content, start)));
+            },
+            _ => bail!(CBORError::MalformedIndefiniteLength),
+        };
+        self.stack.pop();
+        Ok(Some(item))
+    }
+
+    /// Builds a map from its entries, reporting if its keys were not in
+    /// order.
+    fn finish_map(&mut self, start: usize, entries: BTreeMap<Vec<u8>, (CBOR, CBOR)>, sorted: bool) -> Result<CBOR> {
+        if !sorted {
+            self.report(start, TransformationKind::MapKeysSorted);
+        }
+        let mut map = Map::new();
+        for (key_data, (key, value)) in entries {
+            map.insert_next(key_data, key, value)?;
+        }
+        Ok(map.into())
+    }
+
+    /// Adds a completed item to its enclosing containers, returning it if it
+    /// is the top-level item.
+    fn fold(&mut self, mut item: CBOR) -> Result<Option<CBOR>> {
+        loop {
+            match self.stack.last_mut() {
+                None => return Ok(Some(item)),
+                Some(Frame::Array { items, len }) => {
+                    items.push(item);
+                    if *len != Some(items.len()) {
+                        return Ok(None);
+                    }
+                    item = mem::take(items).into();
+                },
+                Some(Frame::Map { key: None, .. }) => {
+                    let key_data = item.to_cbor_data();
+                    if let Err(error) = self.limits.charge_bytes(key_data.len()) {
+                        return Err(located(error, self.entry_start(), self.path()));
+                    }
+                    let Some(Frame::Map { key, .. }) = self.stack.last_mut() else {
+                        unreachable!()
+                    };
+                    *key = Some((key_data, item));
+                    return Ok(None);
+                },
+                Some(Frame::Map { .. }) => match self.add_entry(item)? {
+                    Some(map) => {
+                        item = map;
+                        continue;
+                    },
+                    None => return Ok(None),
+                },
+                Some(Frame::Tagged(tag)) if is_bignum_tag(*tag) => {
+                    let magnitude = item.try_into_byte_string().expect("the content of a bignum is a byte string");
+                    item = integer_from_magnitude(*tag == TAG_NEGATIVE_BIGNUM, &magnitude);
+                },
+                Some(Frame::Tagged(tag)) => {
+                    item = CBOR::to_tagged_value(*tag, item);
+                },
+                Some(Frame::Chunks { .. }) => unreachable!("chunks are not folded"),
+            }
+            self.stack.pop();
+        }
+    }
+
+    /// Returns the offset of the current entry of the map on top of the
+    /// stack.
+    fn entry_start(&self) -> usize {
+        match self.stack.last() {
+            Some(Frame::Map { entry_start, .. }) => *entry_start,
+            _ => unreachable!("only maps have entries"),
+        }
+    }
+
+    /// Adds an entry with the given value to the map on top of the stack,
+    /// returning the map once it is complete.
+    ///
+    /// An entry that repeats an earlier one is dropped, but one whose key
+    /// repeats an earlier key with a different value cannot be reconciled.
+    fn add_entry(&mut self, value: CBOR) -> Result<Option<CBOR>> {
+        let Some(Frame::Map { entries, entry_start, key, .. }) = self.stack.last_mut() else {
+            unreachable!("only maps have entries")
+        };
+        let (key_data, key) = key.take().expect("a key precedes each value");
+        let offset = *entry_start;
+        let existing = entries.get(&key_data).map(|(_, existing)| *existing == value);
+        match existing {
+            Some(true) => self.report(offset, TransformationKind::DuplicateMapEntryRemoved),
+            Some(false) => return Err(located(CBORError::ConflictingMapKey.into(), offset, self.path())),
+            None => {},
+        }
+        let pos = self.pos;
+        let Some(Frame::Map { entries, remaining, index, entry_start, last_key, sorted, .. }) = self.stack.last_mut() else {
+            unreachable!("only maps have entries")
+        };
+        if existing.is_none() {
+            if last_key.as_ref().is_some_and(|last_key| *last_key > key_data) {
+                *sorted = false;
+            }
+            *last_key = Some(key_data.clone());
+            entries.insert(key_data, (key, value));
+        }
+        *index += 1;
+        *entry_start = pos;
+        match remaining {
+            Some(remaining) if *remaining > 1 => {
+                *remaining -= 1;
+                return Ok(None);
+            },
+            None => return Ok(None),
+            Some(_) => {},
+        }
+        let Some(Frame::Map { start, entries, sorted, .. }) = self.stack.pop() else {
+            unreachable!("only maps have entries")
+        };
+        self.finish_map(start, entries, sorted).map(Some)
+    }
+}
+
+fn parse_major_type(header: u8) -> MajorType {
+    match header >> 5 {
+        0 => MajorType::Unsigned,
+        1 => MajorType::Negative,
+        2 => MajorType::ByteString,
+        3 => MajorType::Text,
+        4 => MajorType::Array,
+        5 => MajorType::Map,
+        6 => MajorType::Tagged,
+        _ => MajorType::Simple,
+    }
+}
@@ -36,6 +36,46 @@
 //! features = ["multithreaded"]
 //! ```
 //!
+//! ## Tokio
+//!
+//! The `tokio` feature is available but not enabled by default. It adds
+//! `AsyncCBORReader` and `AsyncCBORWriter` for reading and writing items over
+//! tokio's `AsyncRead` and `AsyncWrite`, and `CBORCodec` for framing items with
+//! `tokio_util::codec`. To enable it, add the following to your `Cargo.toml`:
+//!
+//! ```toml
+//! [dependencies.dcbor]
+//! version = "0.15.2"
+//! features = ["tokio"]
+//! ```
+//!
+//! ## `num-bigint`
+//!
+//! The `num-bigint` feature is available but not enabled by default. It adds
+//! conversions between CBOR and `num_bigint`'s `BigInt` and `BigUint`, which
+//! like `u128` and `i128` are encoded as plain integers when they fit in 64
+//! bits and as bignums (tags 2 and 3) otherwise. To enable it, add the
+//! following to your `Cargo.toml`:
+//!
+//! ```toml
+//! [dependencies.dcbor]
+//! version = "0.15.2"
+//! features = ["num-bigint"]
+//! ```
+//!
+//! ## `rust_decimal`
+//!
+//! The `rust_decimal` feature is available but not enabled by default. It
+//! adds conversions between `rust_decimal`'s `Decimal` and `DecimalFraction`,
+//! and between `Decimal` and CBOR, which encode it as a decimal fraction (tag
+//! 4). To enable it, add the following to your `Cargo.toml`:
+//!
+//! ```toml
+//! [dependencies.dcbor]
+//! version = "0.15.2"
+//! features = ["rust_decimal"]
+//! ```
+//!
 //! ## `no_std`
 //!
 //! The `dcbor` library is `no_std` compatible. To use it in a `no_std` environment, disable the
@@ -94,6 +134,9 @@ mod stdlib;
 mod cbor;
 pub use cbor::*;
 
+mod cbor_ref;
+pub use cbor_ref::{CBORRef, CBORRefCase, MapRef};
+
 mod byte_string;
 pub use byte_string::ByteString;
 
@@ -104,16 +147,23 @@ mod float;
 mod array;
 
 mod error;
-pub use error::CBORError;
+pub use error::{CBORError, DecodeError};
+
+mod path;
+pub use path::{CBORPath, PathElement};
 
 mod date;
 pub use date::Date;
 
+mod decimal;
+pub use decimal::{BigFloat, DecimalFraction};
+
 mod diag;
 mod dump;
+pub use dump::parse_hex;
 
 mod tags_store;
-pub use tags_store::{TagsStoreTrait, TagsStore, CBORSummarizer};
+pub use tags_store::{TagsStoreTrait, TagsStore, CBORSummarizer, CBORDecoder};
 
 mod tag;
 pub use tag::{Tag, TagValue};
@@ -135,8 +185,52 @@ mod cbor_tagged_codable;
 pub use cbor_tagged_codable::CBORTaggedCodable;
 
 mod decode;
+mod encode;
+mod order;
+mod decode_options;
+pub use decode_options::DecodeOptions;
+mod decode_validator;
+pub use decode_validator::DecodeValidator;
+mod digest_algorithm;
+pub use digest_algorithm::DigestAlgorithm;
+
+mod profile;
+pub use profile::EncodingProfile;
+
+mod lenient;
+pub use lenient::{Transformation, TransformationKind};
+
+mod lint;
+pub use lint::{lint, LintReport, Violation, ViolationKind};
+
+mod validate;
+pub use validate::{validate, ValidationSummary};
+
+mod sequence;
+pub use sequence::{decode_sequence, encode_sequence, CBORSequence};
+
+mod encoded;
+pub use encoded::{EncodedCBOR, EncodedEntries, EncodedIndex, EncodedItems};
+
+mod events;
+pub use events::{Event, Events};
+
+#[cfg(feature = "std")]
+mod reader;
+#[cfg(feature = "std")]
+pub use reader::CBORReader;
+#[cfg(feature = "std")]
+mod writer;
+#[cfg(feature = "std")]
+pub use writer::CBORWriter;
+
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "tokio")]
+pub use async_io::{AsyncCBORReader, AsyncCBORWriter, CBORCodec};
 
 mod int;
+mod bignum;
 
 mod map;
 pub use map::{Map, MapIter};
@@ -146,9 +240,11 @@ mod string;
 mod string_util;
 
 mod simple;
-pub use simple::Simple;
+pub use simple::{Simple, SimpleValue};
 
 mod varint;
+#[cfg(all(feature = "multithreaded", not(feature = "std")))]
+mod once_lock;
 mod exact;
 use exact::ExactFrom;
 
@@ -0,0 +1,392 @@
+import_stdlib!();
+
+use half::f16;
+use unicode_normalization::is_nfc;
+
+use crate::{bignum::{is_bignum_tag, validate_bignum}, cbor::RefCounted, decode::{is_minimal_header, parse_header_lenient}, dump::hex_annotated_data, float::{validate_canonical_f16, validate_canonical_f32, validate_canonical_f64}, tags_store::TagsStoreTrait, varint::MajorType, with_tags, CBORError, CBORPath, PathElement, TagValue};
+
+/// A departure from the dCBOR encoding rules found by [`lint`].
+#[derive(Clone)]
+pub struct Violation {
+    /// The byte offset of the header of the offending item.
+    pub offset: usize,
+    /// The rule that was broken.
+    pub kind: ViolationKind,
+    /// The last element of the path of the offending item, if any.
+    path: Option<usize>,
+    paths: RefCounted<PathArena>,
+}
+
+impl Violation {
+    /// Returns the path of the offending item.
+    pub fn path(&self) -> CBORPath {
+        let mut elements = Vec::new();
+        let mut node = self.path;
+        while let Some(index) = node {
+            let (parent, element) = &self.paths[index];
+            elements.push(element.clone());
+            node = *parent;
+        }
+        elements.reverse();
+        CBORPath::new(elements)
+    }
+}
+
+impl fmt::Debug for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Violation")
+            .field("offset", &self.offset)
+            .field("path", &self.path())
+            .field("kind", &self.kind)
+            .finish()
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path();
+        if path.is_empty() {
+            write!(f, "{} at offset {}", self.kind, self.offset)
+        } else {
+            write!(f, "{} at offset {} ({})", self.kind, self.offset, path)
+        }
+    }
+}
+
+/// The kinds of departure from the dCBOR encoding rules found by [`lint`].
+#[derive(Debug, Clone)]
+pub enum ViolationKind {
+    /// An integer, length, tag, or simple value was encoded in more bytes
+    /// than needed.
+    NonMinimalHeader,
+    /// A map key's encoding does not sort after that of the key before it.
+    MisorderedMapKey,
+    /// A map key's encoding is the same as that of the key before it.
+    DuplicateMapKey,
+    /// A text string is not valid UTF-8.
+    InvalidUtf8,
+    /// A text string is not in Unicode Normalization Form C.
+    NonNFCString,
+    /// A floating point number is not encoded in the shortest form that
+    /// preserves its value, or has no fractional part and so should have been
+    /// encoded as an integer.
+    NonCanonicalFloat,
+    /// A NaN is encoded other than as the half-precision quiet NaN `f97e00`.
+    NonCanonicalNaN,
+    /// The content of a bignum is not a byte string.
+    InvalidBignum,
+    /// A bignum has leading zero bytes, or is small enough that it should
+    /// have been encoded as a plain integer.
+    NonCanonicalBignum,
+    /// A simple value other than `false`, `true`, `null`, or a floating point
+    /// number.
+    UnsupportedSimpleValue(u64),
+    /// The given number of bytes follow the top-level item.
+    TrailingData(usize),
+    /// The data is not well-formed CBOR, so its structure, and anything after
+    /// this point, could not be examined.
+    Malformed(CBORError),
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViolationKind::NonMinimalHeader => f.write_str("the header is not in its shortest form"),
+            ViolationKind::MisorderedMapKey => f.write_str("the map key does not sort after the previous key"),
+            ViolationKind::DuplicateMapKey => f.write_str("the map key duplicates the previous key"),
+            ViolationKind::InvalidUtf8 => f.write_str("the text is not valid UTF-8"),
+            ViolationKind::NonNFCString => f.write_str("the text is not in Unicode Normalization Form C"),
+            ViolationKind::NonCanonicalFloat => f.write_str("the floating point number is not in its shortest form"),
+            ViolationKind::NonCanonicalNaN => f.write_str("the NaN is not encoded as f97e00"),
+            ViolationKind::InvalidBignum => f.write_str("the content of the bignum is not a byte string"),
+            ViolationKind::NonCanonicalBignum => f.write_str("the bignum is not in its shortest form"),
+            ViolationKind::UnsupportedSimpleValue(value) => write!(f, "simple({}) is not supported", value),
+            ViolationKind::TrailingData(len) => write!(f, "{} bytes of data follow the item", len),
+            ViolationKind::Malformed(error) => write!(f, "malformed: {}", error),
+        }
+    }
+}
+
+/// The violations found by [`lint`] in some encoded data.
+#[derive(Debug, Clone)]
+pub struct LintReport<'a> {
+    data: &'a [u8],
+    violations: Vec<Violation>,
+}
+
+impl<'a> LintReport<'a> {
+    /// Returns the data that was examined.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the violations found, in the order of their offsets.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// Returns `true` if the data is valid dCBOR.
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Returns the annotated hexadecimal representation of the data, with
+    /// each line containing the start of an offending item marked with the
+    /// rules it breaks.
+    pub fn hex_annotated(&self) -> String {
+        with_tags!(|tags: &dyn TagsStoreTrait| {
+            self.hex_opt(Some(tags))
+        })
+    }
+
+    /// Returns the annotated hexadecimal representation of the data, with
+    /// each line containing the start of an offending item marked with the
+    /// rules it breaks.
+    ///
+    /// Optionally adds names of known tags.
+    pub fn hex_opt(&self, tags: Option<&dyn TagsStoreTrait>) -> String {
+        let marks: Vec<_> = self.violations.iter()
+            .map(|violation| (violation.offset, violation.kind.to_string()))
+            .collect();
+        hex_annotated_data(self.data, &marks, tags)
+    }
+}
+
+/// Checks `data` against every dCBOR encoding rule, collecting all the
+/// violations rather than stopping at the first.
+///
+/// Each item is examined as it is encoded, so for example a map key that is
+/// out of order because of a non-minimal header is reported twice. Only data
+/// that is not well-formed, such as a truncated item or one of indefinite
+/// length, stops the examination, and is reported as
+/// [`ViolationKind::Malformed`].
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// // {2: 1.0, 1: 23} with the 23 in two bytes.
+/// let data = hex_literal::hex!("a2 02 f93c00 01 1817");
+/// let report = dcbor::lint(&data);
+/// let violations: Vec<String> = report.violations().iter().map(ToString::to_string).collect();
+/// assert_eq!(violations, [
+///     "the floating point number is not in its shortest form at offset 2 ({2})",
+///     "the map key does not sort after the previous key at offset 5 (key(1))",
+///     "the header is not in its shortest form at offset 6 ({1})",
+/// ]);
+/// assert_eq!(report.hex_opt(None), indoc::indoc! {"
+///     a2          # map(2)
+///         02      # unsigned(2)
+///         f93c00  # 1.0 <-- the floating point number is not in its shortest form
+///         01      # unsigned(1) <-- the map key does not sort after the previous key
+///         1817    # unsigned(23) <-- the header is not in its shortest form"
+/// });
+/// ```
+pub fn lint(data: &[u8]) -> LintReport<'_> {
+    let mut lint = Lint { data, pos: 0, stack: Vec::new(), recorded: 0, paths: Vec::new(), violations: Vec::new() };
+    lint.run();
+    // Map keys are checked once they have been examined, after anything
+    // within them.
+    lint.violations.sort_by_key(|(offset, _, _)| *offset);
+    let paths = RefCounted::new(lint.paths);
+    let violations = lint.violations.into_iter()
+        .map(|(offset, kind, path)| Violation { offset, kind, path, paths: paths.clone() })
+        .collect();
+    LintReport { data, violations }
+}
+
+/// The elements of the paths of the violations found by [`lint`], each with
+/// the index of the element before it, so that violations within the same
+/// container share the elements leading to it.
+type PathArena = Vec<(Option<usize>, PathElement)>;
+
+/// A container whose items are still being examined.
+struct Frame {
+    container: Container,
+    /// The number of items in the container, counting map keys and values
+    /// separately.
+    len: u64,
+    /// The number of items examined so far.
+    index: u64,
+    /// The offset of the item being examined.
+    item_start: usize,
+    /// The index in the path arena of the path element leading to the item
+    /// being examined, once it has been recorded.
+    path: Option<usize>,
+}
+
+enum Container {
+    Array,
+    Map { previous_key: Option<ops::Range<usize>>, key: Option<ops::Range<usize>> },
+    Tagged(TagValue),
+}
+
+impl Frame {
+    /// Returns the path element leading to the item being examined.
+    fn path_element(&self, data: &[u8]) -> PathElement {
+        match &self.container {
+            Container::Array => PathElement::Index(self.index as usize),
+            Container::Map { key: Some(key), .. } if self.index % 2 == 1 => PathElement::Key(data[key.clone()].to_vec()),
+            Container::Map { .. } => PathElement::KeyAt((self.index / 2) as usize),
+            Container::Tagged(tag) => PathElement::Tag(*tag),
+        }
+    }
+}
+
+struct Lint<'a> {
+    data: &'a [u8],
+    pos: usize,
+    stack: Vec<Frame>,
+    /// The number of frames, from the outermost, whose path elements have
+    /// been recorded for the items they are examining.
+    recorded: usize,
+    paths: PathArena,
+    violations: Vec<(usize, ViolationKind, Option<usize>)>,
+}
+
+impl Lint<'_> {
+    fn run(&mut self) {
+        // Items are visited in pre-order using an explicit stack, so
+        // arbitrarily deep structures cannot overflow the call stack.
+        loop {
+            let start = self.pos;
+            if let Some(frame) = self.stack.last_mut() {
+                frame.item_start = start;
+                self.recorded = self.recorded.min(self.stack.len() - 1);
+            }
+            let (major_type, value, header_len) = match parse_header_lenient(&self.data[start..]) {
+                Ok(header) => header,
+                Err(error) => return self.report(start, ViolationKind::Malformed(error)),
+            };
+            if !is_minimal_header(self.data[start], value, header_len) {
+                self.report(start, ViolationKind::NonMinimalHeader);
+            }
+            self.pos += header_len;
+            let is_bignum = matches!(self.stack.last(), Some(Frame { container: Container::Tagged(tag), .. }) if is_bignum_tag(*tag));
+            if is_bignum && !matches!(major_type, MajorType::ByteString) {
+                self.report(start, ViolationKind::InvalidBignum);
+            }
+            let container = match major_type {
+                MajorType::Unsigned | MajorType::Negative => None,
+                MajorType::ByteString | MajorType::Text => {
+                    if value > (self.data.len() - self.pos) as u64 {
+                        return self.report(start, ViolationKind::Malformed(CBORError::Underrun));
+                    }
+                    let content = &self.data[self.pos..self.pos + value as usize];
+                    if matches!(major_type, MajorType::Text) {
+                        match str::from_utf8(content) {
+                            Ok(text) if !is_nfc(text) => self.report(start, ViolationKind::NonNFCString),
+                            Ok(_) => {},
+                            Err(_) => self.report(start, ViolationKind::InvalidUtf8),
+                        }
+                    } else if is_bignum && validate_bignum(content).is_err() {
+                        self.report(start, ViolationKind::NonCanonicalBignum);
+                    }
+                    self.pos += content.len();
+                    None
+                },
+                MajorType::Array => Some((Container::Array, value)),
+                MajorType::Map => Some((Container::Map { previous_key: None, key: None }, value.saturating_mul(2))),
+                MajorType::Tagged => Some((Container::Tagged(value), 1)),
+                MajorType::Simple => {
+                    if let Some(kind) = simple_violation(value, header_len) {
+                        self.report(start, kind);
+                    }
+                    None
+                },
+            };
+            match container {
+                Some((container, len)) if len > 0 => {
+                    self.stack.push(Frame { container, len, index: 0, item_start: start, path: None });
+                },
+                _ => {
+                    if !self.complete_item() {
+                        break;
+                    }
+                },
+            }
+        }
+        let remaining = self.data.len() - self.pos;
+        if remaining > 0 {
+            self.report(self.pos, ViolationKind::TrailingData(remaining));
+        }
+    }
+
+    /// Records the end of the item just examined, along with any containers
+    /// it completes, returning `false` if it was the top-level item.
+    fn complete_item(&mut self) -> bool {
+        while let Some(frame) = self.stack.last_mut() {
+            let item = frame.item_start..self.pos;
+            let mut kind = None;
+            if let Container::Map { previous_key, key } = &mut frame.container {
+                if frame.index % 2 == 0 {
+                    if let Some(previous_key) = previous_key {
+                        let (previous_key, key) = (&self.data[previous_key.clone()], &self.data[item.clone()]);
+                        if previous_key == key {
+                            kind = Some(ViolationKind::DuplicateMapKey);
+                        } else if previous_key > key {
+                            kind = Some(ViolationKind::MisorderedMapKey);
+                        }
+                    }
+                    *key = Some(item.clone());
+                } else {
+                    *previous_key = key.take();
+                }
+            }
+            if let Some(kind) = kind {
+                self.report(item.start, kind);
+            }
+            let frame = self.stack.last_mut().unwrap();
+            frame.index += 1;
+            if frame.index < frame.len {
+                return true;
+            }
+            self.stack.pop();
+            self.recorded = self.recorded.min(self.stack.len());
+        }
+        false
+    }
+
+    /// Records a violation by the item at `offset`, which is the item being
+    /// examined in the innermost container.
+    ///
+    /// Only the path elements not already recorded for an earlier violation
+    /// are added to the arena, so the paths take space in proportion to the
+    /// data rather than to the number of violations times their depth.
+    fn report(&mut self, offset: usize, kind: ViolationKind) {
+        let mut path = self.recorded.checked_sub(1).and_then(|index| self.stack[index].path);
+        for frame in &mut self.stack[self.recorded..] {
+            self.paths.push((path, frame.path_element(self.data)));
+            path = Some(self.paths.len() - 1);
+            frame.path = path;
+        }
+        self.recorded = self.stack.len();
+        self.violations.push((offset, kind, path));
+    }
+}
+
+/// Returns the violation, if any, by the simple value or floating point
+/// number with the given header value and length.
+fn simple_violation(value: u64, header_len: usize) -> Option<ViolationKind> {
+    let (is_nan, result) = match header_len {
+        3 => {
+            let f = f16::from_bits(value as u16);
+            (f.is_nan(), validate_canonical_f16(f))
+        },
+        5 => {
+            let f = f32::from_bits(value as u32);
+            (f.is_nan(), validate_canonical_f32(f))
+        },
+        9 => {
+            let f = f64::from_bits(value);
+            (f.is_nan(), validate_canonical_f64(f))
+        },
+        _ => return match value {
+            20..=22 => None,
+            _ => Some(ViolationKind::UnsupportedSimpleValue(value)),
+        },
+    };
+    match (is_nan, result) {
+        (_, Ok(())) => None,
+        (true, Err(_)) => Some(ViolationKind::NonCanonicalNaN),
+        (false, Err(_)) => Some(ViolationKind::NonCanonicalFloat),
+    }
+}
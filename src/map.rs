@@ -2,9 +2,7 @@ import_stdlib!();
 
 use anyhow::{bail, Error, Result};
 
-use crate::{CBOR, CBORError, CBORCase};
-
-use super::varint::{EncodeVarInt, MajorType};
+use crate::{encode::{encode, encoded_len, hash_encoding, EncodeTask}, order::{compare, Part}, CBOR, CBORError, CBORCase, CBORRef, EncodingProfile};
 
 /// A CBOR map.
 ///
@@ -41,23 +39,48 @@ impl Map {
         self.0.insert(MapKey::new(key.to_cbor_data()), MapValue::new(key, value));
     }
 
-    pub(crate) fn insert_next(&mut self, key: CBOR, value: CBOR) -> Result<()> {
-        match self.0.last_key_value() {
-            None => {
-                self.insert(key, value);
-                Ok(())
-            },
-            Some(entry) => {
-                let new_key = MapKey::new(key.to_cbor_data());
-                if self.0.contains_key(&new_key) {
-                    bail!(CBORError::DuplicateMapKey)
-                }
-                if entry.0 >= &new_key {
-                    bail!(CBORError::MisorderedMapKey)
-                }
-                self.0.insert(new_key, MapValue::new(key, value));
-                Ok(())
+    /// Inserts a key-value pair whose key encoding is already known, requiring
+    /// that the key sort after all keys already in the map.
+    pub(crate) fn insert_next(&mut self, key_data: Vec<u8>, key: CBOR, value: CBOR) -> Result<()> {
+        let new_key = MapKey::new(key_data);
+        if let Some(entry) = self.0.last_key_value() {
+            if self.0.contains_key(&new_key) {
+                bail!(CBORError::DuplicateMapKey)
             }
+            if entry.0 >= &new_key {
+                bail!(CBORError::MisorderedMapKey)
+            }
+        }
+        self.0.insert(new_key, MapValue::new(key, value));
+        Ok(())
+    }
+
+    /// Inserts a key-value pair whose key encoding is already known, requiring
+    /// that the key not already be in the map.
+    ///
+    /// `profile_key_data` is the key's encoding under the profile it was
+    /// decoded with, if that differs from its dCBOR encoding, so that keys
+    /// that are distinct under that profile are kept apart.
+    pub(crate) fn insert_unique(&mut self, key_data: Vec<u8>, profile_key_data: Option<Vec<u8>>, key: CBOR, value: CBOR) -> Result<()> {
+        let new_key = MapKey { data: key_data, profile_data: profile_key_data };
+        if self.0.contains_key(&new_key) {
+            bail!(CBORError::DuplicateMapKey)
+        }
+        self.0.insert(new_key, MapValue::new(key, value));
+        Ok(())
+    }
+
+    /// Gets an iterator over the encoded keys and the values of the map,
+    /// sorted by key.
+    pub(crate) fn encoded_iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], &CBOR)> + ExactSizeIterator {
+        self.0.iter().map(|(key, value)| (key.data.as_slice(), &value.value))
+    }
+
+    /// Moves all keys and values out of the map onto `stack`.
+    pub(crate) fn drain_into(&mut self, stack: &mut Vec<CBOR>) {
+        for value in mem::take(&mut self.0).into_values() {
+            stack.push(value.key);
+            stack.push(value.value);
         }
     }
 
@@ -96,29 +119,42 @@ impl Default for Map {
 
 impl PartialEq for Map {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        self.cmp(other) == cmp::Ordering::Equal
     }
 }
 
-impl Eq for Map {
-    fn assert_receiver_is_total_eq(&self) {}
+impl Eq for Map { }
+
+impl PartialOrd for Map {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Map {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        compare(Part::Map(self), Part::Map(other))
+    }
+}
+
+impl hash::Hash for Map {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        hash_encoding(EncodeTask::Map(self), state);
+    }
 }
 
 impl Map {
     pub fn cbor_data(&self) -> Vec<u8> {
-        let pairs: Vec<(Vec<u8>, Vec<u8>)> = self.0.iter().map(|x| {
-            let a: Vec<u8> = x.0.0.to_owned();
-            let cbor: &CBOR = &x.1.value;
-            let b: Vec<u8> = cbor.to_cbor_data();
-            (a, b)
-        }).collect();
-        let mut buf = pairs.len().encode_varint(MajorType::Map);
-        for pair in pairs {
-            buf.extend(pair.0);
-            buf.extend(pair.1);
-        }
+        let mut buf = Vec::new();
+        encode(EncodeTask::Map(self), EncodingProfile::Dcbor, &mut buf);
         buf
     }
+
+    /// Returns the length of the encoding returned by
+    /// [`cbor_data`](Self::cbor_data), without producing it.
+    pub fn encoded_len(&self) -> usize {
+        encoded_len(EncodeTask::Map(self), EncodingProfile::Dcbor)
+    }
 }
 
 impl From<Map> for CBOR {
@@ -155,6 +191,13 @@ impl<'a> Iterator for MapIter<'a> {
     }
 }
 
+impl<'a> DoubleEndedIterator for MapIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let entry = self.0.next_back()?;
+        Some((&entry.key, &entry.value))
+    }
+}
+
 #[derive(Clone)]
 struct MapValue {
     key: CBOR,
@@ -167,36 +210,33 @@ impl MapValue {
     }
 }
 
-impl PartialEq for MapValue {
-    fn eq(&self, other: &Self) -> bool {
-        self.key == other.key && self.value == other.value
-    }
-}
-
 impl fmt::Debug for MapValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!("({:?}, {:?})", self.key, self.value))
     }
 }
 
+/// The dCBOR encoding of a key, followed, for a key decoded under another
+/// profile, by its encoding under that profile where the two differ.
 #[derive(Clone)]
-struct MapKey(Vec<u8>);
+struct MapKey {
+    data: Vec<u8>,
+    profile_data: Option<Vec<u8>>,
+}
 
 impl MapKey {
     fn new(key_data: Vec<u8>) -> MapKey {
-        MapKey(key_data)
+        MapKey { data: key_data, profile_data: None }
     }
 }
 
 impl PartialEq for MapKey {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        self.data == other.data && self.profile_data == other.profile_data
     }
 }
 
-impl Eq for MapKey {
-    fn assert_receiver_is_total_eq(&self) {}
-}
+impl Eq for MapKey { }
 
 impl PartialOrd for MapKey {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
@@ -206,13 +246,13 @@ impl PartialOrd for MapKey {
 
 impl Ord for MapKey {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.0.cmp(&other.0)
+        self.data.cmp(&other.data).then_with(|| self.profile_data.cmp(&other.profile_data))
     }
 }
 
 impl fmt::Debug for MapKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!("0x{}", hex::encode(&self.0)))
+        f.write_fmt(format_args!("0x{}", hex::encode(&self.data)))
     }
 }
 
@@ -255,6 +295,20 @@ where
     }
 }
 
+impl<'a, K, V> TryFrom<CBORRef<'a>> for HashMap<K, V>
+where
+    K: TryFrom<CBORRef<'a>, Error = Error> + cmp::Eq + hash::Hash,
+    V: TryFrom<CBORRef<'a>, Error = Error>,
+{
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        cbor.try_into_map()?.into_entries()
+            .map(|(k, v)| Ok((k.try_into()?, v.try_into()?)))
+            .collect()
+    }
+}
+
 impl<K, V> From<BTreeMap<K, V>> for CBOR
 where
     K: Into<CBOR>,
@@ -287,3 +341,17 @@ where
         }
     }
 }
+
+impl<'a, K, V> TryFrom<CBORRef<'a>> for BTreeMap<K, V>
+where
+    K: TryFrom<CBORRef<'a>, Error = Error> + cmp::Ord,
+    V: TryFrom<CBORRef<'a>, Error = Error>,
+{
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        cbor.try_into_map()?.into_entries()
+            .map(|(k, v)| Ok((k.try_into()?, v.try_into()?)))
+            .collect()
+    }
+}
@@ -0,0 +1,81 @@
+import_stdlib!();
+
+use core::{cell::UnsafeCell, hint, sync::atomic::{AtomicU8, Ordering}};
+
+const EMPTY: u8 = 0;
+const INITIALIZING: u8 = 1;
+const READY: u8 = 2;
+
+/// A cell written at most once, which may be shared between threads.
+///
+/// This stands in for `std::sync::OnceLock` in `no_std` builds with the
+/// `multithreaded` feature, providing the part of its interface the crate
+/// uses. Threads that find the cell being initialized by another thread spin
+/// until it is ready.
+pub(crate) struct OnceLock<T> {
+    state: AtomicU8,
+    value: UnsafeCell<Option<T>>,
+}
+
+// SAFETY: the value is only written by the one thread that moves the state
+// from `EMPTY` to `INITIALIZING`, and only read once the state is `READY`.
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> { }
+
+impl<T> OnceLock<T> {
+    pub(crate) const fn new() -> Self {
+        Self { state: AtomicU8::new(EMPTY), value: UnsafeCell::new(None) }
+    }
+
+    pub(crate) fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) != READY {
+            return None;
+        }
+        // SAFETY: the value is never written again once it is ready.
+        unsafe { (*self.value.get()).as_ref() }
+    }
+
+    pub(crate) fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+        loop {
+            match self.state.compare_exchange_weak(EMPTY, INITIALIZING, Ordering::Acquire, Ordering::Acquire) {
+                Ok(_) => {
+                    // If `f` panics, the cell is left empty rather than
+                    // initializing forever.
+                    let reset = ResetOnUnwind(&self.state);
+                    let value = f();
+                    mem::forget(reset);
+                    // SAFETY: no other thread reads or writes the value
+                    // while the state is `INITIALIZING`.
+                    unsafe { *self.value.get() = Some(value) };
+                    self.state.store(READY, Ordering::Release);
+                    break;
+                },
+                Err(READY) => break,
+                Err(_) => hint::spin_loop(),
+            }
+        }
+        self.get().unwrap()
+    }
+
+    pub(crate) fn get_mut(&mut self) -> Option<&mut T> {
+        self.value.get_mut().as_mut()
+    }
+
+    pub(crate) fn take(&mut self) -> Option<T> {
+        *self.state.get_mut() = EMPTY;
+        self.value.get_mut().take()
+    }
+}
+
+struct ResetOnUnwind<'a>(&'a AtomicU8);
+
+impl Drop for ResetOnUnwind<'_> {
+    fn drop(&mut self) {
+        self.0.store(EMPTY, Ordering::Release);
+    }
+}
+
+impl<T> From<T> for OnceLock<T> {
+    fn from(value: T) -> Self {
+        Self { state: AtomicU8::new(READY), value: UnsafeCell::new(Some(value)) }
+    }
+}
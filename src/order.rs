@@ -0,0 +1,103 @@
+import_stdlib!();
+
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::{varint::{Header, MajorType}, CBORCase, Map, CBOR};
+
+/// An item, or part of one, whose dCBOR encoding is being compared.
+#[derive(Clone, Copy)]
+pub(crate) enum Part<'a> {
+    Item(&'a CBOR),
+    Map(&'a Map),
+    /// An encoding, compared only with another encoding.
+    Data(&'a [u8]),
+}
+
+/// What follows the header of an item's encoding.
+enum Body<'a> {
+    None,
+    Bytes(&'a [u8]),
+    Text(String),
+    Items(&'a [CBOR]),
+    Map(&'a Map),
+    Item(&'a CBOR),
+}
+
+/// Splits the encoding of `part` into its header and what follows it.
+fn split(part: Part<'_>) -> (Header, Body<'_>) {
+    let cbor = match part {
+        Part::Item(cbor) => cbor,
+        Part::Map(map) => return (Header::new(MajorType::Map, map.len() as u64), Body::Map(map)),
+        Part::Data(_) => unreachable!("encodings are only compared with encodings"),
+    };
+    match cbor.as_case() {
+        CBORCase::Unsigned(x) => (Header::new(MajorType::Unsigned, *x), Body::None),
+        CBORCase::Negative(x) => (Header::new(MajorType::Negative, *x), Body::None),
+        CBORCase::ByteString(x) => (Header::new(MajorType::ByteString, x.len() as u64), Body::Bytes(x.data())),
+        CBORCase::Text(x) if is_nfc(x) => (Header::new(MajorType::Text, x.len() as u64), Body::Bytes(x.as_bytes())),
+        CBORCase::Text(x) => {
+            let nfc = x.nfc().collect::<String>();
+            (Header::new(MajorType::Text, nfc.len() as u64), Body::Text(nfc))
+        },
+        CBORCase::Array(x) => (Header::new(MajorType::Array, x.len() as u64), Body::Items(x)),
+        CBORCase::Map(x) => (Header::new(MajorType::Map, x.len() as u64), Body::Map(x)),
+        CBORCase::Tagged(tag, item) => (Header::new(MajorType::Tagged, tag.value()), Body::Item(item)),
+        CBORCase::Simple(x) => (x.header(), Body::None),
+    }
+}
+
+/// Compares the dCBOR encodings of `a` and `b` lexicographically, without
+/// producing them.
+///
+/// Encodings are self-delimiting, so no encoding is a prefix of another, and
+/// two arrays or maps of the same length compare as their first differing
+/// items do. Items are therefore compared piece by piece, header first, using
+/// the memoized encodings of those that have them, and the encodings of map
+/// keys held by their maps.
+pub(crate) fn compare(a: Part<'_>, b: Part<'_>) -> cmp::Ordering {
+    let mut stack = vec![(a, b)];
+    while let Some((a, b)) = stack.pop() {
+        let ordering = match (a, b) {
+            (Part::Data(a), Part::Data(b)) => a.cmp(b),
+            (Part::Item(a), Part::Item(b)) if a.is_same_node(b) => cmp::Ordering::Equal,
+            (Part::Item(a), Part::Item(b)) if a.memoized_data().is_some() && b.memoized_data().is_some() => {
+                a.memoized_data().cmp(&b.memoized_data())
+            },
+            _ => {
+                let ((a_header, a_body), (b_header, b_body)) = (split(a), split(b));
+                match a_header.as_slice().cmp(b_header.as_slice()) {
+                    cmp::Ordering::Equal => {},
+                    ordering => return ordering,
+                }
+                // The headers are the same, so the items are of the same
+                // major type and length.
+                match (a_body, b_body) {
+                    (Body::Bytes(a), Body::Bytes(b)) => a.cmp(b),
+                    (Body::Bytes(a), Body::Text(b)) => a.cmp(b.as_bytes()),
+                    (Body::Text(a), Body::Bytes(b)) => a.as_bytes().cmp(b),
+                    (Body::Text(a), Body::Text(b)) => a.cmp(&b),
+                    (Body::Items(a), Body::Items(b)) => {
+                        stack.extend(a.iter().zip(b).rev().map(|(a, b)| (Part::Item(a), Part::Item(b))));
+                        cmp::Ordering::Equal
+                    },
+                    (Body::Map(a), Body::Map(b)) => {
+                        for ((a_key, a_value), (b_key, b_value)) in a.encoded_iter().zip(b.encoded_iter()).rev() {
+                            stack.push((Part::Item(a_value), Part::Item(b_value)));
+                            stack.push((Part::Data(a_key), Part::Data(b_key)));
+                        }
+                        cmp::Ordering::Equal
+                    },
+                    (Body::Item(a), Body::Item(b)) => {
+                        stack.push((Part::Item(a), Part::Item(b)));
+                        cmp::Ordering::Equal
+                    },
+                    _ => cmp::Ordering::Equal,
+                }
+            },
+        };
+        if ordering != cmp::Ordering::Equal {
+            return ordering;
+        }
+    }
+    cmp::Ordering::Equal
+}
@@ -0,0 +1,80 @@
+import_stdlib!();
+
+use crate::{decode::decode_cbor, TagValue, CBOR};
+
+/// One step in a [`CBORPath`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathElement {
+    /// The element at the given index of an array.
+    Index(usize),
+    /// The value associated with a key in a map, identified by the key's
+    /// encoded form.
+    Key(Vec<u8>),
+    /// The key of the entry at the given index of a map.
+    KeyAt(usize),
+    /// The content of a tagged value with the given tag.
+    Tag(TagValue),
+}
+
+impl PathElement {
+    /// Creates an element addressing the value associated with the given key.
+    pub fn key(key: impl Into<CBOR>) -> Self {
+        PathElement::Key(key.into().to_cbor_data())
+    }
+}
+
+impl fmt::Display for PathElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathElement::Index(index) => write!(f, "[{}]", index),
+            PathElement::Key(key_data) => match decode_cbor(key_data) {
+                Ok(key) => write!(f, "{{{}}}", key),
+                Err(_) => write!(f, "{{h'{}'}}", hex::encode(key_data)),
+            },
+            PathElement::KeyAt(index) => write!(f, "key({})", index),
+            PathElement::Tag(tag) => write!(f, "tag({})", tag),
+        }
+    }
+}
+
+/// The location of an item within a CBOR document, as the sequence of steps
+/// leading from the top-level item to it.
+///
+/// Paths are displayed with their elements separated by periods, e.g.
+/// `[3].{"name"}.tag(1)`. The path of the top-level item is empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CBORPath(Vec<PathElement>);
+
+impl CBORPath {
+    /// Creates a new path from the given elements.
+    pub fn new(elements: impl Into<Vec<PathElement>>) -> Self {
+        Self(elements.into())
+    }
+
+    /// Returns the elements of the path.
+    pub fn elements(&self) -> &[PathElement] {
+        &self.0
+    }
+
+    /// Returns `true` if this is the path of the top-level item.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Appends an element to the path.
+    pub fn push(&mut self, element: PathElement) {
+        self.0.push(element);
+    }
+}
+
+impl fmt::Display for CBORPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, element) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{}", element)?;
+        }
+        Ok(())
+    }
+}
@@ -4,19 +4,49 @@ pub use crate::{
     CBORCase,
     CBORCodable,
     CBORDecodable,
+    CBORDecoder,
     CBOREncodable,
     CBORError,
+    CBORPath,
+    CBORRef,
+    CBORRefCase,
     CBORTagged,
     CBORTaggedCodable,
     CBORTaggedDecodable,
     CBORTaggedEncodable,
+    CBORSequence,
     CBORSummarizer,
+    DecodeError,
+    DecodeOptions,
+    DecodeValidator,
+    DigestAlgorithm,
+    EncodedCBOR,
+    EncodedIndex,
+    EncodingProfile,
+    Event,
+    Events,
+    LintReport,
     Map,
+    MapRef,
+    PathElement,
+    Simple,
+    SimpleValue,
     Tag,
     TagValue,
     TagsStore,
     TagsStoreTrait,
+    Transformation,
+    TransformationKind,
+    ValidationSummary,
+    Violation,
+    ViolationKind,
     with_tags,
     with_tags_mut,
     tags_for_values,
 };
+
+#[cfg(feature = "std")]
+pub use crate::{CBORReader, CBORWriter};
+
+#[cfg(feature = "tokio")]
+pub use crate::{AsyncCBORReader, AsyncCBORWriter, CBORCodec};
@@ -0,0 +1,58 @@
+import_stdlib!();
+
+/// A set of rules for encoding CBOR, honored both when encoding and when
+/// decoding.
+///
+/// Every profile requires the shortest possible encoding of each integer,
+/// length, and tag, definite lengths, and the shortest encoding of each
+/// floating point number that preserves its value. Beyond that:
+///
+/// | Rule                                        | dCBOR | CDE | Preferred |
+/// |---------------------------------------------|-------|-----|-----------|
+/// | Map keys sorted by their encodings          | yes   | yes | no        |
+/// | Integral floats encoded as integers         | yes   | no  | no        |
+/// | NaN encoded only as `f97e00`                | yes   | no  | no        |
+/// | Only `false`, `true`, and `null` allowed    | yes   | no  | no        |
+/// | Text in Unicode Normalization Form C        | yes   | no  | no        |
+///
+/// Under the other profiles, NaNs keep their payloads, and other simple
+/// values, such as `undefined`, are decoded as [`Simple::Value`](crate::Simple::Value).
+/// Encoding such a value as dCBOR writes it as the other profiles do,
+/// leaving the decoder to reject it. Maps are always stored in the order of
+/// their keys' dCBOR encodings, but keys that are distinct under the profile
+/// they were decoded with and the same under dCBOR, such as `1` and `1.0`,
+/// are kept apart, and as dCBOR such a map has duplicate keys.
+///
+/// Numeric reduction and normalization also take place when a
+/// [`CBOR`](crate::CBOR) is created from a floating point number or text, so
+/// to encode an integral float as a float under another profile, create it
+/// from [`Simple::Float`](crate::Simple::Float), and to encode text that is
+/// not normalized, create it with
+/// [`CBOR::to_raw_text`](crate::CBOR::to_raw_text).
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let cbor = CBOR::from(vec![CBOR::from(Simple::Float(1.0)), CBOR::to_raw_text("e\u{301}")]);
+/// assert_eq!(cbor.hex(), "820162c3a9");
+/// let data = cbor.to_cbor_data_with_profile(EncodingProfile::CoreDeterministic);
+/// assert_eq!(hex::encode(&data), "82f93c006365cc81");
+///
+/// // dCBOR rejects the float and the unnormalized text.
+/// assert!(CBOR::try_from_data(&data).is_err());
+/// let options = DecodeOptions { profile: EncodingProfile::CoreDeterministic, ..Default::default() };
+/// assert_eq!(CBOR::try_from_data_with_options(&data, &options).unwrap(), cbor);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EncodingProfile {
+    /// Deterministic CBOR as specified by
+    /// [dCBOR](https://datatracker.ietf.org/doc/draft-mcnally-deterministic-cbor/).
+    #[default]
+    Dcbor,
+    /// Core deterministic encoding, as specified by [§4.2.1 of
+    /// RFC 8949](https://www.rfc-editor.org/rfc/rfc8949.html#name-core-deterministic-encoding).
+    CoreDeterministic,
+    /// Preferred serialization, as specified by [§4.1 of
+    /// RFC 8949](https://www.rfc-editor.org/rfc/rfc8949.html#name-preferred-serialization),
+    /// without requiring map keys to be sorted.
+    Preferred,
+}
@@ -0,0 +1,172 @@
+import_stdlib!();
+
+use std::io::{self, Read};
+
+use anyhow::{bail, Result};
+
+use crate::{decode::{decode_cbor_internal, DecodeLimits, Source}, CBORError, DecodeOptions, EncodingProfile, CBOR};
+
+/// A decoder that reads dCBOR items directly from a [`Read`] implementation.
+///
+/// Each item is held to the same deterministic encoding rules as
+/// [`CBOR::try_from_data`], and the [`DecodeOptions`] limits apply to each
+/// item separately. Items are decoded without first buffering their encoded
+/// form, so large files and sockets can be read without a second copy.
+///
+/// The reader is read a few bytes at a time, so wrap unbuffered sources such
+/// as files and sockets in a [`BufReader`](std::io::BufReader).
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let data: &[u8] = &hex_literal::hex!("0182020361");
+/// let mut reader = CBORReader::new(data);
+/// assert_eq!(reader.read_cbor().unwrap(), Some(CBOR::from(1)));
+/// assert_eq!(reader.read_cbor().unwrap(), Some(CBOR::from([2, 3])));
+/// // The last item is truncated.
+/// let error = reader.read_cbor().unwrap_err();
+/// assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::Underrun)));
+/// ```
+#[derive(Debug)]
+pub struct CBORReader<R> {
+    reader: R,
+    options: DecodeOptions,
+    position: usize,
+    peeked: Option<u8>,
+    failed: bool,
+}
+
+impl<R: Read> CBORReader<R> {
+    /// Creates a new reader that decodes items from `reader` without limits.
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, DecodeOptions::default())
+    }
+
+    /// Creates a new reader that decodes items from `reader`, enforcing the
+    /// given limits on each item.
+    pub fn with_options(reader: R, options: DecodeOptions) -> Self {
+        Self { reader, options, position: 0, peeked: None, failed: false }
+    }
+
+    /// Reads the next item.
+    ///
+    /// Returns `Ok(None)` if the underlying reader is at its end before the
+    /// first byte of an item. Returns [`CBORError::Underrun`] if it ends
+    /// partway through an item, and any other I/O error unchanged.
+    ///
+    /// After an error the position of the underlying reader within the
+    /// stream is unspecified.
+    pub fn read_cbor(&mut self) -> Result<Option<CBOR>> {
+        if self.peeked.is_none() {
+            let mut byte = [0u8; 1];
+            if self.fill(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            self.peeked = Some(byte[0]);
+        }
+        let options = self.options.clone();
+        let mut limits = DecodeLimits::new(&options);
+        decode_cbor_internal(self, &mut limits).map(Some)
+    }
+
+    /// Returns the number of bytes consumed from the underlying reader.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// Any byte read ahead to detect the end of the stream is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads as many bytes as are available into `buf`, up to its length,
+    /// returning the number read.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(filled)
+    }
+}
+
+impl<R: Read> Source for CBORReader<R> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        let mut start = 0;
+        if let (Some(byte), Some(first)) = (self.peeked, buf.first_mut()) {
+            *first = byte;
+            self.peeked = None;
+            self.position += 1;
+            start = 1;
+        }
+        let filled = self.fill(&mut buf[start..])?;
+        self.position += filled;
+        if start + filled < buf.len() {
+            bail!(CBORError::Underrun);
+        }
+        Ok(())
+    }
+
+    fn read_content(&mut self, len: usize) -> Result<Vec<u8>> {
+        // The content is read incrementally rather than into a buffer of the
+        // declared length, so a bogus length cannot force a huge allocation.
+        let mut content = Vec::new();
+        loop {
+            match (&mut self.reader).take((len - content.len()) as u64).read_to_end(&mut content) {
+                Ok(_) => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => return Err(error.into()),
+            }
+        }
+        self.position += content.len();
+        if content.len() < len {
+            bail!(CBORError::Underrun);
+        }
+        Ok(content)
+    }
+
+    fn key_data(&self, _start: usize, key: &CBOR, profile: EncodingProfile) -> Vec<u8> {
+        // The key has been validated as following the profile, so encoding
+        // it again reproduces the bytes it was read from.
+        key.to_cbor_data_with_profile(profile)
+    }
+}
+
+impl<R: Read> Iterator for CBORReader<R> {
+    type Item = Result<CBOR>;
+
+    /// Reads the next item, ending after the underlying reader ends or the
+    /// first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.read_cbor();
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
@@ -0,0 +1,156 @@
+import_stdlib!();
+
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use anyhow::Result;
+
+#[cfg(feature = "std")]
+use crate::encode::{encode_to, WriteSink};
+use crate::{encode::{encode, EncodeTask}, decode::{decode_cbor_internal, DecodeLimits, SliceSource}, DecodeOptions, EncodingProfile, CBOR};
+
+/// Affordances for CBOR Sequences ([RFC 8742](https://www.rfc-editor.org/rfc/rfc8742.html)),
+/// in which items are stored back to back with no other framing.
+impl CBOR {
+    /// Decodes the item at the start of `data`, returning it along with the
+    /// bytes that follow it.
+    ///
+    /// The item is held to the same rules as
+    /// [`try_from_data`](Self::try_from_data), but any bytes after it are
+    /// returned rather than rejected.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let data = hex_literal::hex!("01 820203 61");
+    /// let (first, rest) = CBOR::try_from_data_prefix(&data).unwrap();
+    /// assert_eq!(first, CBOR::from(1));
+    /// assert_eq!(rest, hex_literal::hex!("820203 61"));
+    /// ```
+    pub fn try_from_data_prefix(data: &[u8]) -> Result<(CBOR, &[u8])> {
+        Self::try_from_data_prefix_with_options(data, &DecodeOptions::default())
+    }
+
+    /// Decodes the item at the start of `data`, enforcing the limits in
+    /// `options`, and returns it along with the bytes that follow it.
+    pub fn try_from_data_prefix_with_options<'a>(data: &'a [u8], options: &DecodeOptions) -> Result<(CBOR, &'a [u8])> {
+        let mut limits = DecodeLimits::new(options);
+        let mut source = SliceSource { data, pos: 0 };
+        let cbor = decode_cbor_internal(&mut source, &mut limits)?;
+        Ok((cbor, &data[source.pos..]))
+    }
+
+    /// Appends the encoded form of this item to `buf`.
+    ///
+    /// Appending several items to the same buffer produces a CBOR Sequence.
+    pub fn append_cbor_data(&self, buf: &mut Vec<u8>) {
+        encode(EncodeTask::Item(self), EncodingProfile::Dcbor, buf);
+    }
+
+    /// Writes the encoded form of this item to `writer`.
+    ///
+    /// The encoding is written as it is produced, without first being
+    /// collected in a buffer, so unbuffered writers such as files and sockets
+    /// are best wrapped in a [`BufWriter`](std::io::BufWriter).
+    ///
+    /// Writing several items to the same writer produces a CBOR Sequence.
+    #[cfg(feature = "std")]
+    pub fn write_cbor_data(&self, writer: &mut (impl Write + ?Sized)) -> io::Result<()> {
+        encode_to(EncodeTask::Item(self), EncodingProfile::Dcbor, &mut WriteSink(writer))
+    }
+}
+
+/// Returns the encoding of the CBOR Sequence made up of `items`.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let items = [CBOR::from(1), CBOR::from("a")];
+/// assert_eq!(dcbor::encode_sequence(&items), hex_literal::hex!("01 6161"));
+/// ```
+pub fn encode_sequence<I>(items: I) -> Vec<u8>
+where
+    I: IntoIterator,
+    I::Item: Borrow<CBOR>,
+{
+    let mut buf = Vec::new();
+    for item in items {
+        item.borrow().append_cbor_data(&mut buf);
+    }
+    buf
+}
+
+/// Returns an iterator over the items of the CBOR Sequence in `data`.
+///
+/// See [`CBORSequence`].
+pub fn decode_sequence(data: &[u8]) -> CBORSequence<'_> {
+    CBORSequence::new(data)
+}
+
+/// An iterator over the items of a CBOR Sequence held in a slice.
+///
+/// Each item is held to the same deterministic encoding rules as
+/// [`CBOR::try_from_data`], and the [`DecodeOptions`] limits apply to each
+/// item separately. The offsets of any errors are relative to the start of
+/// the whole sequence. The iterator ends at the end of the data, or after the
+/// first error.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let data = hex_literal::hex!("01 820203 1817");
+/// let mut items = dcbor::decode_sequence(&data);
+/// assert_eq!(items.next().unwrap().unwrap(), CBOR::from(1));
+/// assert_eq!(items.next().unwrap().unwrap(), CBOR::from([2, 3]));
+/// // The last item is not deterministically encoded.
+/// let error = items.next().unwrap().unwrap_err();
+/// assert_eq!(error.downcast_ref::<DecodeError>().unwrap().offset(), 4);
+/// assert!(items.next().is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CBORSequence<'a> {
+    data: &'a [u8],
+    pos: usize,
+    options: DecodeOptions,
+    failed: bool,
+}
+
+impl<'a> CBORSequence<'a> {
+    /// Creates an iterator over the items in `data` without limits.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_options(data, DecodeOptions::default())
+    }
+
+    /// Creates an iterator over the items in `data`, enforcing the given
+    /// limits on each item.
+    pub fn with_options(data: &'a [u8], options: DecodeOptions) -> Self {
+        Self { data, pos: 0, options, failed: false }
+    }
+
+    /// Returns the offset of the next item.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the bytes that have not yet been decoded.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+}
+
+impl Iterator for CBORSequence<'_> {
+    type Item = Result<CBOR>;
+
+    /// Decodes the next item, ending after the end of the data or the first
+    /// error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos == self.data.len() {
+            return None;
+        }
+        let mut limits = DecodeLimits::new(&self.options);
+        let mut source = SliceSource { data: self.data, pos: self.pos };
+        let result = decode_cbor_internal(&mut source, &mut limits);
+        match result {
+            Ok(_) => self.pos = source.pos,
+            Err(_) => self.failed = true,
+        }
+        Some(result)
+    }
+}
@@ -2,9 +2,9 @@ import_stdlib!();
 
 use anyhow::{bail, Error, Result};
 
-use crate::{float::f64_cbor_data, CBORCase, CBORError, CBOR};
+use crate::{float::f64_header, CBORCase, CBORError, CBORRef, CBOR};
 
-use super::varint::{EncodeVarInt, MajorType};
+use super::varint::{Header, MajorType};
 
 /// A CBOR simple value.
 #[derive(Clone)]
@@ -17,6 +17,51 @@ pub enum Simple {
     Null,
     /// A floating point value.
     Float(f64),
+    /// Any other simple value, such as 23 (`undefined`).
+    ///
+    /// Such values are only allowed by encoding profiles other than dCBOR.
+    Value(SimpleValue),
+}
+
+/// A simple value other than `false`, `true`, or `null`, such as 23
+/// (`undefined`).
+///
+/// Values 20, 21, and 22 cannot be created, as they are `false`, `true`, and
+/// `null`, and nor can values from 24 to 31, which are not well-formed.
+///
+/// dCBOR does not allow these values, so a [`CBOR`] containing one can only
+/// be encoded under another [`EncodingProfile`](crate::EncodingProfile), with
+/// [`CBOR::to_cbor_data_with_profile`], and cannot be a map key.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let undefined = CBOR::from(Simple::Value(SimpleValue::UNDEFINED));
+/// assert_eq!(undefined.to_cbor_data_with_profile(EncodingProfile::CoreDeterministic), [0xf7]);
+/// assert_eq!(SimpleValue::new(32).unwrap().value(), 32);
+/// assert!(matches!(SimpleValue::new(20), Err(CBORError::InvalidSimpleValue)));
+/// assert!(matches!(SimpleValue::new(25), Err(CBORError::InvalidSimpleValue)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SimpleValue(u8);
+
+impl SimpleValue {
+    /// The value `undefined`.
+    pub const UNDEFINED: SimpleValue = SimpleValue(23);
+
+    /// Creates the simple value `value`, failing with
+    /// [`CBORError::InvalidSimpleValue`] if it is `false`, `true`, `null`, or
+    /// from 24 to 31.
+    pub fn new(value: u8) -> Result<Self, CBORError> {
+        match value {
+            20..=22 | 24..=31 => Err(CBORError::InvalidSimpleValue),
+            _ => Ok(Self(value)),
+        }
+    }
+
+    /// Returns the number of the simple value.
+    pub fn value(&self) -> u8 {
+        self.0
+    }
 }
 
 impl Simple {
@@ -26,11 +71,17 @@ impl Simple {
     }
 
     pub fn cbor_data(&self) -> Vec<u8> {
+        self.header().as_slice().to_vec()
+    }
+
+    /// Returns the encoding of the value, which consists only of its header.
+    pub(crate) fn header(&self) -> Header {
         match self {
-            Self::False => 20u8.encode_varint(MajorType::Simple),
-            Self::True => 21u8.encode_varint(MajorType::Simple),
-            Self::Null => 22u8.encode_varint(MajorType::Simple),
-            Self::Float(v) => f64_cbor_data(*v),
+            Self::False => Header::new(MajorType::Simple, 20),
+            Self::True => Header::new(MajorType::Simple, 21),
+            Self::Null => Header::new(MajorType::Simple, 22),
+            Self::Float(v) => f64_header(*v),
+            Self::Value(v) => Header::new(MajorType::Simple, v.value() as u64),
         }
     }
 }
@@ -52,15 +103,37 @@ impl TryFrom<CBOR> for Simple {
     }
 }
 
+impl<'a> TryFrom<CBORRef<'a>> for Simple {
+    type Error = Error;
+
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        cbor.try_into_simple_value()
+    }
+}
+
 impl PartialEq for Simple {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::False, Self::False) => true,
-            (Self::True, Self::True) => true,
-            (Self::Null, Self::Null) => true,
-            (Self::Float(v1), Self::Float(v2)) => v1 == v2,
-            _ => false,
-        }
+        self.header().as_slice() == other.header().as_slice()
+    }
+}
+
+impl Eq for Simple { }
+
+impl PartialOrd for Simple {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Simple {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.header().as_slice().cmp(other.header().as_slice())
+    }
+}
+
+impl hash::Hash for Simple {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.header().as_slice().hash(state);
     }
 }
 
@@ -71,6 +144,8 @@ impl fmt::Debug for Simple {
             Self::True => "true".to_owned(),
             Self::Null => "null".to_owned(),
             Self::Float(v) => format!("{:?}", v),
+            Self::Value(SimpleValue::UNDEFINED) => "undefined".to_owned(),
+            Self::Value(v) => v.value().to_string(),
         };
         f.write_str(&s)
     }
@@ -83,6 +158,8 @@ impl fmt::Display for Simple {
             Self::True => "true".to_owned(),
             Self::Null => "null".to_owned(),
             Self::Float(v) => format!("{:?}", v),
+            Self::Value(SimpleValue::UNDEFINED) => "undefined".to_owned(),
+            Self::Value(v) => format!("simple({})", v.value()),
         };
         f.write_str(&s)
     }
@@ -2,11 +2,12 @@
 pub mod with_std {
     pub use std::{fmt, str::FromStr};
 
+    pub use std::any::{Any, TypeId};
     pub use std::array::TryFromSliceError;
     pub use std::string::String;
     pub use std::vec::Vec;
     pub use std::string::ToString;
-    pub use std::borrow::ToOwned;
+    pub use std::borrow::{Borrow, ToOwned};
     pub use std::boxed::Box;
     pub use std::sync::Arc;
     pub use std::collections::{BTreeMap, btree_map::Values as BTreeMapValues, VecDeque, HashSet, HashMap};
@@ -15,6 +16,7 @@ pub mod with_std {
     pub use std::sync::{self};
     pub use std::ops::{self, Deref};
     pub use std::cmp::{self};
+    pub use std::mem;
     pub use std::str::{self};
     pub use std::time::Duration;
     pub use std::format;
@@ -26,6 +28,7 @@ pub mod with_std {
 pub mod without_std {
     extern crate alloc;
 
+    pub use core::any::{Any, TypeId};
     pub use core::array::TryFromSliceError;
     pub use alloc::fmt::{self};
     pub use alloc::string::String;
@@ -38,12 +41,13 @@ pub mod without_std {
     pub use core::hash::{self};
     pub use core::ops::{self, Deref};
     pub use core::cmp::{self};
+    pub use core::mem;
     pub use core::time::Duration;
     pub use alloc::rc::{self};
     pub use alloc::sync::{self};
     pub use alloc::string::ToString;
     pub use alloc::str::{self};
-    pub use alloc::borrow::ToOwned;
+    pub use alloc::borrow::{Borrow, ToOwned};
     pub use alloc::format;
     pub use thiserror_no_std::Error as ThisError;
 
@@ -2,17 +2,33 @@ import_stdlib!();
 
 use anyhow::{bail, Error, Result};
 
-use crate::{CBOR, CBORError, CBORCase};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
 
+use crate::{CBOR, CBORError, CBORCase, CBORRef};
+
+/// Text is normalized to Unicode Normalization Form C, as dCBOR requires, so
+/// that texts that encode the same compare equal. Text that is already
+/// normalized is stored as given.
 impl From<&str> for CBOR {
     fn from(value: &str) -> Self {
-        CBORCase::Text(value.to_string()).into()
+        if is_nfc(value) {
+            CBORCase::Text(value.to_string()).into()
+        } else {
+            CBORCase::Text(value.nfc().collect()).into()
+        }
     }
 }
 
+/// Text is normalized to Unicode Normalization Form C, as dCBOR requires, so
+/// that texts that encode the same compare equal. Text that is already
+/// normalized is stored as given, without being copied.
 impl From<String> for CBOR {
     fn from(value: String) -> Self {
-        CBORCase::Text(value.clone()).into()
+        if is_nfc(&value) {
+            CBORCase::Text(value).into()
+        } else {
+            CBOR::from(value.as_str())
+        }
     }
 }
 
@@ -25,3 +41,17 @@ impl TryFrom<CBOR> for String {
         }
     }
 }
+
+impl<'a> TryFrom<CBORRef<'a>> for &'a str {
+    type Error = Error;
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        cbor.try_into_text()
+    }
+}
+
+impl<'a> TryFrom<CBORRef<'a>> for String {
+    type Error = Error;
+    fn try_from(cbor: CBORRef<'a>) -> Result<Self> {
+        Ok(cbor.try_into_text()?.to_owned())
+    }
+}
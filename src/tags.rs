@@ -1,14 +1,22 @@
-use std::sync::{ Arc, Mutex, Once };
+import_stdlib!();
 
-use crate::{CBORTaggedDecodable, Date, Tag, TagValue, TagsStore, TagsStoreTrait};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, MutexGuard, Once};
 
+#[cfg(not(feature = "std"))]
+use core::{cell::UnsafeCell, sync::atomic::{AtomicBool, Ordering}};
+
+use crate::{bignum::integer_to_decimal, BigFloat, CBORTaggedDecodable, Date, DecimalFraction, Tag, TagValue, TagsStore, TagsStoreTrait};
+
+#[cfg(feature = "std")]
 pub struct LazyTagsStore {
     init: Once,
     data: Mutex<Option<TagsStore>>,
 }
 
+#[cfg(feature = "std")]
 impl LazyTagsStore {
-    pub fn get(&self) -> std::sync::MutexGuard<'_, Option<TagsStore>> {
+    pub fn get(&self) -> MutexGuard<'_, Option<TagsStore>> {
         self.init.call_once(|| {
             let m = TagsStore::new([]);
             *self.data.lock().unwrap() = Some(m);
@@ -17,11 +25,72 @@ impl LazyTagsStore {
     }
 }
 
+#[cfg(feature = "std")]
 pub static GLOBAL_TAGS: LazyTagsStore = LazyTagsStore {
     init: Once::new(),
     data: Mutex::new(None),
 };
 
+/// Without `std` there is no `Mutex`, so the global tags store is guarded by
+/// a spin lock.
+#[cfg(not(feature = "std"))]
+pub struct LazyTagsStore {
+    locked: AtomicBool,
+    data: UnsafeCell<Option<TagsStore>>,
+}
+
+// SAFETY: `data` is only accessed through a `LazyTagsStoreGuard`, of which
+// `locked` ensures there is at most one at a time.
+#[cfg(not(feature = "std"))]
+unsafe impl Sync for LazyTagsStore { }
+
+#[cfg(not(feature = "std"))]
+impl LazyTagsStore {
+    pub fn get(&self) -> LazyTagsStoreGuard<'_> {
+        while self.locked.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            core::hint::spin_loop();
+        }
+        let mut guard = LazyTagsStoreGuard(self);
+        guard.get_or_insert_with(|| TagsStore::new([]));
+        guard
+    }
+}
+
+/// Exclusive access to the global tags store, released when dropped.
+#[cfg(not(feature = "std"))]
+pub struct LazyTagsStoreGuard<'a>(&'a LazyTagsStore);
+
+#[cfg(not(feature = "std"))]
+impl Deref for LazyTagsStoreGuard<'_> {
+    type Target = Option<TagsStore>;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the guard holds the lock.
+        unsafe { &*self.0.data.get() }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl ops::DerefMut for LazyTagsStoreGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: the guard holds the lock.
+        unsafe { &mut *self.0.data.get() }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Drop for LazyTagsStoreGuard<'_> {
+    fn drop(&mut self) {
+        self.0.locked.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub static GLOBAL_TAGS: LazyTagsStore = LazyTagsStore {
+    locked: AtomicBool::new(false),
+    data: UnsafeCell::new(None),
+};
+
 /// A macro for accessing the global tags store.
 #[macro_export]
 macro_rules! with_tags {
@@ -49,10 +118,18 @@ macro_rules! with_tags_mut {
 }
 
 pub const TAG_DATE: TagValue = 1;
+pub const TAG_POSITIVE_BIGNUM: TagValue = 2;
+pub const TAG_NEGATIVE_BIGNUM: TagValue = 3;
+pub const TAG_DECIMAL_FRACTION: TagValue = 4;
+pub const TAG_BIGFLOAT: TagValue = 5;
 
 pub fn register_tags_in(tags_store: &mut TagsStore) {
     let tags = vec![
         (TAG_DATE, "date"),
+        (TAG_POSITIVE_BIGNUM, "positive-bignum"),
+        (TAG_NEGATIVE_BIGNUM, "negative-bignum"),
+        (TAG_DECIMAL_FRACTION, "decimal-fraction"),
+        (TAG_BIGFLOAT, "bigfloat"),
     ];
     for tag in tags.into_iter() {
         tags_store.insert(Tag::new(tag.0, tag.1));
@@ -60,6 +137,21 @@ pub fn register_tags_in(tags_store: &mut TagsStore) {
     tags_store.set_summarizer(TAG_DATE, Arc::new(|untagged_cbor| {
         Ok(format!("{}", Date::from_untagged_cbor(untagged_cbor)?))
     }));
+    tags_store.set_summarizer(TAG_POSITIVE_BIGNUM, Arc::new(|untagged_cbor| {
+        Ok(integer_to_decimal(false, &untagged_cbor.try_into_byte_string()?))
+    }));
+    tags_store.set_summarizer(TAG_NEGATIVE_BIGNUM, Arc::new(|untagged_cbor| {
+        Ok(integer_to_decimal(true, &untagged_cbor.try_into_byte_string()?))
+    }));
+    tags_store.set_summarizer(TAG_DECIMAL_FRACTION, Arc::new(|untagged_cbor| {
+        Ok(format!("{}", DecimalFraction::from_untagged_cbor(untagged_cbor)?))
+    }));
+    tags_store.set_summarizer(TAG_BIGFLOAT, Arc::new(|untagged_cbor| {
+        Ok(format!("{}", BigFloat::from_untagged_cbor(untagged_cbor)?))
+    }));
+    tags_store.register_decoder::<Date>();
+    tags_store.register_decoder::<DecimalFraction>();
+    tags_store.register_decoder::<BigFloat>();
 }
 
 pub fn register_tags() {
@@ -1,9 +1,15 @@
 import_stdlib!();
 
-use crate::{Tag, TagValue, CBOR};
+use anyhow::{bail, Result};
+
+use crate::{CBORCase, CBORError, CBORTaggedDecodable, Tag, TagValue, CBOR};
 
 pub type CBORSummarizer = Arc<dyn Fn(CBOR) -> anyhow::Result<String> + Send + Sync>;
 
+/// A function that decodes the untagged content of a tagged item into a
+/// value of some type.
+pub type CBORDecoder = Arc<dyn Fn(CBOR) -> anyhow::Result<Box<dyn Any>> + Send + Sync>;
+
 /// A type that can map between tags and their names.
 pub trait TagsStoreTrait {
     fn assigned_name_for_tag(&self, tag: &Tag) -> Option<String>;
@@ -27,6 +33,7 @@ pub struct TagsStore {
     tags_by_value: HashMap<u64, Tag>,
     tags_by_name: HashMap<String, Tag>,
     summarizers: HashMap<u64, CBORSummarizer>,
+    decoders: HashMap<u64, CBORDecoder>,
 }
 
 impl TagsStore {
@@ -40,6 +47,7 @@ impl TagsStore {
             tags_by_value,
             tags_by_name,
             summarizers: HashMap::new(),
+            decoders: HashMap::new(),
         }
     }
 
@@ -51,6 +59,91 @@ impl TagsStore {
         self.summarizers.insert(tag, summarizer);
     }
 
+    /// Sets the decoder used by [`decode_any`](Self::decode_any) for items
+    /// with the given tag.
+    pub fn set_decoder(&mut self, tag: TagValue, decoder: CBORDecoder) {
+        self.decoders.insert(tag, decoder);
+    }
+
+    /// Returns the decoder for items with the given tag, if one is
+    /// registered.
+    pub fn decoder(&self, tag: TagValue) -> Option<&CBORDecoder> {
+        self.decoders.get(&tag)
+    }
+
+    /// Registers `T` as the decoder for items with any of its tags.
+    ///
+    /// ```
+    /// # use dcbor::{prelude::*, Date};
+    /// let mut tags = TagsStore::default();
+    /// tags.register_decoder::<Date>();
+    /// let cbor = Date::from_ymd(2024, 1, 1).tagged_cbor();
+    /// let date = tags.decode_any(cbor).unwrap().downcast::<Date>().unwrap();
+    /// assert_eq!(*date, Date::from_ymd(2024, 1, 1));
+    /// ```
+    pub fn register_decoder<T>(&mut self) where T: CBORTaggedDecodable + 'static {
+        self.register_decoder_with(|value: T| value);
+    }
+
+    /// Registers `T` as the decoder for items with any of its tags, passing
+    /// each decoded value through `f`.
+    ///
+    /// This lets several types decode into the variants of a single enum,
+    /// which [`decode_as`](Self::decode_as) then returns.
+    pub fn register_decoder_with<T, U, F>(&mut self, f: F)
+    where
+        T: CBORTaggedDecodable + 'static,
+        U: 'static,
+        F: Fn(T) -> U + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        for tag in T::cbor_tags() {
+            let f = f.clone();
+            self.set_decoder(tag.value(), Arc::new(move |untagged_cbor| {
+                Ok(Box::new(f(T::from_untagged_cbor(untagged_cbor)?)))
+            }));
+        }
+    }
+
+    /// Decodes a tagged item using the decoder registered for its tag.
+    ///
+    /// Returns [`CBORError::WrongType`] if the item is not tagged, and
+    /// [`CBORError::UnregisteredTag`] if no decoder is registered for its tag.
+    pub fn decode_any(&self, cbor: CBOR) -> Result<Box<dyn Any>> {
+        let CBORCase::Tagged(tag, item) = cbor.into_case() else {
+            bail!(CBORError::WrongType);
+        };
+        match self.decoder(tag.value()) {
+            Some(decoder) => decoder(item),
+            None => bail!(CBORError::UnregisteredTag(tag)),
+        }
+    }
+
+    /// Decodes a tagged item using the decoder registered for its tag, which
+    /// must produce a `U`.
+    ///
+    /// Returns [`CBORError::WrongType`] if the decoder produces some other
+    /// type.
+    ///
+    /// ```
+    /// # use dcbor::{prelude::*, Date};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Payload {
+    ///     Date(Date),
+    /// }
+    ///
+    /// let mut tags = TagsStore::default();
+    /// tags.register_decoder_with(Payload::Date);
+    /// let cbor = Date::from_ymd(2024, 1, 1).tagged_cbor();
+    /// assert_eq!(tags.decode_as::<Payload>(cbor).unwrap(), Payload::Date(Date::from_ymd(2024, 1, 1)));
+    /// ```
+    pub fn decode_as<U>(&self, cbor: CBOR) -> Result<U> where U: 'static {
+        match self.decode_any(cbor)?.downcast::<U>() {
+            Ok(value) => Ok(*value),
+            Err(_) => bail!(CBORError::WrongType),
+        }
+    }
+
     fn _insert(tag: Tag, tags_by_value: &mut HashMap<u64, Tag>, tags_by_name: &mut HashMap<String, Tag>) {
         let name = tag.name().unwrap();
         assert!(!name.is_empty());
@@ -0,0 +1,222 @@
+import_stdlib!();
+
+use crate::{bignum::{is_bignum_tag, validate_bignum}, decode::{decode_simple, parse_bytes, parse_header_lenient, parse_header_varint, validate_text}, varint::MajorType, CBORError};
+
+/// Counts of the items found by [`validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ValidationSummary {
+    /// The total number of items, including every array, map, tag, and the
+    /// keys and values of maps.
+    pub items: usize,
+    /// The number of arrays.
+    pub arrays: usize,
+    /// The number of maps.
+    pub maps: usize,
+    /// The number of tags.
+    pub tags: usize,
+    /// The total length in bytes of the content of all byte strings and text
+    /// strings.
+    pub string_bytes: usize,
+}
+
+/// The number of nested maps whose keys [`validate`] compares as it reaches
+/// them. The keys of maps nested more deeply are compared by a separate scan
+/// of each such map, so that the space used is fixed.
+const MAP_STACK_LEN: usize = 16;
+
+/// A map that [`validate`] has not yet reached the end of.
+#[derive(Clone, Copy, Default)]
+struct OpenMap {
+    /// The number of items still expected once the map has ended.
+    end: u64,
+    /// The number of items still expected once the current key or value has
+    /// ended.
+    next: u64,
+    /// The offset of the current key, or of the next key if the current item
+    /// is a value.
+    key_start: usize,
+    /// The extent of the previous key, which is empty before the first key.
+    previous_key: (usize, usize),
+}
+
+/// Checks that `data` is a single well-formed deterministic CBOR item,
+/// without decoding it.
+///
+/// The data is held to the same rules as
+/// [`CBOR::try_from_data`](crate::CBOR::try_from_data), and is accepted or
+/// rejected exactly when it would be. No tree is built, no memory is
+/// allocated, and the stack space used does not depend on the data. The data
+/// is read in a single pass, with map keys compared by their encoded byte
+/// ranges as each one ends, except that the keys of maps within 16 or more
+/// other maps of several entries are compared by scanning each such map
+/// again, so such deeply nested data takes longer to validate. When the data
+/// has several problems, the one reported may differ from the one the decoder
+/// reports.
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// // [1, {"a": h'0102'}]
+/// let summary = dcbor::validate(&hex_literal::hex!("8201a1616142 0102")).unwrap();
+/// assert_eq!(summary.items, 5);
+/// assert_eq!(summary.maps, 1);
+/// assert_eq!(summary.string_bytes, 3);
+///
+/// // {2: 0, 1: 0}
+/// let error = dcbor::validate(&hex_literal::hex!("a202000100")).unwrap_err();
+/// assert!(matches!(error, CBORError::MisorderedMapKey));
+/// ```
+pub fn validate(data: &[u8]) -> Result<ValidationSummary, CBORError> {
+    let mut summary = ValidationSummary::default();
+    let mut pos = 0;
+    // The number of items still expected. Arrays and tags need nothing more
+    // than this to be checked, so only maps, whose keys must be compared,
+    // are kept on a stack.
+    let mut pending: u64 = 1;
+    let mut maps = [OpenMap::default(); MAP_STACK_LEN];
+    let mut depth = 0;
+    // Whether the next item is the content of a bignum, which must be a byte
+    // string.
+    let mut bignum_next = false;
+    while pending > 0 {
+        let (major_type, value, header_len) = parse_header_varint(&data[pos..])?;
+        let is_bignum = mem::take(&mut bignum_next);
+        if is_bignum && !matches!(major_type, MajorType::ByteString) {
+            return Err(CBORError::WrongType);
+        }
+        pos += header_len;
+        pending -= 1;
+        summary.items += 1;
+        let remaining = (data.len() - pos) as u64;
+        match major_type {
+            MajorType::Unsigned | MajorType::Negative => {},
+            MajorType::ByteString | MajorType::Text => {
+                if value > remaining {
+                    return Err(CBORError::Underrun);
+                }
+                let content = parse_bytes(&data[pos..], value as usize)?;
+                if matches!(major_type, MajorType::Text) {
+                    validate_text(content)?;
+                } else if is_bignum {
+                    validate_bignum(content)?;
+                }
+                pos += content.len();
+                summary.string_bytes += content.len();
+            },
+            MajorType::Array => {
+                summary.arrays += 1;
+                pending = pending.saturating_add(value);
+            },
+            MajorType::Map => {
+                summary.maps += 1;
+                // Every entry occupies at least two bytes.
+                if value > remaining / 2 {
+                    return Err(CBORError::Underrun);
+                }
+                // The keys of a map of one entry need no comparing.
+                if value > 1 {
+                    if depth < MAP_STACK_LEN {
+                        maps[depth] = OpenMap { end: pending, next: pending + value * 2 - 1, key_start: pos, previous_key: (0, 0) };
+                        depth += 1;
+                    } else {
+                        check_map_keys(&data[pos..], value)?;
+                    }
+                }
+                pending += value * 2;
+            },
+            MajorType::Tagged => {
+                summary.tags += 1;
+                pending += 1;
+                bignum_next = is_bignum_tag(value);
+            },
+            MajorType::Simple => {
+                decode_simple(value, header_len)?;
+            },
+        }
+        // Every expected item occupies at least one byte.
+        if pending > (data.len() - pos) as u64 {
+            return Err(CBORError::Underrun);
+        }
+        // End the keys and values, and then the maps, that end here.
+        while depth > 0 && maps[depth - 1].next == pending {
+            let map = &mut maps[depth - 1];
+            if (map.next - map.end) % 2 == 1 {
+                let (start, end) = map.previous_key;
+                let (previous_key, key) = (&data[start..end], &data[map.key_start..pos]);
+                if end > start {
+                    if previous_key == key {
+                        return Err(CBORError::DuplicateMapKey);
+                    }
+                    if previous_key > key {
+                        return Err(CBORError::MisorderedMapKey);
+                    }
+                }
+                map.previous_key = (map.key_start, pos);
+            } else {
+                map.key_start = pos;
+            }
+            if map.next == map.end {
+                depth -= 1;
+            } else {
+                map.next -= 1;
+                break;
+            }
+        }
+    }
+    let remaining = data.len() - pos;
+    if remaining > 0 {
+        return Err(CBORError::UnusedData(remaining));
+    }
+    Ok(summary)
+}
+
+/// Checks that the keys of the map whose `len` entries begin at the start of
+/// `data` are in increasing order.
+fn check_map_keys(data: &[u8], len: u64) -> Result<(), CBORError> {
+    let mut pos = 0;
+    let mut previous_key: Option<&[u8]> = None;
+    for _ in 0..len {
+        let key_len = skip_item(&data[pos..])?;
+        let key = &data[pos..pos + key_len];
+        if let Some(previous_key) = previous_key {
+            match previous_key.cmp(key) {
+                cmp::Ordering::Less => {},
+                cmp::Ordering::Equal => return Err(CBORError::DuplicateMapKey),
+                cmp::Ordering::Greater => return Err(CBORError::MisorderedMapKey),
+            }
+        }
+        previous_key = Some(key);
+        pos += key_len;
+        pos += skip_item(&data[pos..])?;
+    }
+    Ok(())
+}
+
+/// Returns the length of the encoding of the item at the start of `data`.
+///
+/// Only the structure of the item is examined, so this succeeds for any
+/// well-formed definite-length CBOR, deterministic or not.
+pub(crate) fn skip_item(data: &[u8]) -> Result<usize, CBORError> {
+    let mut pos = 0;
+    let mut pending: u64 = 1;
+    while pending > 0 {
+        let (major_type, value, header_len) = parse_header_lenient(&data[pos..])?;
+        pos += header_len;
+        pending -= 1;
+        match major_type {
+            MajorType::ByteString | MajorType::Text => {
+                if value > (data.len() - pos) as u64 {
+                    return Err(CBORError::Underrun);
+                }
+                pos += value as usize;
+            },
+            MajorType::Array => pending = pending.saturating_add(value),
+            MajorType::Map => pending = pending.saturating_add(value.saturating_mul(2)),
+            MajorType::Tagged => pending += 1,
+            MajorType::Unsigned | MajorType::Negative | MajorType::Simple => {},
+        }
+        if pending > (data.len() - pos) as u64 {
+            return Err(CBORError::Underrun);
+        }
+    }
+    Ok(pos)
+}
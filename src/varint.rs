@@ -26,6 +26,52 @@ fn type_bits(t: MajorType) -> u8 {
     b << 5
 }
 
+/// An encoded header, held without allocating.
+pub(crate) struct Header {
+    data: [u8; 9],
+    len: usize,
+}
+
+impl Header {
+    /// Returns the header with the given major type and value, encoded in its
+    /// minimal form.
+    pub(crate) fn new(major_type: MajorType, value: u64) -> Self {
+        let len = if value <= 23 {
+            1
+        } else if value <= u8::MAX as u64 {
+            2
+        } else if value <= u16::MAX as u64 {
+            3
+        } else if value <= u32::MAX as u64 {
+            5
+        } else {
+            9
+        };
+        Self::with_len(major_type, value, len)
+    }
+
+    /// Returns the header with the given major type and value, which is
+    /// encoded in the initial byte if `len` is 1, or otherwise in the `len -
+    /// 1` bytes that follow it, where `len` is 2, 3, 5, or 9.
+    pub(crate) fn with_len(major_type: MajorType, value: u64, len: usize) -> Self {
+        let additional = match len {
+            1 => value as u8,
+            2 => 24,
+            3 => 25,
+            5 => 26,
+            _ => 27,
+        };
+        let mut data = [0; 9];
+        data[0] = type_bits(major_type) | additional;
+        data[1..len].copy_from_slice(&value.to_be_bytes()[9 - len..]);
+        Self { data, len }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
 pub trait EncodeVarInt {
     fn encode_varint(&self, major_type: MajorType) -> Vec<u8>;
     fn encode_int(&self, major_type: MajorType) -> Vec<u8>;
@@ -0,0 +1,365 @@
+import_stdlib!();
+
+use std::io::{self, Write};
+
+use anyhow::{bail, Result};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::{bignum::{is_bignum_tag, validate_bignum}, encode::{encode_to, EncodeSink, EncodeTask}, float::f64_header, varint::{Header, MajorType}, CBORCase, CBORError, EncodingProfile, Simple, Tag, CBOR};
+
+/// An encoder that writes dCBOR to a [`Write`] implementation one item at a
+/// time, so that large arrays and maps can be written without first being
+/// built in memory.
+///
+/// Arrays and maps are begun with their lengths, and are complete once that
+/// many items, or key-value pairs, have been written into them. Tags apply to
+/// the single item written after them. Any item, including one within an
+/// array or map, can also be written whole from a [`CBOR`] with
+/// [`write_cbor`](Self::write_cbor). Items written at the top level follow
+/// one another, forming a CBOR Sequence.
+///
+/// The writer holds its output to the same rules as the decoder:
+///
+/// - Map keys must be written in increasing order of their encodings, and a
+///   key that is not is rejected with [`CBORError::MisorderedMapKey`] or
+///   [`CBORError::DuplicateMapKey`]. The rejected key is discarded without
+///   having been written, and another key may be written in its place.
+/// - Text is normalized to Unicode Normalization Form C, and floating point
+///   numbers are reduced as by [`CBOR::from`].
+/// - The item tagged as a bignum, by tag 2 or 3, must be a byte string, and
+///   one holding an integer too large to be written as a plain integer,
+///   without leading zero bytes. Any other item is rejected with
+///   [`CBORError::WrongType`] or [`CBORError::NonCanonicalNumeric`] without
+///   having been written, and another item may be written in its place.
+/// - [`finish`](Self::finish) fails with [`CBORError::IncompleteContainer`] if
+///   an array, map, or tag is still waiting for items.
+///
+/// Map keys are held in memory until they are complete so that they can be
+/// compared, but everything else is written as soon as it is encoded, so wrap
+/// unbuffered writers such as files and sockets in a
+/// [`BufWriter`](std::io::BufWriter).
+///
+/// ```
+/// # use dcbor::prelude::*;
+/// let mut writer = CBORWriter::new(Vec::new());
+/// writer.begin_map(2).unwrap();
+/// writer.write_text("a").unwrap();
+/// writer.begin_array(2).unwrap();
+/// writer.write_u64(1).unwrap();
+/// writer.tag(1).unwrap();
+/// writer.write_f64(2.5).unwrap();
+/// // Keys must be in order.
+/// let error = writer.write_text("").unwrap_err();
+/// assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+/// writer.write_text("b").unwrap();
+/// writer.write_cbor(&CBOR::from([true, false])).unwrap();
+/// let data = writer.finish().unwrap();
+/// assert_eq!(CBOR::try_from_data(&data).unwrap().diagnostic_flat(), r#"{"a": [1, 1(2.5)], "b": [true, false]}"#);
+/// ```
+#[derive(Debug)]
+pub struct CBORWriter<W> {
+    output: Output<W>,
+    stack: Vec<Frame>,
+}
+
+/// An array, map, or tag still waiting for items.
+#[derive(Debug)]
+struct Frame {
+    /// The number of items still to be written, counting map keys and values
+    /// separately.
+    remaining: u64,
+    map: Option<MapFrame>,
+    /// Whether this is a bignum tag, whose item must be a valid bignum.
+    bignum: bool,
+}
+
+#[derive(Debug)]
+struct MapFrame {
+    /// Whether the next item written is a key.
+    expecting_key: bool,
+    /// The offset in [`Output::keys`] at which the current key starts.
+    key_start: usize,
+    previous_key: Option<Vec<u8>>,
+}
+
+/// The destination of the encoded data, which holds back the encodings of
+/// map keys until they are complete.
+#[derive(Debug)]
+struct Output<W> {
+    writer: W,
+    /// The encoding of the outermost map key being written, which includes
+    /// those of any map keys within it.
+    keys: Vec<u8>,
+    /// The number of map keys being written, one within another.
+    key_depth: usize,
+    position: usize,
+}
+
+impl<W: Write> EncodeSink for Output<W> {
+    type Error = io::Error;
+
+    fn put(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        if self.key_depth > 0 {
+            self.keys.extend_from_slice(data);
+        } else {
+            self.writer.write_all(data)?;
+            self.position += data.len();
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> CBORWriter<W> {
+    /// Creates a new writer that encodes items to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { output: Output { writer, keys: Vec::new(), key_depth: 0, position: 0 }, stack: Vec::new() }
+    }
+
+    /// Begins an array of `len` items, which are the next items written.
+    pub fn begin_array(&mut self, len: usize) -> Result<()> {
+        self.check_bignum(None)?;
+        self.output.put(Header::new(MajorType::Array, len as u64).as_slice())?;
+        self.begin(len as u64, None)
+    }
+
+    /// Begins a map of `len` key-value pairs, whose keys and values are the
+    /// next items written, in turn.
+    pub fn begin_map(&mut self, len: usize) -> Result<()> {
+        self.check_bignum(None)?;
+        self.output.put(Header::new(MajorType::Map, len as u64).as_slice())?;
+        let map = MapFrame { expecting_key: false, key_start: 0, previous_key: None };
+        self.begin((len as u64).saturating_mul(2), Some(map))
+    }
+
+    /// Writes `tag`, which applies to the next item written.
+    pub fn tag(&mut self, tag: impl Into<Tag>) -> Result<()> {
+        self.check_bignum(None)?;
+        let tag = tag.into().value();
+        self.output.put(Header::new(MajorType::Tagged, tag).as_slice())?;
+        self.begin(1, None)?;
+        if let Some(frame) = self.stack.last_mut() {
+            frame.bignum = is_bignum_tag(tag);
+        }
+        Ok(())
+    }
+
+    /// Writes an unsigned integer.
+    pub fn write_u64(&mut self, value: u64) -> Result<()> {
+        self.check_bignum(None)?;
+        self.output.put(Header::new(MajorType::Unsigned, value).as_slice())?;
+        self.end_item()
+    }
+
+    /// Writes a signed integer.
+    pub fn write_i64(&mut self, value: i64) -> Result<()> {
+        self.check_bignum(None)?;
+        let header = if value < 0 {
+            Header::new(MajorType::Negative, (-1 - value) as u64)
+        } else {
+            Header::new(MajorType::Unsigned, value as u64)
+        };
+        self.output.put(header.as_slice())?;
+        self.end_item()
+    }
+
+    /// Writes a floating point number, which is encoded as an integer if it
+    /// has no fractional part.
+    pub fn write_f64(&mut self, value: f64) -> Result<()> {
+        self.check_bignum(None)?;
+        self.output.put(f64_header(value).as_slice())?;
+        self.end_item()
+    }
+
+    /// Writes a boolean value.
+    pub fn write_bool(&mut self, value: bool) -> Result<()> {
+        self.check_bignum(None)?;
+        self.output.put(Header::new(MajorType::Simple, if value { 21 } else { 20 }).as_slice())?;
+        self.end_item()
+    }
+
+    /// Writes `null`.
+    pub fn write_null(&mut self) -> Result<()> {
+        self.check_bignum(None)?;
+        self.output.put(Header::new(MajorType::Simple, 22).as_slice())?;
+        self.end_item()
+    }
+
+    /// Writes a byte string.
+    pub fn write_bytes(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        let data = data.as_ref();
+        self.check_bignum(Some(data))?;
+        self.output.put(Header::new(MajorType::ByteString, data.len() as u64).as_slice())?;
+        self.output.put(data)?;
+        self.end_item()
+    }
+
+    /// Writes a text string, normalized to Unicode Normalization Form C.
+    pub fn write_text(&mut self, text: &str) -> Result<()> {
+        self.check_bignum(None)?;
+        let nfc;
+        let text = if is_nfc(text) {
+            text
+        } else {
+            nfc = text.nfc().collect::<String>();
+            &nfc
+        };
+        self.output.put(Header::new(MajorType::Text, text.len() as u64).as_slice())?;
+        self.output.put(text.as_bytes())?;
+        self.end_item()
+    }
+
+    /// Writes `cbor` whole.
+    ///
+    /// Fails without writing anything if `cbor` could not be decoded once
+    /// written: with [`CBORError::InvalidSimpleValue`] if it contains a
+    /// [`Simple::Value`](crate::Simple::Value), with
+    /// [`CBORError::DuplicateMapKey`] if it contains a map with keys that are
+    /// the same under dCBOR, and as described above if it contains a bignum
+    /// that is not canonical.
+    pub fn write_cbor(&mut self, cbor: &CBOR) -> Result<()> {
+        check_dcbor(cbor)?;
+        match cbor.as_case() {
+            CBORCase::ByteString(bytes) => self.check_bignum(Some(bytes.data()))?,
+            _ => self.check_bignum(None)?,
+        }
+        encode_to(EncodeTask::Item(cbor), EncodingProfile::Dcbor, &mut self.output)?;
+        self.end_item()
+    }
+
+    /// Returns the number of arrays, maps, and tags still waiting for items.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Returns the number of bytes written to the underlying writer.
+    ///
+    /// This does not include the map key being written, if any.
+    pub fn position(&self) -> usize {
+        self.output.position
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.output.writer.flush()
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.output.writer
+    }
+
+    /// Returns the underlying writer once every array, map, and tag is
+    /// complete, after flushing it.
+    pub fn finish(mut self) -> Result<W> {
+        if !self.stack.is_empty() {
+            bail!(CBORError::IncompleteContainer(self.stack.len()));
+        }
+        self.flush()?;
+        Ok(self.output.writer)
+    }
+
+    /// Records the start of a container of `len` items, whose header has been
+    /// written.
+    fn begin(&mut self, len: u64, map: Option<MapFrame>) -> Result<()> {
+        if len == 0 {
+            return self.end_item();
+        }
+        self.stack.push(Frame { remaining: len, map, bignum: false });
+        self.next_item();
+        Ok(())
+    }
+
+    /// Checks that the item about to be written can be the item of the
+    /// innermost tag, if that is a bignum tag, given the content of the item
+    /// if it is a byte string.
+    fn check_bignum(&self, bytes: Option<&[u8]>) -> Result<()> {
+        if !self.stack.last().is_some_and(|frame| frame.bignum) {
+            return Ok(());
+        }
+        match bytes {
+            Some(bytes) => validate_bignum(bytes)?,
+            None => bail!(CBORError::WrongType),
+        }
+        Ok(())
+    }
+
+    /// Prepares the innermost container to receive its next item.
+    fn next_item(&mut self) {
+        let Some(Frame { remaining, map: Some(map), .. }) = self.stack.last_mut() else {
+            return;
+        };
+        map.expecting_key = *remaining % 2 == 0;
+        if map.expecting_key {
+            map.key_start = self.output.keys.len();
+            self.output.key_depth += 1;
+        }
+    }
+
+    /// Records the end of the item just written, along with any containers
+    /// it completes.
+    fn end_item(&mut self) -> Result<()> {
+        while let Some(frame) = self.stack.last_mut() {
+            if let Some(map) = frame.map.as_mut().filter(|map| map.expecting_key) {
+                let key = &self.output.keys[map.key_start..];
+                if let Some(previous_key) = &map.previous_key {
+                    let error = match previous_key.as_slice().cmp(key) {
+                        cmp::Ordering::Less => None,
+                        cmp::Ordering::Equal => Some(CBORError::DuplicateMapKey),
+                        cmp::Ordering::Greater => Some(CBORError::MisorderedMapKey),
+                    };
+                    if let Some(error) = error {
+                        self.output.keys.truncate(map.key_start);
+                        bail!(error);
+                    }
+                }
+                map.previous_key = Some(key.to_vec());
+                map.expecting_key = false;
+                self.output.key_depth -= 1;
+                if self.output.key_depth == 0 {
+                    let keys = mem::take(&mut self.output.keys);
+                    self.output.put(&keys)?;
+                    self.output.keys = keys;
+                    self.output.keys.clear();
+                }
+            }
+            let frame = self.stack.last_mut().unwrap();
+            frame.remaining -= 1;
+            if frame.remaining > 0 {
+                self.next_item();
+                return Ok(());
+            }
+            self.stack.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Checks the parts of `cbor` that could be written but not decoded as
+/// dCBOR: simple values that dCBOR does not allow, maps with keys that are
+/// the same under dCBOR, and bignums that are not canonical.
+fn check_dcbor(cbor: &CBOR) -> Result<()> {
+    let mut stack = vec![cbor];
+    while let Some(cbor) = stack.pop() {
+        match cbor.as_case() {
+            CBORCase::Array(items) => stack.extend(items),
+            CBORCase::Map(map) => {
+                if map.encoded_iter().zip(map.encoded_iter().skip(1)).any(|((a, _), (b, _))| a == b) {
+                    bail!(CBORError::DuplicateMapKey);
+                }
+                stack.extend(map.iter().flat_map(|(key, value)| [key, value]));
+            },
+            CBORCase::Tagged(tag, item) => {
+                if is_bignum_tag(tag.value()) {
+                    match item.as_case() {
+                        CBORCase::ByteString(bytes) => validate_bignum(bytes.data())?,
+                        _ => bail!(CBORError::WrongType),
+                    }
+                }
+                stack.push(item);
+            },
+            CBORCase::Simple(Simple::Value(_)) => bail!(CBORError::InvalidSimpleValue),
+            _ => {},
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+#![cfg(feature = "tokio")]
+
+use dcbor::prelude::*;
+use futures_util::{SinkExt, StreamExt};
+use hex_literal::hex;
+use tokio::io::AsyncWriteExt;
+use tokio_util::codec::{Decoder, FramedRead, FramedWrite};
+
+fn items() -> Vec<CBOR> {
+    vec![
+        CBOR::from(1),
+        CBOR::from([2, 3]),
+        CBOR::try_from_hex("a201f9be006161420102").unwrap(),
+        CBOR::from("héllo"),
+        CBOR::to_byte_string([0x55; 100]),
+    ]
+}
+
+#[tokio::test]
+async fn async_reader_and_writer_over_duplex() {
+    // A small buffer splits items across reads.
+    let (client, server) = tokio::io::duplex(7);
+    let expected = items();
+    let write = async {
+        let mut writer = AsyncCBORWriter::new(client);
+        for item in items() {
+            writer.write_cbor(&item).await.unwrap();
+        }
+        writer.flush().await.unwrap();
+    };
+    let read = async {
+        let mut reader = AsyncCBORReader::new(server);
+        let mut read = Vec::new();
+        while let Some(item) = reader.read_cbor().await.unwrap() {
+            read.push(item);
+        }
+        assert_eq!(reader.position(), dcbor::encode_sequence(items()).len());
+        read
+    };
+    let ((), read) = tokio::join!(write, read);
+    assert_eq!(read, expected);
+}
+
+#[tokio::test]
+async fn async_reader_errors_match_reader() {
+    let cases = [
+        &hex!("01 1817")[..],
+        &hex!("01 fa3fc00000"),
+        &hex!("01 82 a2020001 00"),
+        &hex!("6365cc81"),
+        &hex!("01 1c"),
+        &hex!("01 82 1c"),
+        &hex!("01 8219"),
+        &hex!("a20102"),
+        &hex!("5bffffffffffffffff00"),
+    ];
+    for data in cases {
+        let mut reader = CBORReader::new(data);
+        let expected = loop {
+            if let Err(error) = reader.read_cbor() {
+                break error;
+            }
+        };
+        let mut reader = AsyncCBORReader::new(data);
+        let error = loop {
+            if let Err(error) = reader.read_cbor().await {
+                break error;
+            }
+        };
+        assert_eq!(error.to_string(), expected.to_string(), "{}", hex::encode(data));
+        let error = error.downcast_ref::<DecodeError>().unwrap();
+        let expected = expected.downcast_ref::<DecodeError>().unwrap();
+        assert_eq!(error.offset(), expected.offset(), "{}", hex::encode(data));
+        assert_eq!(error.path(), expected.path(), "{}", hex::encode(data));
+    }
+}
+
+#[tokio::test]
+async fn async_reader_rejects_oversized_items_early() {
+    // Only the header of a long string arrives, and the stream stays open, so
+    // the limit must be checked before the content is awaited.
+    let (mut client, server) = tokio::io::duplex(64);
+    client.write_all(&hex!("01 81 5a00100000")).await.unwrap();
+    let options = DecodeOptions { max_string_len: 1024, ..Default::default() };
+    let mut reader = AsyncCBORReader::with_options(server, options);
+    assert_eq!(reader.read_cbor().await.unwrap(), Some(CBOR::from(1)));
+    let error = reader.read_cbor().await.unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::StringLenLimitExceeded(1024))));
+    let error = error.downcast_ref::<DecodeError>().unwrap();
+    assert_eq!(error.offset(), 2);
+    assert_eq!(error.path().to_string(), "[0]");
+    drop(client);
+}
+
+#[tokio::test]
+async fn async_reader_enforces_depth_and_allocation_while_buffering() {
+    // Nesting deeper than the limit is rejected while the stream is still
+    // open, before the innermost item arrives.
+    let (mut client, server) = tokio::io::duplex(64);
+    client.write_all(&[0x81; 10]).await.unwrap();
+    let options = DecodeOptions { max_depth: 4, ..Default::default() };
+    let mut reader = AsyncCBORReader::with_options(server, options);
+    let error = reader.read_cbor().await.unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::DepthLimitExceeded(4))));
+    assert_eq!(error.downcast_ref::<DecodeError>().unwrap().offset(), 5);
+    drop(client);
+
+    // The allocation limit bounds how much of an item is buffered, even
+    // without any other limits.
+    let (mut client, server) = tokio::io::duplex(64);
+    client.write_all(&hex!("82 5a00100000")).await.unwrap();
+    let options = DecodeOptions { max_allocated_bytes: 4096, ..Default::default() };
+    let mut reader = AsyncCBORReader::with_options(server, options);
+    let error = reader.read_cbor().await.unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::AllocationLimitExceeded(4096))));
+    assert_eq!(error.downcast_ref::<DecodeError>().unwrap().path().to_string(), "[0]");
+    drop(client);
+
+    // Items within the limits are unaffected.
+    let item = CBOR::from([[[CBOR::to_byte_string([0; 100])]]]);
+    let options = DecodeOptions { max_depth: 3, max_allocated_bytes: 4096, ..Default::default() };
+    let data = item.to_cbor_data();
+    let mut reader = AsyncCBORReader::with_options(&data[..], options.clone());
+    assert_eq!(reader.read_cbor().await.unwrap(), Some(item.clone()));
+    let mut codec = CBORCodec::with_options(1024, options);
+    assert_eq!(codec.decode(&mut bytes::BytesMut::from(&data[..])).unwrap(), Some(item));
+}
+
+#[tokio::test]
+async fn codec_enforces_depth_and_allocation_while_buffering() {
+    let options = DecodeOptions { max_depth: 4, ..Default::default() };
+    let mut codec = CBORCodec::with_options(usize::MAX, options);
+    let error = codec.decode(&mut bytes::BytesMut::from(&[0x81; 10][..])).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::DepthLimitExceeded(4))));
+
+    let options = DecodeOptions { max_allocated_bytes: 4096, ..Default::default() };
+    let mut codec = CBORCodec::with_options(usize::MAX, options);
+    let error = codec.decode(&mut bytes::BytesMut::from(&hex!("82 5a00100000")[..])).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::AllocationLimitExceeded(4096))));
+}
+
+#[tokio::test]
+async fn codec_frames_items_over_duplex() {
+    let (client, server) = tokio::io::duplex(5);
+    let expected = items();
+    let write = async {
+        let mut sink = FramedWrite::new(client, CBORCodec::new(1024));
+        for item in items() {
+            sink.send(item).await.unwrap();
+        }
+    };
+    let read = async {
+        let stream = FramedRead::new(server, CBORCodec::new(1024));
+        stream.map(Result::unwrap).collect::<Vec<_>>().await
+    };
+    let ((), read) = tokio::join!(write, read);
+    assert_eq!(read, expected);
+}
+
+#[tokio::test]
+async fn codec_enforces_item_len_limit() {
+    let item = CBOR::to_byte_string([0; 32]);
+
+    let mut sink = FramedWrite::new(Vec::new(), CBORCodec::new(16));
+    let error = sink.send(&item).await.unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::ItemLenLimitExceeded(16))));
+
+    let data = item.to_cbor_data();
+    let mut stream = FramedRead::new(&data[..], CBORCodec::new(16));
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::ItemLenLimitExceeded(16))));
+
+    // A partial item longer than the limit is rejected without waiting for
+    // the rest.
+    let mut codec = CBORCodec::new(16);
+    let mut buf = bytes::BytesMut::from(&data[..20]);
+    let error = codec.decode(&mut buf).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::ItemLenLimitExceeded(16))));
+}
+
+#[tokio::test]
+async fn codec_reports_truncated_and_invalid_items() {
+    let data = hex!("01 820102 8201");
+    let mut stream = FramedRead::new(&data[..], CBORCodec::new(1024));
+    assert_eq!(stream.next().await.unwrap().unwrap(), CBOR::from(1));
+    assert_eq!(stream.next().await.unwrap().unwrap(), CBOR::from([1, 2]));
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::Underrun)));
+
+    let data = hex!("01 a2020001 00");
+    let options = DecodeOptions { max_depth: 4, ..Default::default() };
+    let mut stream = FramedRead::new(&data[..], CBORCodec::with_options(1024, options));
+    assert_eq!(stream.next().await.unwrap().unwrap(), CBOR::from(1));
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+    // Offsets are relative to the start of the item.
+    assert_eq!(error.downcast_ref::<DecodeError>().unwrap().offset(), 3);
+}
@@ -0,0 +1,124 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+type IsExpected = fn(&CBORError) -> bool;
+
+fn is_error<T>(result: anyhow::Result<T>, is_expected: IsExpected) -> bool {
+    result.err().and_then(|error| error.downcast_ref::<CBORError>().map(is_expected)).unwrap_or(false)
+}
+
+#[test]
+fn bignum_encoding() {
+    let cases: [(CBOR, &str); 8] = [
+        (CBOR::from(0u128), "00"),
+        (CBOR::from(u64::MAX as u128), "1bffffffffffffffff"),
+        (CBOR::from(u64::MAX as u128 + 1), "c249010000000000000000"),
+        (CBOR::from(u128::MAX), "c250ffffffffffffffffffffffffffffffff"),
+        (CBOR::from(-1i128), "20"),
+        (CBOR::from(-1 - u64::MAX as i128), "3bffffffffffffffff"),
+        (CBOR::from(-2 - u64::MAX as i128), "c349010000000000000000"),
+        (CBOR::from(i128::MIN), "c3507fffffffffffffffffffffffffffffff"),
+    ];
+    for (cbor, expected_hex) in cases {
+        assert_eq!(cbor.hex(), expected_hex);
+        assert_eq!(CBOR::try_from_hex(expected_hex).unwrap(), cbor);
+    }
+}
+
+#[test]
+fn bignum_conversions() {
+    for value in [0, 1, u64::MAX as u128, u64::MAX as u128 + 1, u128::MAX] {
+        let data = CBOR::from(value).to_cbor_data();
+        assert_eq!(u128::try_from(CBOR::try_from_data(&data).unwrap()).unwrap(), value);
+        assert_eq!(u128::try_from(CBORRef::try_from_data(&data).unwrap()).unwrap(), value);
+    }
+    for value in [0, -1, i64::MIN as i128, -1 - u64::MAX as i128, -2 - u64::MAX as i128, i128::MIN, i128::MAX] {
+        let data = CBOR::from(value).to_cbor_data();
+        assert_eq!(i128::try_from(CBOR::try_from_data(&data).unwrap()).unwrap(), value);
+        assert_eq!(i128::try_from(CBORRef::try_from_data(&data).unwrap()).unwrap(), value);
+    }
+
+    let out_of_range = |e: &CBORError| matches!(e, CBORError::OutOfRange);
+    assert!(is_error(u128::try_from(CBOR::from(-1)), out_of_range));
+    assert!(is_error(i128::try_from(CBOR::from(u128::MAX)), out_of_range));
+    // -1 - 2^127
+    let below_min = CBOR::to_tagged_value(3, CBOR::to_byte_string(hex!("80000000000000000000000000000000")));
+    assert!(is_error(i128::try_from(below_min), out_of_range));
+    // 2^128
+    let above_max = CBOR::to_tagged_value(2, CBOR::to_byte_string(hex!("0100000000000000000000000000000000")));
+    assert!(is_error(u128::try_from(above_max), out_of_range));
+
+    let wrong_type = |e: &CBORError| matches!(e, CBORError::WrongType);
+    assert!(is_error(u128::try_from(CBOR::from("1")), wrong_type));
+    assert!(is_error(u128::try_from(CBOR::to_tagged_value(2, 1)), wrong_type));
+    assert!(is_error(u64::try_from(CBOR::from(u128::MAX)), wrong_type));
+}
+
+#[test]
+fn bignum_decoding_rules() {
+    let cases: [(&[u8], IsExpected, &str); 5] = [
+        // Fits in 64 bits.
+        (&hex!("c2 48 0100000000000000"), |e| matches!(e, CBORError::NonCanonicalNumeric), "tag(2)"),
+        (&hex!("c3 40"), |e| matches!(e, CBORError::NonCanonicalNumeric), "tag(3)"),
+        // A leading zero.
+        (&hex!("c2 4a 00010000000000000000"), |e| matches!(e, CBORError::NonCanonicalNumeric), "tag(2)"),
+        // Not a byte string.
+        (&hex!("c2 01"), |e| matches!(e, CBORError::WrongType), "tag(2)"),
+        (&hex!("c3 6161"), |e| matches!(e, CBORError::WrongType), "tag(3)"),
+    ];
+    for (data, is_expected, path) in cases {
+        let case = hex::encode(data);
+        let error = CBOR::try_from_data(data).unwrap_err();
+        assert!(is_expected(error.downcast_ref::<CBORError>().unwrap()), "{}", case);
+        let location = error.downcast_ref::<DecodeError>().unwrap();
+        assert_eq!((location.offset(), location.path().to_string()), (1, path.to_string()), "{}", case);
+        assert!(is_expected(&dcbor::validate(data).unwrap_err()), "{}", case);
+        let violations = dcbor::lint(data);
+        assert_eq!(violations.violations().len(), 1, "{}", case);
+        assert_eq!(violations.violations()[0].offset, 1, "{}", case);
+    }
+    assert!(matches!(dcbor::lint(&hex!("c2 01")).violations()[0].kind, ViolationKind::InvalidBignum));
+    assert!(matches!(dcbor::lint(&hex!("c3 40")).violations()[0].kind, ViolationKind::NonCanonicalBignum));
+}
+
+#[test]
+fn bignum_summary() {
+    dcbor::register_tags();
+    let cbor = CBOR::from(vec![CBOR::from(u128::MAX), CBOR::from(i128::MIN), CBOR::from(-2 - u64::MAX as i128)]);
+    assert_eq!(cbor.summary(), "[340282366920938463463374607431768211455, -170141183460469231731687303715884105728, -18446744073709551617]");
+    assert_eq!(CBOR::from(u64::MAX as u128 + 1).diagnostic_annotated(), "2(   / positive-bignum /\n    h'010000000000000000'\n)");
+}
+
+#[cfg(feature = "num-bigint")]
+#[test]
+fn bignum_num_bigint() {
+    use num_bigint::{BigInt, BigUint};
+
+    let cases = [
+        ("0", "00"),
+        ("-1", "20"),
+        ("18446744073709551615", "1bffffffffffffffff"),
+        ("-18446744073709551616", "3bffffffffffffffff"),
+        ("18446744073709551616", "c249010000000000000000"),
+        ("-18446744073709551617", "c349010000000000000000"),
+        ("-1000000000000000000000000000000000000000", "c35102f050fe938943acc45f65567fffffffff"),
+    ];
+    for (decimal, expected_hex) in cases {
+        let value: BigInt = decimal.parse().unwrap();
+        let cbor = CBOR::from(value.clone());
+        assert_eq!(cbor.hex(), expected_hex, "{}", decimal);
+        let data = cbor.to_cbor_data();
+        assert_eq!(BigInt::try_from(CBOR::try_from_data(&data).unwrap()).unwrap(), value);
+        assert_eq!(BigInt::try_from(CBORRef::try_from_data(&data).unwrap()).unwrap(), value);
+        match BigUint::try_from(CBOR::try_from_data(&data).unwrap()) {
+            Ok(unsigned) => assert_eq!(BigInt::from(unsigned), value),
+            Err(error) => {
+                assert!(decimal.starts_with('-'));
+                assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::OutOfRange)));
+            },
+        }
+    }
+    let value = BigUint::from(u128::MAX);
+    assert_eq!(CBOR::from(value.clone()), CBOR::from(u128::MAX));
+    assert_eq!(BigUint::try_from(CBOR::from(u128::MAX)).unwrap(), value);
+}
@@ -0,0 +1,123 @@
+use dcbor::{prelude::*, BigFloat, Date, DecimalFraction};
+use hex_literal::hex;
+
+#[test]
+fn cbor_ref_accessors() {
+    // [1, -2, h'0102', "hello", {1: "a", "b": [true]}, 1(2.5), null]
+    let data = hex!("87 01 21 420102 6568656c6c6f a2 01 6161 6162 81f5 c1 f94100 f6");
+    let cbor = CBORRef::try_from_data(&data).unwrap();
+    assert_eq!(cbor.encoded(), &data);
+    assert_eq!(cbor.to_cbor(), CBOR::try_from_data(data).unwrap());
+    assert_eq!(cbor.to_string(), r#"[1, -2, h'0102', "hello", {1: "a", "b": [true]}, 1(2.5), null]"#);
+
+    let items = cbor.try_into_array().unwrap();
+    assert_eq!(items.len(), 7);
+    assert_eq!(u8::try_from(items[0].clone()).unwrap(), 1);
+    assert_eq!(i32::try_from(items[1].clone()).unwrap(), -2);
+
+    let bytes = items[2].clone().try_into_byte_string().unwrap();
+    assert_eq!(bytes, [1, 2]);
+    assert!(data.as_ptr_range().contains(&bytes.as_ptr()));
+    assert_eq!(ByteString::try_from(items[2].clone()).unwrap(), ByteString::from([1, 2]));
+
+    let text: &str = items[3].clone().try_into().unwrap();
+    assert_eq!(text, "hello");
+    assert!(data.as_ptr_range().contains(&text.as_ptr()));
+    assert_eq!(String::try_from(items[3].clone()).unwrap(), "hello");
+    assert!(items[3].clone().try_into_byte_string().is_err());
+
+    let map = items[4].clone().try_into_map().unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get::<_, &str>(1), Some("a"));
+    assert_eq!(map.extract::<_, Vec<bool>>("b").unwrap(), [true]);
+    assert_eq!(map.get::<_, &str>(2), None);
+    assert!(matches!(map.extract::<_, &str>(2).unwrap_err().downcast_ref::<CBORError>(), Some(CBORError::MissingMapKey)));
+    let keys: Vec<_> = map.iter().map(|(key, _)| key.to_cbor()).collect();
+    assert_eq!(keys, [CBOR::from(1), CBOR::from("b")]);
+
+    let value = items[5].clone().try_into_expected_tagged_value(1).unwrap();
+    assert_eq!(f64::try_from(value).unwrap(), 2.5);
+    assert!(items[5].clone().try_into_expected_tagged_value(2).is_err());
+
+    assert_eq!(items[6].clone().try_into_simple_value().unwrap(), Simple::Null);
+    assert!(bool::try_from(items[6].clone()).is_err());
+}
+
+#[test]
+fn cbor_ref_conversions_match_cbor() {
+    use std::collections::{BTreeMap, VecDeque};
+
+    fn check<T>(data: &[u8])
+    where
+        T: TryFrom<CBOR, Error = anyhow::Error> + for<'a> TryFrom<CBORRef<'a>, Error = anyhow::Error> + PartialEq + std::fmt::Debug,
+    {
+        let from_ref = T::try_from(CBORRef::try_from_data(data).unwrap());
+        let from_cbor = T::try_from(CBOR::try_from_data(data).unwrap());
+        match (from_ref, from_cbor) {
+            (Ok(from_ref), Ok(from_cbor)) => assert_eq!(from_ref, from_cbor),
+            (Err(ref_error), Err(cbor_error)) => assert_eq!(ref_error.to_string(), cbor_error.to_string()),
+            (from_ref, from_cbor) => panic!("{:?} {:?}", from_ref, from_cbor),
+        }
+    }
+
+    // [1, 2, 3], {1: "a", 2: "b"}, and values of the wrong type
+    let array = hex!("83 01 02 03");
+    let map = hex!("a2 01 6161 02 6162");
+    let text = hex!("6161");
+    for data in [&array[..], &map, &text] {
+        check::<VecDeque<u32>>(data);
+        check::<BTreeMap<u32, String>>(data);
+        #[cfg(feature = "std")]
+        {
+            check::<std::collections::HashSet<u32>>(data);
+            check::<std::collections::HashMap<u32, String>>(data);
+        }
+    }
+    assert_eq!(VecDeque::<u32>::try_from(CBORRef::try_from_data(&array).unwrap()).unwrap(), [1, 2, 3]);
+    assert_eq!(BTreeMap::<u32, String>::try_from(CBORRef::try_from_data(&map).unwrap()).unwrap().len(), 2);
+
+    // 1(1675854714), 4([-2, 27315]), 5([-1, 3]), a non-canonical decimal
+    // fraction, and a tag of the wrong kind
+    let date = hex!("c1 1a63e3837a");
+    let decimal = hex!("c4 82 21 196ab3");
+    let bigfloat = hex!("c5 82 20 03");
+    let non_canonical = hex!("c4 82 20 0a");
+    for data in [&date[..], &decimal, &bigfloat, &non_canonical, &text] {
+        check::<Date>(data);
+        check::<DecimalFraction>(data);
+        check::<BigFloat>(data);
+        #[cfg(feature = "rust_decimal")]
+        check::<rust_decimal::Decimal>(data);
+    }
+    assert_eq!(Date::try_from(CBORRef::try_from_data(&date).unwrap()).unwrap(), Date::from_timestamp(1675854714.0));
+    assert_eq!(DecimalFraction::try_from(CBORRef::try_from_data(&decimal).unwrap()).unwrap(), DecimalFraction::new(27315, -2));
+}
+
+#[test]
+fn cbor_ref_equality() {
+    let data = hex!("82 a10102 a10102");
+    let items = CBORRef::try_from_data(&data).unwrap().try_into_array().unwrap();
+    assert_eq!(items[0], items[1]);
+    assert_eq!(CBOR::from(items[0].clone()), CBOR::try_from_hex("a10102").unwrap());
+}
+
+#[test]
+fn cbor_ref_enforces_dcbor_rules() {
+    for hex in ["1817", "a2020001 00", "6365cc81", "f93c00", "0100", "8301"] {
+        let data = hex::decode(hex.replace(' ', "")).unwrap();
+        let error = CBORRef::try_from_data(&data).unwrap_err();
+        assert_eq!(error.to_string(), CBOR::try_from_data(&data).unwrap_err().to_string());
+    }
+}
+
+#[test]
+fn cbor_ref_deeply_nested() {
+    const DEPTH: usize = 100_000;
+    let mut data = vec![0x81; DEPTH];
+    data.push(0x00);
+    let cbor = CBORRef::try_from_data(&data).unwrap();
+    let copy = cbor.clone();
+    assert_eq!(cbor, copy);
+    drop(cbor);
+    assert_eq!(copy.to_cbor().to_cbor_data(), data);
+}
@@ -0,0 +1,105 @@
+use dcbor::{prelude::*, BigFloat, DecimalFraction};
+use hex_literal::hex;
+
+fn decode_error<T: CBORTaggedDecodable>(data: &[u8]) -> CBORError {
+    T::from_tagged_cbor_data(data).err().unwrap().downcast_ref::<CBORError>().unwrap().clone()
+}
+
+#[test]
+fn decimal_fraction_canonical_form() {
+    let cases = [
+        (DecimalFraction::new(1234, -2), (1234, -2), "12.34", "c482211904d2"),
+        (DecimalFraction::new(123400, -4), (1234, -2), "12.34", "c482211904d2"),
+        (DecimalFraction::new(-5, 0), (-5, 0), "-5", "c4820024"),
+        (DecimalFraction::new(1200, 0), (12, 2), "1200", "c482020c"),
+        (DecimalFraction::new(0, -7), (0, 0), "0", "c4820000"),
+        (DecimalFraction::new(-25, -4), (-25, -4), "-0.0025", "c482233818"),
+        (DecimalFraction::new(1, 21), (1, 21), "1e21", "c4821501"),
+        (DecimalFraction::new(i128::MAX, -100), (i128::MAX, -100), "170141183460469231731687303715884105727e-100", "c4823863c2507fffffffffffffffffffffffffffffff"),
+    ];
+    for (value, (mantissa, exponent), display, hex) in cases {
+        assert_eq!((value.mantissa(), value.exponent()), (mantissa, exponent));
+        assert_eq!(value.to_string(), display);
+        let cbor = CBOR::from(value);
+        assert_eq!(cbor.hex(), hex, "{}", display);
+        assert_eq!(DecimalFraction::try_from(CBOR::try_from_hex(hex).unwrap()).unwrap(), value);
+    }
+    assert_eq!(DecimalFraction::from(100), DecimalFraction::new(1, 2));
+}
+
+#[test]
+fn big_float_canonical_form() {
+    let cases = [
+        (BigFloat::new(3, -1), (3, -1), "1.5"),
+        (BigFloat::new(12, -3), (3, -1), "1.5"),
+        (BigFloat::new(-1, -2), (-1, -2), "-0.25"),
+        (BigFloat::new(8, 0), (1, 3), "8"),
+        (BigFloat::new(0, 10), (0, 0), "0"),
+        (BigFloat::new(3, -200), (3, -200), "0x3p-200"),
+        (BigFloat::new(-1, 1000), (-1, 1000), "-0x1p1000"),
+    ];
+    for (value, (mantissa, exponent), display) in cases {
+        assert_eq!((value.mantissa(), value.exponent()), (mantissa, exponent));
+        assert_eq!(value.to_string(), display);
+        let cbor = CBOR::from(value);
+        assert_eq!(BigFloat::try_from(CBOR::try_from_data(cbor.to_cbor_data()).unwrap()).unwrap(), value);
+    }
+    assert_eq!(CBOR::from(BigFloat::new(3, -1)).hex(), "c5822003");
+}
+
+#[test]
+fn decimal_decoding_rules() {
+    // 4([-2, 1230])
+    assert!(matches!(decode_error::<DecimalFraction>(&hex!("c4 82 21 1904ce")), CBORError::NonCanonicalNumeric));
+    // 4([1, 0])
+    assert!(matches!(decode_error::<DecimalFraction>(&hex!("c4 82 01 00")), CBORError::NonCanonicalNumeric));
+    // 5([0, 2])
+    assert!(matches!(decode_error::<BigFloat>(&hex!("c5 82 00 02")), CBORError::NonCanonicalNumeric));
+    // 4([-2]), 4([-2, 1, 1]), and 4([-2, "1"])
+    assert!(matches!(decode_error::<DecimalFraction>(&hex!("c4 81 21")), CBORError::WrongType));
+    assert!(matches!(decode_error::<DecimalFraction>(&hex!("c4 83 21 01 01")), CBORError::WrongType));
+    assert!(matches!(decode_error::<DecimalFraction>(&hex!("c4 82 21 6131")), CBORError::WrongType));
+    // An exponent that is a bignum.
+    assert!(matches!(decode_error::<DecimalFraction>(&hex!("c4 82 c249010000000000000000 01")), CBORError::WrongType));
+    // 4([2^63, 1])
+    assert!(matches!(decode_error::<DecimalFraction>(&hex!("c4 82 1b8000000000000000 01")), CBORError::OutOfRange));
+    // 5([0, 2^128 + 1])
+    assert!(matches!(decode_error::<BigFloat>(&hex!("c5 82 00 c2510100000000000000000000000000000001")), CBORError::OutOfRange));
+    // 5([1, 3])
+    assert!(matches!(decode_error::<DecimalFraction>(&hex!("c5 82 01 03")), CBORError::WrongTag(_, _)));
+}
+
+#[test]
+fn decimal_summary() {
+    dcbor::register_tags();
+    let cbor = CBOR::from(vec![
+        CBOR::from(DecimalFraction::new(1234, -2)),
+        CBOR::from(DecimalFraction::new(-1, -3)),
+        CBOR::from(BigFloat::new(3, -1)),
+    ]);
+    assert_eq!(cbor.diagnostic_flat(), "[4([-2, 1234]), 4([-3, -1]), 5([-1, 3])]");
+    assert_eq!(cbor.summary(), "[12.34, -0.001, 1.5]");
+    let values = with_tags!(|tags: &TagsStore| tags.decode_any(CBOR::from(BigFloat::new(3, -1)))).unwrap();
+    assert_eq!(values.downcast_ref::<BigFloat>(), Some(&BigFloat::new(3, -1)));
+}
+
+#[cfg(feature = "rust_decimal")]
+#[test]
+fn decimal_rust_decimal() {
+    use rust_decimal::Decimal;
+
+    let amount = Decimal::new(123450, 4);
+    let cbor = CBOR::from(amount);
+    assert_eq!(cbor.diagnostic_flat(), "4([-3, 12345])");
+    let decoded = Decimal::try_from(CBOR::try_from_data(cbor.to_cbor_data()).unwrap()).unwrap();
+    assert_eq!(decoded, amount);
+    assert_eq!(decoded.to_string(), "12.345");
+
+    assert_eq!(Decimal::try_from(DecimalFraction::new(12, 3)).unwrap(), Decimal::new(12000, 0));
+    assert_eq!(DecimalFraction::from(Decimal::MAX), DecimalFraction::new(Decimal::MAX.mantissa(), 0));
+    // Too many decimal places, and too large.
+    for value in [DecimalFraction::new(1, -29), DecimalFraction::new(1, 29)] {
+        let error = Decimal::try_from(value).unwrap_err();
+        assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::OutOfRange)));
+    }
+}
@@ -0,0 +1,261 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+use indoc::indoc;
+
+fn decode_error(data: &[u8], options: &DecodeOptions) -> String {
+    let error = CBOR::try_from_data_with_options(data, options).unwrap_err();
+    format!("{}", error)
+}
+
+#[test]
+fn decode_limits_default_is_unlimited() {
+    let data = hex!("a2616101616282020a");
+    let cbor = CBOR::try_from_data_with_options(data, &DecodeOptions::default()).unwrap();
+    assert_eq!(cbor, CBOR::try_from_data(data).unwrap());
+}
+
+#[test]
+fn decode_limit_depth() {
+    let options = DecodeOptions { max_depth: 2, ..Default::default() };
+    // [[1]] has its deepest item at depth 2.
+    CBOR::try_from_data_with_options(hex!("818101"), &options).unwrap();
+    // [[[1]]] has its deepest item at depth 3.
+    assert_eq!(decode_error(&hex!("81818101"), &options), "the CBOR nesting depth exceeds the limit of 2");
+    // Tags count toward the depth.
+    assert_eq!(decode_error(&hex!("81c1c101"), &options), "the CBOR nesting depth exceeds the limit of 2");
+}
+
+#[test]
+fn decode_limit_container_len() {
+    let options = DecodeOptions { max_container_len: 2, ..Default::default() };
+    CBOR::try_from_data_with_options(hex!("820102"), &options).unwrap();
+    assert_eq!(decode_error(&hex!("83010203"), &options), "a CBOR array or map has more elements than the limit of 2");
+    assert_eq!(decode_error(&hex!("a3010102020303"), &options), "a CBOR array or map has more elements than the limit of 2");
+    // The declared length is checked before any element is read.
+    assert_eq!(decode_error(&hex!("9bffffffffffffffff"), &options), "a CBOR array or map has more elements than the limit of 2");
+}
+
+#[test]
+fn decode_limit_string_len() {
+    let options = DecodeOptions { max_string_len: 3, ..Default::default() };
+    CBOR::try_from_data_with_options(hex!("63616263"), &options).unwrap();
+    assert_eq!(decode_error(&hex!("6461626364"), &options), "a CBOR string is longer than the limit of 3 bytes");
+    assert_eq!(decode_error(&hex!("4400112233"), &options), "a CBOR string is longer than the limit of 3 bytes");
+    // A huge declared length is rejected without reading the content.
+    assert_eq!(decode_error(&hex!("5b7fffffffffffffff"), &options), "a CBOR string is longer than the limit of 3 bytes");
+}
+
+#[test]
+fn decode_limit_nodes() {
+    let options = DecodeOptions { max_nodes: 4, ..Default::default() };
+    // The array and its three elements.
+    CBOR::try_from_data_with_options(hex!("83010203"), &options).unwrap();
+    assert_eq!(decode_error(&hex!("8401020304"), &options), "the CBOR contains more items than the limit of 4");
+}
+
+#[test]
+fn decode_limit_allocated_bytes() {
+    let options = DecodeOptions { max_allocated_bytes: 1024, ..Default::default() };
+    CBOR::try_from_data_with_options(hex!("4400112233"), &options).unwrap();
+    let mut data = vec![0x59, 0x04, 0x00];
+    data.extend([0u8; 1024]);
+    assert_eq!(decode_error(&data, &options), "decoding the CBOR would allocate more than the limit of 1024 bytes");
+}
+
+fn decode_error_location(data: &[u8]) -> DecodeError {
+    let error = CBOR::try_from_data(data).unwrap_err();
+    error.downcast_ref::<DecodeError>().unwrap().clone()
+}
+
+#[test]
+fn decode_error_locations() {
+    // [0, 0, 0, {"name": 1(5)}], with the 5 encoded in two bytes.
+    let error = decode_error_location(&hex!("84000000a1646e616d65c11805"));
+    assert!(matches!(error.error(), CBORError::NonCanonicalNumeric));
+    assert_eq!(error.offset(), 11);
+    assert_eq!(error.path().to_string(), r#"[3].{"name"}.tag(1)"#);
+    assert_eq!(error.path(), &CBORPath::new([
+        PathElement::Index(3),
+        PathElement::key("name"),
+        PathElement::Tag(1),
+    ]));
+
+    // [{2: 0, 1: 0}]
+    let error = decode_error_location(&hex!("81a202000100"));
+    assert!(matches!(error.error(), CBORError::MisorderedMapKey));
+    assert_eq!(error.offset(), 4);
+    assert_eq!(error.path().to_string(), "[0].key(1)");
+
+    let error = decode_error_location(&hex!("0102"));
+    assert!(matches!(error.error(), CBORError::UnusedData(1)));
+    assert_eq!(error.offset(), 1);
+    assert!(error.path().is_empty());
+
+    // The underlying error is still available directly.
+    let error = CBOR::try_from_hex("81a202000100").unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+    assert_eq!(error.to_string(), "the decoded CBOR map has keys that are not in canonical order");
+}
+
+#[test]
+fn decode_error_hex_annotated() {
+    let data = hex!("84000000a1646e616d65c11805");
+    let error = decode_error_location(&data);
+    let expected = indoc! {r#"
+        84                      # array(4)
+            00                  # unsigned(0)
+            00                  # unsigned(0)
+            00                  # unsigned(0)
+            a1                  # map(1)
+                64              # text(4)
+                    6e616d65    # "name"
+                c1              # tag(1)
+                    1805        # unsigned(5) <-- a CBOR numeric value was encoded in non-canonical form
+    "#}.trim();
+    assert_eq!(error.hex_opt(data, None), expected);
+
+    // Truncated data is shown on a line of its own.
+    let data = hex!("82016341");
+    let error = decode_error_location(&data);
+    let expected = indoc! {r#"
+        82          # array(2)
+            01      # unsigned(1)
+            6341    # malformed or truncated <-- early end of CBOR data
+    "#}.trim();
+    assert_eq!(error.hex_opt(data, None), expected);
+}
+
+#[test]
+fn decode_annotated_hex() {
+    dcbor::register_tags();
+    let mut map = Map::new();
+    map.insert(1, [1.5, -2.0]);
+    map.insert("name", CBOR::to_tagged_value(1, 1675854714));
+    map.insert(CBOR::to_byte_string([0xab; 40]), "x".repeat(30));
+    let cbor = CBOR::from(map);
+    assert_eq!(CBOR::try_from_hex(&cbor.hex_annotated()).unwrap(), cbor);
+    assert_eq!(CBOR::try_from_hex(&cbor.hex()).unwrap(), cbor);
+
+    // Dumps pasted from elsewhere, with their own indentation and comments.
+    let dump = indoc! {r#"
+        # A tagged date.
+        C1                  # tag(1)   date
+            1A 63E3837A     # unsigned(1675854714)
+
+    "#};
+    assert_eq!(CBOR::try_from_hex(dump).unwrap(), CBOR::to_tagged_value(1, 1675854714));
+    assert_eq!(dcbor::parse_hex("\t01\r\n02").unwrap(), [1, 2]);
+    assert_eq!(dcbor::parse_hex("# nothing here").unwrap(), []);
+}
+
+#[test]
+fn decode_invalid_hex() {
+    let cases = [
+        ("0g", 1, 2),
+        ("01 2", 1, 4),
+        ("81\n  01 x", 2, 6),
+        ("8101 # ok\n0 1", 2, 1),
+        ("é 01", 1, 1),
+        ("  \"abc\"", 1, 3),
+    ];
+    for (text, line, column) in cases {
+        let error = CBOR::try_from_hex(text).unwrap_err();
+        assert!(matches!(error.downcast_ref::<CBORError>(), Some(&CBORError::InvalidHex(l, c)) if (l, c) == (line, column)), "{:?}: {}", text, error);
+    }
+    assert_eq!(dcbor::parse_hex("01 é").unwrap_err().to_string(), "invalid hexadecimal at line 1, column 4");
+}
+
+#[test]
+fn decode_deeply_nested() {
+    const DEPTH: usize = 1_000_000;
+    let mut data = vec![0x81; DEPTH];
+    data.push(0x00);
+    let cbor = CBOR::try_from_data(&data).unwrap();
+    assert_eq!(cbor.to_cbor_data(), data);
+    assert_eq!(cbor, CBOR::try_from_data(&data).unwrap());
+
+    let description = format!("{}", cbor);
+    assert_eq!(description.len(), DEPTH * 2 + 1);
+    assert_eq!(&description[DEPTH - 2..DEPTH + 3], "[[0]]");
+    assert_eq!(cbor.diagnostic_flat(), description);
+
+    // Maps and tags nest the same way.
+    let mut data = Vec::new();
+    for _ in 0..DEPTH {
+        data.extend(hex!("a101c1"));
+    }
+    data.push(0x00);
+    let cbor = CBOR::try_from_data(&data).unwrap();
+    assert_eq!(cbor.to_cbor_data(), data);
+}
+
+#[test]
+fn decode_validator_sees_every_item() {
+    // [1, {"a": 1(h'00')}, -2.5]
+    let data = hex!("83 01 a1 6161 c1 4100 f9c100");
+    let mut seen = Vec::new();
+    let mut validator = |path: &CBORPath, event: &Event<'_>| {
+        seen.push(format!("{} {:?}", path, event));
+        Ok(())
+    };
+    let cbor = CBOR::try_from_data_with_validator(data, &DecodeOptions::default(), &mut validator).unwrap();
+    assert_eq!(cbor, CBOR::try_from_data(data).unwrap());
+    assert_eq!(seen, [
+        " StartArray(3)",
+        "[0] Unsigned(1)",
+        "[1] StartMap(1)",
+        "[1].key(0) Text(\"a\")",
+        "[1].{\"a\"} Tag(1)",
+        "[1].{\"a\"}.tag(1) Bytes([0])",
+        "[2] Float(-2.5)",
+    ]);
+}
+
+#[test]
+fn decode_validator_rejects_items() {
+    #[derive(Debug)]
+    struct Forbidden(&'static str);
+
+    impl std::fmt::Display for Forbidden {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{} is forbidden", self.0)
+        }
+    }
+
+    impl std::error::Error for Forbidden {}
+
+    let mut validator = |path: &CBORPath, event: &Event<'_>| {
+        let is_key = matches!(path.elements().last(), Some(PathElement::KeyAt(_)));
+        match event {
+            Event::Unsigned(_) | Event::Negative(_) => {},
+            Event::Text(text) if text.len() > 4 => return Err(CBORError::StringLenLimitExceeded(4).into()),
+            Event::Text(_) => {},
+            _ if is_key => return Err(Forbidden("a key that is not an integer or text").into()),
+            Event::Tag(1) => return Err(Forbidden("tag 1").into()),
+            Event::Float(_) => return Err(Forbidden("a float").into()),
+            _ => {},
+        }
+        Ok(())
+    };
+    let options = DecodeOptions::default();
+    let mut check = |data: &[u8], message: &str, offset: usize, path: &str| {
+        let error = CBOR::try_from_data_with_validator(data, &options, &mut validator).unwrap_err();
+        assert_eq!(error.to_string(), message);
+        let decode_error = error.downcast_ref::<DecodeError>().unwrap();
+        assert_eq!(decode_error.offset(), offset);
+        assert_eq!(decode_error.path().to_string(), path);
+        error
+    };
+
+    let error = check(&hex!("a2 01 00 4100 00"), "the CBOR was rejected by a validator: a key that is not an integer or text is forbidden", 3, "key(1)");
+    assert!(error.downcast_ref::<Forbidden>().is_some());
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::Rejected(_))));
+    check(&hex!("82 01 c1 00"), "the CBOR was rejected by a validator: tag 1 is forbidden", 2, "[1]");
+    check(&hex!("a1 6161 81 f93e00"), "the CBOR was rejected by a validator: a float is forbidden", 4, "{\"a\"}.[0]");
+    let error = check(&hex!("81 6568656c6c6f"), "a CBOR string is longer than the limit of 4 bytes", 1, "[0]");
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::StringLenLimitExceeded(4))));
+
+    // Data that breaks the encoding rules is rejected before it is validated.
+    let error = CBOR::try_from_data_with_validator(hex!("81 fa3fc00000"), &options, &mut validator).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::NonCanonicalNumeric)));
+}
@@ -31,6 +31,7 @@ fn test_cbor(t: impl Into<CBOR>, expected_debug: &str, expected_display: &str, e
     assert_eq!(format!("{}", cbor), expected_display);
     let data = cbor.to_cbor_data();
     assert_eq!(hex::encode(&data), expected_data);
+    assert_eq!(cbor.encoded_len(), data.len());
     let decoded_cbor = CBOR::try_from_data(&data).unwrap();
     assert_eq!(cbor, decoded_cbor);
 }
@@ -224,6 +225,35 @@ fn test_normalized_string() {
     }
 }
 
+#[test]
+fn text_normalized_at_construction() {
+    let composed = CBOR::from("\u{00E9}");
+    let decomposed = CBOR::from("e\u{0301}");
+    assert_eq!(composed, decomposed);
+    assert_eq!(decomposed.diagnostic(), "\"\u{00E9}\"");
+    assert_eq!(CBOR::from(String::from("e\u{0301}")), composed);
+
+    let mut map = Map::new();
+    map.insert("e\u{0301}", 1);
+    map.insert("\u{00E9}", 2);
+    assert_eq!(map.len(), 1);
+    let (key, value) = map.iter().next().unwrap();
+    assert_eq!(key, &composed);
+    assert_eq!(value, &CBOR::from(2));
+
+    // Normalized text is stored without being copied.
+    let text = String::from("caf\u{00E9}");
+    let ptr = text.as_ptr();
+    let text = CBOR::from(text).try_into_text().unwrap();
+    assert_eq!(text.as_ptr(), ptr);
+
+    // Raw text is stored as given, but still normalized when encoded.
+    let raw = CBOR::to_raw_text("e\u{0301}");
+    assert!(matches!(raw.as_case(), CBORCase::Text(text) if text == "e\u{0301}"));
+    assert_eq!(raw.to_cbor_data(), composed.to_cbor_data());
+    assert_eq!(CBOR::try_from_data(raw.to_cbor_data()).unwrap(), composed);
+}
+
 #[test]
 fn encode_array() {
     test_cbor(vec![1, 2, 3], "array([unsigned(1), unsigned(2), unsigned(3)])", "[1, 2, 3]", "83010203");
@@ -652,3 +682,298 @@ fn decode_infinity() {
     CBOR::try_from_data(hex!("faff800000")).err().unwrap();
     CBOR::try_from_data(hex!("fbfff0000000000000")).err().unwrap();
 }
+
+#[cfg(feature = "std")]
+#[test]
+fn encode_write_through() {
+    // A writer that accepts a limited number of bytes, then fails.
+    struct LimitedWriter {
+        data: Vec<u8>,
+        writes: usize,
+        limit: usize,
+    }
+
+    impl std::io::Write for LimitedWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.data.len() + buf.len() > self.limit {
+                return Err(std::io::Error::new(std::io::ErrorKind::WriteZero, "full"));
+            }
+            self.data.extend_from_slice(buf);
+            self.writes += 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut map = Map::new();
+    map.insert("b", vec![CBOR::from(1.5), CBOR::to_byte_string([1, 2, 3])]);
+    map.insert("a", CBOR::to_tagged_value(1, "A\u{30a}"));
+    let cbor = CBOR::from(vec![CBOR::from(map), CBOR::from(-1000), CBOR::null()]);
+    let expected = hex!("83 a2 6161 c1 62c385 6162 82 f93e00 43010203 3903e7 f6");
+    assert_eq!(cbor.to_cbor_data(), expected);
+
+    let mut writer = LimitedWriter { data: Vec::new(), writes: 0, limit: usize::MAX };
+    cbor.write_cbor_data(&mut writer).unwrap();
+    assert_eq!(writer.data, expected);
+    assert!(writer.writes > 1);
+
+    let mut buf = vec![0x01];
+    cbor.append_cbor_data(&mut buf);
+    assert_eq!(buf[1..], expected);
+
+    let mut writer = LimitedWriter { data: Vec::new(), writes: 0, limit: 10 };
+    let error = cbor.write_cbor_data(&mut writer).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+    assert_eq!(writer.data, expected[..writer.data.len()]);
+}
+
+#[test]
+fn encoded_len() {
+    let mut map = Map::new();
+    map.insert("A\u{30a}", f64::NAN);
+    map.insert(-1_000_000, 1.0e300);
+    map.insert(CBOR::to_byte_string([0; 300]), f32::MAX);
+    let date = dcbor::Date::from_timestamp(1675854714.5);
+    let cbor = CBOR::from(vec![
+        CBOR::from(map.clone()),
+        CBOR::to_tagged_value(100_000, "e\u{301}".repeat(20)),
+        CBOR::from(u64::MAX),
+        CBOR::from(-2.5),
+        CBOR::from(-3.0),
+        CBOR::from(Simple::Float(65504.5)),
+        CBOR::from(date.clone()),
+    ]);
+    assert_eq!(cbor.encoded_len(), cbor.to_cbor_data().len());
+    assert_eq!(map.encoded_len(), map.cbor_data().len());
+    for profile in [EncodingProfile::CoreDeterministic, EncodingProfile::Preferred] {
+        assert_eq!(cbor.encoded_len_with_profile(profile), cbor.to_cbor_data_with_profile(profile).len());
+    }
+
+    for len in [0, 23, 24, 255, 256, 65536] {
+        let bytes = ByteString::new(vec![0; len]);
+        assert_eq!(bytes.encoded_len(), CBOR::from(bytes.clone()).to_cbor_data().len());
+    }
+    assert_eq!(date.tagged_encoded_len(), date.tagged_cbor_data().len());
+}
+
+#[test]
+fn encode_memoized() {
+    let shared = CBOR::from(vec![CBOR::to_raw_text("A\u{30a}"), CBOR::from([1.5, 2.0])]).memoized();
+    let documents: Vec<CBOR> = (0..3)
+        .map(|i| CBOR::from(vec![CBOR::from(i), shared.clone(), CBOR::to_tagged_value(1, shared.clone())]))
+        .collect();
+    let expected_shared = hex!("82 62c385 82f93e0002");
+    for (i, document) in documents.iter().enumerate() {
+        let mut expected = vec![0x83, i as u8];
+        expected.extend(expected_shared);
+        expected.push(0xc1);
+        expected.extend(expected_shared);
+        assert_eq!(document.to_cbor_data(), expected);
+        assert_eq!(document.encoded_len(), expected.len());
+        #[cfg(feature = "std")]
+        {
+            let mut written = Vec::new();
+            document.write_cbor_data(&mut written).unwrap();
+            assert_eq!(written, expected);
+        }
+    }
+    assert_eq!(shared.to_cbor_data(), expected_shared);
+    assert_eq!(shared.to_cbor_data_with_profile(EncodingProfile::CoreDeterministic), hex!("82 6341cc8a 82f93e0002"));
+
+    struct Length;
+
+    impl DigestAlgorithm for Length {
+        type Output = usize;
+
+        fn digest(data: &[u8]) -> usize {
+            data.len()
+        }
+    }
+
+    struct FirstByte;
+
+    impl DigestAlgorithm for FirstByte {
+        type Output = usize;
+
+        fn digest(data: &[u8]) -> usize {
+            data[0] as usize
+        }
+    }
+
+    assert_eq!(shared.memoized_digest::<Length>(), expected_shared.len());
+    assert_eq!(shared.memoized_digest::<Length>(), expected_shared.len());
+    assert_eq!(shared.memoized_digest::<FirstByte>(), 0x82);
+    assert_eq!(shared.clone().memoized_digest::<Length>(), expected_shared.len());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn encode_memoized_write() {
+    // A writer that counts the writes made to it.
+    struct CountingWriter {
+        data: Vec<u8>,
+        writes: usize,
+    }
+
+    impl std::io::Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.data.extend_from_slice(buf);
+            self.writes += 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let cbor = CBOR::from(vec![CBOR::from("a"), CBOR::from([1, 2, 3])]);
+    let expected = hex!("82 6161 83010203");
+    // Encoding a value does not memoize it.
+    assert_eq!(cbor.to_cbor_data(), expected);
+    let mut writer = CountingWriter { data: Vec::new(), writes: 0 };
+    cbor.write_cbor_data(&mut writer).unwrap();
+    assert_eq!(writer.data, expected);
+    assert!(writer.writes > 1);
+
+    // A memoized value is written with a single write of its encoding.
+    let cbor = cbor.memoized();
+    let mut writer = CountingWriter { data: Vec::new(), writes: 0 };
+    cbor.write_cbor_data(&mut writer).unwrap();
+    assert_eq!(writer.data, expected);
+    assert_eq!(writer.writes, 1);
+}
+
+#[cfg(feature = "multithreaded")]
+#[test]
+fn encode_memoized_across_threads() {
+    let shared = CBOR::from(vec![CBOR::from("shared"), CBOR::from([1, 2, 3])]);
+    let expected = shared.to_cbor_data_with_profile(EncodingProfile::Preferred);
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                let document = CBOR::from(vec![CBOR::from(i), shared.clone()]);
+                (document.to_cbor_data(), shared.clone().memoized().to_cbor_data())
+            })
+        })
+        .collect();
+    for (i, handle) in handles.into_iter().enumerate() {
+        let (document, encoded) = handle.join().unwrap();
+        assert_eq!(document[1], i as u8);
+        assert_eq!(document[2..], expected);
+        assert_eq!(encoded, expected);
+    }
+}
+
+#[test]
+fn encode_validated_data() {
+    // {1: [1.5, "a"], 2: h'00'}
+    let data = hex!("a2 01 82f93e006161 02 4100");
+    let raw = CBOR::from_validated_data(data).unwrap();
+    assert_eq!(raw.to_cbor_data(), data);
+    assert_eq!(raw.encoded_len(), data.len());
+
+    let mut map = Map::new();
+    map.insert(raw.clone(), "raw");
+    map.insert(CBOR::from_validated_data(hex!("a0")).unwrap(), "empty");
+    map.insert(10, raw.clone());
+    let document = CBOR::from(map);
+    let expected = hex!("a3 0a a2 01 82f93e006161 02 4100  a0 65656d707479  a2 01 82f93e006161 02 4100 63726177");
+    assert_eq!(document.to_cbor_data(), expected);
+    #[cfg(feature = "std")]
+    {
+        let mut written = Vec::new();
+        document.write_cbor_data(&mut written).unwrap();
+        assert_eq!(written, expected);
+    }
+
+    assert_eq!(raw.diagnostic_flat(), r#"{1: [1.5, "a"], 2: h'00'}"#);
+    assert_eq!(raw.hex_opt(true, None), CBOR::try_from_data(data).unwrap().hex_opt(true, None));
+    assert_eq!(raw, CBOR::try_from_data(data).unwrap());
+    let CBORCase::Map(expanded) = raw.as_case() else { panic!() };
+    assert_eq!(expanded.get::<i32, CBOR>(2).unwrap(), CBOR::to_byte_string([0]));
+    assert_eq!(CBOR::from(raw.clone().into_case()), raw);
+
+    assert!(matches!(CBOR::from_validated_data(hex!("a2 0200 0100")).unwrap_err().downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+    assert!(matches!(CBOR::from_validated_data(hex!("01 02")).unwrap_err().downcast_ref::<CBORError>(), Some(CBORError::UnusedData(1))));
+    assert!(matches!(CBOR::from_validated_data(hex!("f93c00")).unwrap_err().downcast_ref::<CBORError>(), Some(CBORError::NonCanonicalNumeric)));
+}
+
+#[test]
+fn ordering_matches_encoding() {
+    fn hash_of(value: &impl std::hash::Hash) -> u64 {
+        use std::hash::{BuildHasher, BuildHasherDefault};
+        BuildHasherDefault::<std::collections::hash_map::DefaultHasher>::default().hash_one(value)
+    }
+
+    let mut map = Map::new();
+    map.insert(1, "a");
+    map.insert("b", [1, 2]);
+    let values = vec![
+        CBOR::from(0),
+        CBOR::from(23),
+        CBOR::from(24),
+        CBOR::from(u64::MAX),
+        CBOR::from(-1),
+        CBOR::from(-1000),
+        CBOR::to_byte_string([2]),
+        CBOR::to_byte_string([1, 1]),
+        CBOR::from(""),
+        CBOR::from("a"),
+        CBOR::from("\u{00E9}"),
+        CBOR::from(vec![CBOR::from(1), CBOR::from(2)]),
+        CBOR::from(vec![CBOR::from(1), CBOR::from(-1)]),
+        CBOR::from([[1]]),
+        CBOR::from(map.clone()),
+        CBOR::from_validated_data(map.cbor_data()).unwrap(),
+        CBOR::to_tagged_value(1, "a"),
+        CBOR::to_tagged_value(1000, 0),
+        CBOR::from(false),
+        CBOR::null(),
+        CBOR::from(1.5),
+        CBOR::from(f64::NAN),
+        CBOR::from(-f64::INFINITY),
+    ];
+    for a in &values {
+        for b in &values {
+            let expected = a.to_cbor_data().cmp(&b.to_cbor_data());
+            // Compare both with and without memoized encodings.
+            let (a2, b2) = (CBOR::try_from_data(a.to_cbor_data()).unwrap().memoized(), CBOR::try_from_data(b.to_cbor_data()).unwrap().memoized());
+            assert_eq!(a2.cmp(&b2), expected, "{} {}", a, b);
+            assert_eq!(a.cmp(&b2), expected, "{} {}", a, b);
+            assert_eq!(a.cmp(b), expected, "{} {}", a, b);
+            assert_eq!(a == b, expected.is_eq());
+        }
+        let memoized = CBOR::try_from_data(a.to_cbor_data()).unwrap().memoized();
+        assert_eq!(hash_of(&memoized), hash_of(a));
+    }
+
+    // Equality follows the encoding.
+    assert_eq!(CBOR::from(f64::NAN), CBOR::from(Simple::Float(f64::NAN)));
+    assert_eq!(CBOR::from(Simple::Float(1.0)), CBOR::from(1));
+    assert_eq!(CBOR::to_raw_text("e\u{301}"), CBOR::from("\u{00E9}"));
+    assert_eq!(hash_of(&CBOR::to_raw_text("e\u{301}")), hash_of(&CBOR::from("\u{00E9}")));
+    assert_eq!(Simple::Float(f64::NAN), Simple::Float(f64::NAN));
+    assert!(Simple::False < Simple::True && Simple::Null < Simple::Float(1.5));
+    assert!(ByteString::new([2]) < ByteString::new([1, 1]));
+    let mut other_map = map.clone();
+    assert_eq!(hash_of(&map), hash_of(&other_map));
+    other_map.insert(2, "c");
+    assert!(map < other_map);
+
+    let set: std::collections::HashSet<CBOR> = [CBOR::from(1), CBOR::from(Simple::Float(1.0)), CBOR::from("a")].into_iter().collect();
+    assert_eq!(set.len(), 2);
+    let mut sorted: Vec<CBOR> = values.iter().cloned().rev().collect();
+    sorted.sort();
+    sorted.dedup();
+    let mut key_map = Map::new();
+    for value in &values {
+        key_map.insert(value.clone(), CBOR::null());
+    }
+    let keys: Vec<CBOR> = key_map.iter().map(|(key, _)| key.clone()).collect();
+    assert_eq!(sorted, keys);
+}
@@ -0,0 +1,96 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+fn records() -> CBOR {
+    let records: Vec<CBOR> = (0..10_000u32)
+        .map(|i| {
+            let mut map = Map::new();
+            map.insert("id", i);
+            map.insert("name", format!("record {}", i));
+            map.insert("tags", CBOR::to_tagged_value(1, [i, i * 2]));
+            map.into()
+        })
+        .collect();
+    records.into()
+}
+
+#[test]
+fn encoded_random_access() {
+    let cbor = records();
+    let data = cbor.to_cbor_data();
+    let encoded = EncodedCBOR::try_from_data(&data).unwrap();
+    assert_eq!(encoded.data(), data);
+    assert_eq!(encoded.array_len().unwrap(), 10_000);
+    assert!(encoded.map_len().is_err());
+
+    let record = encoded.array_item(5000).unwrap().unwrap();
+    assert_eq!(record.map_len().unwrap(), 3);
+    assert_eq!(record.to_cbor(), CBOR::try_from_data(record.data()).unwrap());
+    assert_eq!(record.map_value("name").unwrap().unwrap().to_cbor(), CBOR::from("record 5000"));
+    assert_eq!(record.map_value("missing").unwrap(), None);
+    assert_eq!(record.map_value(0).unwrap(), None);
+
+    let (tag, content) = record.map_value("tags").unwrap().unwrap().tagged_value().unwrap();
+    assert_eq!(tag.value(), 1);
+    assert_eq!(content.to_cbor(), CBOR::from([5000, 10000]));
+    assert!(content.tagged_value().is_err());
+
+    assert_eq!(encoded.array_item(10_000).unwrap(), None);
+    assert!(record.array_item(0).is_err());
+
+    let keys: Vec<CBOR> = record.map_entries().unwrap().map(|(key, _)| key.to_cbor()).collect();
+    assert_eq!(keys, [CBOR::from("id"), CBOR::from("name"), CBOR::from("tags")]);
+    assert_eq!(encoded.array_items().unwrap().len(), 10_000);
+}
+
+#[test]
+fn encoded_index() {
+    let cbor = records();
+    let data = cbor.to_cbor_data();
+    let encoded = EncodedCBOR::try_from_data(&data).unwrap();
+    let index = encoded.index().unwrap();
+    assert_eq!(index.len(), 10_000);
+    assert_eq!(index.item(), encoded);
+    for i in [0, 1, 4999, 9999] {
+        assert_eq!(index.array_item(i).unwrap(), encoded.array_item(i).unwrap());
+    }
+    assert_eq!(index.array_item(10_000).unwrap(), None);
+    assert!(index.map_value("id").is_err());
+    assert!(index.map_entry(0).is_err());
+
+    let record = index.array_item(1234).unwrap().unwrap().index().unwrap();
+    assert_eq!(record.len(), 3);
+    assert_eq!(record.map_value("id").unwrap().unwrap().to_cbor(), CBOR::from(1234));
+    assert_eq!(record.map_value("tags").unwrap(), record.item().map_value("tags").unwrap());
+    assert_eq!(record.map_value("zzz").unwrap(), None);
+    assert_eq!(record.map_value("").unwrap(), None);
+    let (key, value) = record.map_entry(1).unwrap().unwrap();
+    assert_eq!(key.to_cbor(), CBOR::from("name"));
+    assert_eq!(value.to_cbor(), CBOR::from("record 1234"));
+    assert!(record.array_item(0).is_err());
+
+    // Every key of a larger map is found by binary search.
+    let mut map = Map::new();
+    for i in 0..300 {
+        map.insert(i, -i);
+    }
+    let data = CBOR::from(map).to_cbor_data();
+    let index = EncodedCBOR::try_from_data(&data).unwrap().index().unwrap();
+    for i in 0..300 {
+        assert_eq!(index.map_value(i).unwrap().unwrap().to_cbor(), CBOR::from(-i));
+    }
+    assert_eq!(index.map_value(300).unwrap(), None);
+    assert_eq!(index.map_value(-1).unwrap(), None);
+
+    let empty = hex!("80");
+    assert!(EncodedCBOR::try_from_data(&empty).unwrap().index().unwrap().is_empty());
+    assert!(EncodedCBOR::try_from_data(&hex!("01")).unwrap().index().is_err());
+}
+
+#[test]
+fn encoded_rejects_invalid_data() {
+    for data in [&hex!("1817")[..], &hex!("a2 02 00 01 00"), &hex!("82 01"), &hex!("01 02"), &[]] {
+        let error = EncodedCBOR::try_from_data(data).unwrap_err();
+        assert_eq!(error.to_string(), CBOR::try_from_data(data).unwrap_err().to_string());
+    }
+}
@@ -0,0 +1,108 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+fn events(data: &[u8]) -> Vec<Event<'_>> {
+    Events::new(data).map(Result::unwrap).collect()
+}
+
+fn events_error(data: &[u8]) -> anyhow::Error {
+    Events::new(data).find_map(Result::err).unwrap()
+}
+
+#[test]
+fn events_structure() {
+    // [1, -2, h'01', "a", {1: [], 2: {}}, 1(null), true, 1.5]
+    let data = hex!("88 01 21 4101 6161 a2 01 80 02 a0 c1 f6 f5 f93e00");
+    assert_eq!(events(&data), [
+        Event::StartArray(8),
+        Event::Unsigned(1),
+        Event::Negative(1),
+        Event::Bytes(&[0x01]),
+        Event::Text("a"),
+        Event::StartMap(2),
+        Event::Unsigned(1),
+        Event::StartArray(0),
+        Event::End,
+        Event::Unsigned(2),
+        Event::StartMap(0),
+        Event::End,
+        Event::End,
+        Event::Tag(1),
+        Event::Simple(Simple::Null),
+        Event::Simple(Simple::True),
+        Event::Float(1.5),
+        Event::End,
+    ]);
+
+    assert_eq!(events(&hex!("c1c601")), [Event::Tag(1), Event::Tag(6), Event::Unsigned(1)]);
+}
+
+#[test]
+fn events_large_array() {
+    const COUNT: u64 = 1_000_000;
+    // [{"n": 0, "s": "skip"}, {"n": 1, "s": "skip"}, ...]
+    let mut data = CBOR::from(COUNT).to_cbor_data();
+    data[0] |= 0x80;
+    for n in 0..COUNT {
+        data.extend(hex!("a2 616e"));
+        data.extend(CBOR::from(n).to_cbor_data());
+        data.extend(hex!("6173 64736b6970"));
+    }
+
+    let mut sum = 0;
+    let mut is_n = false;
+    let mut events = Events::new(&data);
+    for event in &mut events {
+        match event.unwrap() {
+            Event::Text(text) => is_n = text == "n",
+            Event::Unsigned(n) if is_n => sum += n,
+            _ => {},
+        }
+    }
+    assert_eq!(sum, COUNT * (COUNT - 1) / 2);
+    assert_eq!(events.position(), data.len());
+}
+
+#[test]
+fn events_errors_match_decoder() {
+    let cases = [
+        "1817",                             // non-minimal integer
+        "f93c00",                           // float that is an integer
+        "f7",                               // undefined
+        "6365cc81",                         // non-NFC text
+        "62c328",                           // invalid UTF-8
+        "82a202000100",                     // misordered keys
+        "82a201000100",                     // duplicate keys
+        "84000000a1646e616d65c11805",       // non-minimal integer in a tag in a map
+        "830102",                           // truncated array
+        "0102",                             // unused data
+        "1c",                               // unsupported header value
+        "8201c2480100000000000000",         // bignum that fits in 64 bits
+        "c349000100000000000000",           // bignum with a leading zero
+        "a101c201",                         // bignum that is not a byte string
+    ];
+    for case in cases {
+        let data = hex::decode(case).unwrap();
+        let decode_error = CBOR::try_from_data(&data).unwrap_err();
+        let events_error = events_error(&data);
+        assert_eq!(events_error.to_string(), decode_error.to_string(), "{}", case);
+        let decode_location = decode_error.downcast_ref::<DecodeError>().unwrap();
+        let events_location = events_error.downcast_ref::<DecodeError>().unwrap();
+        assert_eq!(events_location.offset(), decode_location.offset(), "{}", case);
+        assert_eq!(events_location.path(), decode_location.path(), "{}", case);
+    }
+}
+
+#[test]
+fn events_stop_after_error() {
+    // [1, 2] followed by an extra byte.
+    let mut events = Events::new(&hex!("82010200"));
+    assert_eq!(events.by_ref().take(4).map(Result::unwrap).collect::<Vec<_>>(), [
+        Event::StartArray(2),
+        Event::Unsigned(1),
+        Event::Unsigned(2),
+        Event::End,
+    ]);
+    assert!(matches!(events.next().unwrap().unwrap_err().downcast_ref::<CBORError>(), Some(CBORError::UnusedData(1))));
+    assert!(events.next().is_none());
+}
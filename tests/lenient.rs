@@ -0,0 +1,145 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+type Report<'a> = &'a [(usize, &'a str, TransformationKind)];
+type IsExpected = fn(&CBORError) -> bool;
+
+fn lenient(hex: &str) -> (CBOR, Vec<(usize, String, TransformationKind)>) {
+    let data = hex::decode(hex.replace(' ', "")).unwrap();
+    let (cbor, report) = CBOR::try_from_data_lenient(data).unwrap();
+    let report = report.into_iter().map(|t| (t.offset, t.path.to_string(), t.kind)).collect();
+    (cbor, report)
+}
+
+#[test]
+fn lenient_accepts_canonical_data_unchanged() {
+    let data = hex!("87 01 21 420102 6568656c6c6f a2 01 6161 6162 81f5 c1 f94100 f6");
+    let (cbor, report) = CBOR::try_from_data_lenient(data).unwrap();
+    assert_eq!(cbor, CBOR::try_from_data(data).unwrap());
+    assert!(report.is_empty());
+}
+
+#[test]
+fn lenient_transformations() {
+    use TransformationKind::*;
+    let cases: &[(&str, &str, Report)] = &[
+        // Non-minimal integers, lengths, and tags
+        ("1817", "23", &[(0, "", NonMinimalHeader)]),
+        ("3a00000000", "-1", &[(0, "", NonMinimalHeader)]),
+        ("98 01 00", "[0]", &[(0, "", NonMinimalHeader)]),
+        ("d8 01 00", "1(0)", &[(0, "", NonMinimalHeader)]),
+        // Indefinite lengths
+        ("9f 01 9f ff ff", "[1, []]", &[(0, "", IndefiniteLength), (2, "[1]", IndefiniteLength)]),
+        ("bf 01 02 ff", "{1: 2}", &[(0, "", IndefiniteLength)]),
+        ("5f 4101 40 4102 ff", "h'0102'", &[(0, "", IndefiniteLength)]),
+        ("7f 6161 6162 ff", r#""ab""#, &[(0, "", IndefiniteLength)]),
+        ("7f ff", r#""""#, &[(0, "", IndefiniteLength)]),
+        // Map keys
+        ("a2 02 00 01 00", "{1: 0, 2: 0}", &[(0, "", MapKeysSorted)]),
+        ("a2 01 00 01 00", "{1: 0}", &[(3, "key(1)", DuplicateMapEntryRemoved)]),
+        ("a3 01 00 02 00 01 00", "{1: 0, 2: 0}", &[(5, "key(2)", DuplicateMapEntryRemoved)]),
+        ("a1 00 a2 02 00 01 00", "{0: {1: 0, 2: 0}}", &[(2, "{0}", MapKeysSorted)]),
+        // Text
+        ("6365cc81", r#""é""#, &[(0, "", TextNormalized)]),
+        ("7f 6165 62cc81 ff", r#""é""#, &[(0, "", IndefiniteLength), (0, "", TextNormalized)]),
+        // Floats
+        ("f93c00", "1", &[(0, "", FloatReduced)]),
+        ("fa3fc00000", "1.5", &[(0, "", FloatReduced)]),
+        ("fb7ff8000000000001", "NaN", &[(0, "", FloatReduced)]),
+        // Undefined
+        ("82 f6 f7", "[null, null]", &[(2, "[1]", UndefinedReplaced)]),
+        // Bignums
+        ("c2 49 000100000000000000", "72057594037927936", &[(1, "tag(2)", BignumReduced)]),
+        ("c3 4a 00010000000000000000", "3(h'010000000000000000')", &[(1, "tag(3)", BignumReduced)]),
+        ("c2 5f 4101 ff", "1", &[(1, "tag(2)", IndefiniteLength), (1, "tag(2)", BignumReduced)]),
+        ("c3 40", "-1", &[(1, "tag(3)", BignumReduced)]),
+    ];
+    for (data, diagnostic, expected) in cases {
+        let (cbor, report) = lenient(data);
+        assert_eq!(cbor.diagnostic_flat(), *diagnostic, "{}", data);
+        let expected: Vec<_> = expected.iter().map(|(offset, path, kind)| (*offset, path.to_string(), *kind)).collect();
+        assert_eq!(report, expected, "{}", data);
+        // The result is always valid dCBOR.
+        let encoded = cbor.to_cbor_data();
+        assert_eq!(CBOR::try_from_data(&encoded).unwrap().to_cbor_data(), encoded);
+    }
+}
+
+#[test]
+fn lenient_keys_collide_after_normalization() {
+    // {"é" (NFD): 1, "é" (NFC): 1}
+    let (cbor, report) = lenient("a2 6365cc81 01 62c3a9 01");
+    assert_eq!(cbor.diagnostic_flat(), r#"{"é": 1}"#);
+    let kinds: Vec<_> = report.into_iter().map(|(_, _, kind)| kind).collect();
+    assert_eq!(kinds, [TransformationKind::TextNormalized, TransformationKind::DuplicateMapEntryRemoved]);
+
+    // {"é" (NFD): 1, "é" (NFC): 2}
+    let error = CBOR::try_from_data_lenient(hex!("a2 6365cc81 01 62c3a9 02")).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::ConflictingMapKey)));
+    let error = error.downcast_ref::<DecodeError>().unwrap();
+    assert_eq!(error.offset(), 6);
+    assert_eq!(error.path().to_string(), "key(1)");
+
+    // {1.0: "a", 1: "b"}
+    let error = CBOR::try_from_data_lenient(hex!("a2 f93c00 6161 01 6162")).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::ConflictingMapKey)));
+}
+
+#[test]
+fn lenient_rejects_malformed_data() {
+    let cases: &[(&str, IsExpected)] = &[
+        ("", |e| matches!(e, CBORError::Underrun)),
+        ("9f 01", |e| matches!(e, CBORError::Underrun)),
+        ("ff", |e| matches!(e, CBORError::MalformedIndefiniteLength)),
+        ("81 ff", |e| matches!(e, CBORError::MalformedIndefiniteLength)),
+        ("bf 01 ff", |e| matches!(e, CBORError::MalformedIndefiniteLength)),
+        ("5f 6161 ff", |e| matches!(e, CBORError::MalformedIndefiniteLength)),
+        ("7f 7f ff ff", |e| matches!(e, CBORError::MalformedIndefiniteLength)),
+        ("1f", |e| matches!(e, CBORError::UnsupportedHeaderValue(31))),
+        ("1c", |e| matches!(e, CBORError::UnsupportedHeaderValue(28))),
+        ("62 c328", |e| matches!(e, CBORError::InvalidString(_))),
+        ("f0", |e| matches!(e, CBORError::InvalidSimpleValue)),
+        ("f8 14", |e| matches!(e, CBORError::InvalidSimpleValue)),
+        ("00 00", |e| matches!(e, CBORError::UnusedData(1))),
+        ("c2 01", |e| matches!(e, CBORError::WrongType)),
+        ("c3 9f ff", |e| matches!(e, CBORError::WrongType)),
+    ];
+    for (data, is_expected) in cases {
+        let bytes = hex::decode(data.replace(' ', "")).unwrap();
+        let error = CBOR::try_from_data_lenient(bytes).unwrap_err();
+        let cbor_error = error.downcast_ref::<CBORError>().unwrap();
+        assert!(is_expected(cbor_error), "{}: {}", data, cbor_error);
+    }
+}
+
+#[test]
+fn lenient_enforces_limits() {
+    let options = DecodeOptions {
+        max_depth: 2,
+        max_container_len: 2,
+        max_string_len: 3,
+        ..Default::default()
+    };
+    let decode = |hex: &str| {
+        let data = hex::decode(hex.replace(' ', "")).unwrap();
+        let error = CBOR::try_from_data_lenient_with_options(data, &options).unwrap_err();
+        error.downcast_ref::<CBORError>().unwrap().to_string()
+    };
+    assert_eq!(decode("9f 01 02 03 ff"), CBORError::ContainerLenLimitExceeded(2).to_string());
+    assert_eq!(decode("bf 01 01 02 02 03 03 ff"), CBORError::ContainerLenLimitExceeded(2).to_string());
+    assert_eq!(decode("5f 42 0102 42 0304 ff"), CBORError::StringLenLimitExceeded(3).to_string());
+    assert_eq!(decode("81 81 81 00"), CBORError::DepthLimitExceeded(2).to_string());
+}
+
+#[test]
+fn lenient_deeply_nested() {
+    const DEPTH: usize = 10_000;
+    let mut data = vec![0x9f; DEPTH];
+    data.push(0x00);
+    data.extend(vec![0xff; DEPTH]);
+    let (cbor, report) = CBOR::try_from_data_lenient(&data).unwrap();
+    assert_eq!(report.len(), DEPTH);
+    let mut expected = vec![0x81; DEPTH];
+    expected.push(0x00);
+    assert_eq!(cbor.to_cbor_data(), expected);
+}
@@ -0,0 +1,133 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+use indoc::indoc;
+
+fn violations(data: &[u8]) -> Vec<String> {
+    dcbor::lint(data).violations().iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn lint_clean_data() {
+    let mut map = Map::new();
+    map.insert("a", [1.5, -2.0]);
+    map.insert(10, CBOR::to_tagged_value(1, "héllo"));
+    map.insert(CBOR::to_byte_string([1, 2, 3]), f64::NAN);
+    let data = CBOR::from(map).to_cbor_data();
+    assert!(dcbor::lint(&data).is_clean());
+    assert!(dcbor::lint(&hex!("f5")).is_clean());
+}
+
+#[test]
+fn lint_reports_every_violation() {
+    // [1(h'00' in a long header), "e\u{301}", 0xc0, {"b": f64 1.5, "a": NaN, "a": undefined}, simple(99)] 99
+    let data = hex!("
+        98 05
+            c1 5800
+            63 65cc81
+            f8 c0
+            a3
+                61 62   fb 3ff8000000000000
+                61 61   f97e01
+                61 61   f7
+            f8 63
+        18 63
+    ");
+    assert_eq!(violations(&data), [
+        "the header is not in its shortest form at offset 0",
+        "the header is not in its shortest form at offset 3 ([0].tag(1))",
+        "the text is not in Unicode Normalization Form C at offset 5 ([1])",
+        "simple(192) is not supported at offset 9 ([2])",
+        "the floating point number is not in its shortest form at offset 14 ([3].{\"b\"})",
+        "the map key does not sort after the previous key at offset 23 ([3].key(1))",
+        "the NaN is not encoded as f97e00 at offset 25 ([3].{\"a\"})",
+        "the map key duplicates the previous key at offset 28 ([3].key(2))",
+        "simple(23) is not supported at offset 30 ([3].{\"a\"})",
+        "simple(99) is not supported at offset 31 ([4])",
+        "2 bytes of data follow the item at offset 33",
+    ]);
+    let report = dcbor::lint(&data);
+    assert!(matches!(report.violations()[3].kind, ViolationKind::UnsupportedSimpleValue(192)));
+    assert!(matches!(report.violations()[10].kind, ViolationKind::TrailingData(2)));
+}
+
+#[test]
+fn lint_stops_at_malformed_data() {
+    // Invalid UTF-8 is not malformed, so examination continues past it.
+    assert_eq!(violations(&hex!("82 62c328 1801")), [
+        "the text is not valid UTF-8 at offset 1 ([0])",
+        "the header is not in its shortest form at offset 4 ([1])",
+    ]);
+    assert_eq!(violations(&hex!("83 1801 5f")), [
+        "the header is not in its shortest form at offset 1 ([0])",
+        "malformed: ensupported value in CBOR header at offset 3 ([1])",
+    ]);
+    let report = dcbor::lint(&hex!("a1 1801 63 6162"));
+    assert_eq!(report.violations().len(), 2);
+    assert!(matches!(report.violations()[1].kind, ViolationKind::Malformed(CBORError::Underrun)));
+    assert_eq!(report.violations()[1].path().to_string(), "{h'1801'}");
+    assert!(matches!(dcbor::lint(&[]).violations()[0].kind, ViolationKind::Malformed(CBORError::Underrun)));
+}
+
+#[test]
+fn lint_report_hex_annotated() {
+    let data = hex!("a2 626162 fa3fc00000 6161 c1 1a00000001 00");
+    let expected = indoc! {r#"
+        a2                  # map(2)
+            62              # text(2)
+                6162        # "ab"
+            fa3fc00000      # 1.5 <-- the floating point number is not in its shortest form
+            61              # text(1) <-- the map key does not sort after the previous key
+                61          # "a"
+            c1              # tag(1)
+                1a00000001  # unsigned(1) <-- the header is not in its shortest form
+        00                  # unsigned(0) <-- 1 bytes of data follow the item
+    "#}.trim();
+    assert_eq!(dcbor::lint(&data).hex_opt(None), expected);
+}
+
+#[test]
+fn lint_agrees_with_validate() {
+    let cases = [
+        &hex!("a2616101616282020a")[..],
+        &hex!("a202000100"),
+        &hex!("a2010001 00"),
+        &hex!("fa3fc00000"),
+        &hex!("f97e00"),
+        &hex!("fb7ff8000000000000"),
+        &hex!("6365cc81"),
+        &hex!("c1c1c1 00"),
+        &hex!("820100"),
+        &hex!("8201"),
+    ];
+    for data in cases {
+        assert_eq!(dcbor::lint(data).is_clean(), dcbor::validate(data).is_ok(), "{}", hex::encode(data));
+    }
+}
+
+#[test]
+fn lint_deeply_nested() {
+    const DEPTH: usize = 100_000;
+    let mut data = vec![0x81; DEPTH];
+    data.extend(hex!("1801"));
+    let report = dcbor::lint(&data);
+    assert_eq!(report.violations().len(), 1);
+    assert_eq!(report.violations()[0].offset, DEPTH);
+    assert_eq!(report.violations()[0].path().elements().len(), DEPTH);
+}
+
+#[test]
+fn lint_deeply_nested_violations() {
+    // Arrays of one element, each with a non-minimal header, nested so that
+    // every violation has a longer path than the one before.
+    const DEPTH: usize = 16_000;
+    let mut data = hex!("9801").repeat(DEPTH);
+    data.push(0x00);
+    let report = dcbor::lint(&data);
+    assert_eq!(report.violations().len(), DEPTH);
+    for (depth, violation) in report.violations().iter().enumerate().step_by(1000) {
+        assert_eq!(violation.offset, depth * 2);
+        assert!(matches!(violation.kind, ViolationKind::NonMinimalHeader));
+        assert_eq!(violation.path().elements(), vec![PathElement::Index(0); depth]);
+    }
+    assert_eq!(violations(&hex!("9801 9801 9801 00"))[2], "the header is not in its shortest form at offset 4 ([0].[0])");
+}
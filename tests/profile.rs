@@ -0,0 +1,215 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+const PROFILES: [EncodingProfile; 3] = [EncodingProfile::Dcbor, EncodingProfile::CoreDeterministic, EncodingProfile::Preferred];
+
+fn options(profile: EncodingProfile) -> DecodeOptions {
+    DecodeOptions { profile, ..Default::default() }
+}
+
+fn decode(data: &[u8], profile: EncodingProfile) -> anyhow::Result<CBOR> {
+    CBOR::try_from_data_with_options(data, &options(profile))
+}
+
+fn decode_error(data: &[u8], profile: EncodingProfile) -> CBORError {
+    decode(data, profile).unwrap_err().downcast_ref::<CBORError>().unwrap().clone()
+}
+
+#[test]
+fn profile_default_is_dcbor() {
+    assert_eq!(EncodingProfile::default(), EncodingProfile::Dcbor);
+    assert_eq!(DecodeOptions::default().profile, EncodingProfile::Dcbor);
+    let cbor = CBOR::from(vec![CBOR::from(Simple::Float(2.0)), CBOR::from(1.5)]);
+    assert_eq!(cbor.to_cbor_data_with_profile(EncodingProfile::Dcbor), cbor.to_cbor_data());
+}
+
+#[test]
+fn profile_encoding() {
+    let mut map = Map::new();
+    map.insert(Simple::Float(1.0), "float");
+    map.insert(-1, "negative");
+    map.insert(10, "ten");
+    let cbor = CBOR::from(vec![
+        CBOR::from(Simple::Float(-0.0)),
+        CBOR::from(Simple::Float(100000.0)),
+        CBOR::from(Simple::Float(f64::from_bits(0x7ff8000000000001))),
+        CBOR::to_raw_text("A\u{30a}"),
+        CBOR::from(map),
+    ]);
+    let dcbor = hex!("
+        85
+            00
+            1a000186a0
+            f97e00
+            62c385
+            a3
+                01 65666c6f6174
+                0a 6374656e
+                20 686e65676174697665
+    ");
+    let cde = hex!("
+        85
+            f98000
+            fa47c35000
+            fb7ff8000000000001
+            6341cc8a
+            a3
+                0a 6374656e
+                20 686e65676174697665
+                f93c00 65666c6f6174
+    ");
+    assert_eq!(cbor.to_cbor_data(), dcbor);
+    assert_eq!(cbor.to_cbor_data_with_profile(EncodingProfile::CoreDeterministic), cde);
+    assert_eq!(cbor.to_cbor_data_with_profile(EncodingProfile::Preferred), cde);
+
+    for profile in [EncodingProfile::CoreDeterministic, EncodingProfile::Preferred] {
+        let decoded = decode(&cde, profile).unwrap();
+        assert_eq!(decoded.to_cbor_data_with_profile(profile), cde);
+        assert_eq!(decoded.to_cbor_data(), dcbor);
+    }
+    assert!(matches!(decode_error(&cde, EncodingProfile::Dcbor), CBORError::NonCanonicalNumeric));
+}
+
+#[test]
+fn profile_decoding_rules() {
+    // Each case is decoded under dCBOR, CDE, and preferred serialization in
+    // turn, and is accepted under those marked `true`.
+    let cases: [(&[u8], [bool; 3]); 14] = [
+        // Non-minimal headers.
+        (&hex!("1817"), [false, false, false]),
+        (&hex!("f90000"), [false, true, true]),
+        // A float that fits in fewer bytes.
+        (&hex!("fa3fc00000"), [false, false, false]),
+        (&hex!("f93e00"), [true, true, true]),
+        // NaNs.
+        (&hex!("f97e00"), [true, true, true]),
+        (&hex!("f97e01"), [false, true, true]),
+        (&hex!("fa7fc00000"), [false, false, false]),
+        // Simple values.
+        (&hex!("f7"), [false, true, true]),
+        (&hex!("f820"), [false, true, true]),
+        (&hex!("f818"), [false, false, false]),
+        // Text not in NFC.
+        (&hex!("6341cc8a"), [false, true, true]),
+        // Map key order.
+        (&hex!("a2 0100 0200"), [true, true, true]),
+        (&hex!("a2 0200 0100"), [false, false, true]),
+        (&hex!("a2 0100 0100"), [false, false, false]),
+    ];
+    for (data, accepted) in cases {
+        for (profile, accepted) in PROFILES.into_iter().zip(accepted) {
+            assert_eq!(decode(data, profile).is_ok(), accepted, "{} {:?}", hex::encode(data), profile);
+        }
+    }
+    assert!(matches!(decode_error(&hex!("a2 0200 0100"), EncodingProfile::CoreDeterministic), CBORError::MisorderedMapKey));
+    assert!(matches!(decode_error(&hex!("a2 0100 0100"), EncodingProfile::CoreDeterministic), CBORError::DuplicateMapKey));
+    assert!(matches!(decode_error(&hex!("a2 0100 0100"), EncodingProfile::Preferred), CBORError::DuplicateMapKey));
+    assert!(matches!(decode_error(&hex!("f818"), EncodingProfile::Preferred), CBORError::InvalidSimpleValue));
+
+    // Keys that are distinct under the other profiles but the same under
+    // dCBOR are kept apart, and round-trip. As dCBOR, the map has duplicate
+    // keys, which its decoder rejects.
+    for data in [&hex!("a2 01 00 f93c00 00")[..], &hex!("a2 f97e00 00 f97e01 00"), &hex!("a2 8101 01 81f93c00 00")] {
+        for profile in [EncodingProfile::CoreDeterministic, EncodingProfile::Preferred] {
+            let cbor = decode(data, profile).unwrap();
+            assert_eq!(cbor.to_cbor_data_with_profile(profile), data, "{} {:?}", hex::encode(data), profile);
+            let map = cbor.try_into_map().unwrap();
+            assert_eq!(map.len(), 2);
+            assert!(matches!(decode_error(&CBOR::from(map).to_cbor_data(), EncodingProfile::Dcbor), CBORError::DuplicateMapKey | CBORError::NonCanonicalNumeric));
+        }
+    }
+    assert!(matches!(decode_error(&hex!("a2 01 00 01 00"), EncodingProfile::CoreDeterministic), CBORError::DuplicateMapKey));
+    assert!(matches!(decode_error(&hex!("a2 f93c00 00 f93c00 00"), EncodingProfile::Preferred), CBORError::DuplicateMapKey));
+    let mut map = decode(&hex!("a2 01 00 f93c00 00"), EncodingProfile::CoreDeterministic).unwrap().try_into_map().unwrap();
+    map.insert(1, 2);
+    assert_eq!(CBOR::from(map).to_cbor_data_with_profile(EncodingProfile::CoreDeterministic), hex!("a2 01 02 f93c00 00"));
+
+    // Unsorted keys are sorted when decoded under preferred serialization.
+    let cbor = decode(&hex!("a2 0200 0100"), EncodingProfile::Preferred).unwrap();
+    assert_eq!(cbor.to_cbor_data_with_profile(EncodingProfile::Preferred), hex!("a2 0100 0200"));
+    assert_eq!(cbor.diagnostic(), "{1: 0, 2: 0}");
+}
+
+#[test]
+fn profile_simple_values() {
+    // Every simple value that dCBOR allows round-trips through it.
+    let floats = [0.0, -0.0, 1.0, 1.5, -2.5, 65504.5, 1e-310, f64::MAX, f64::INFINITY, f64::NEG_INFINITY, f64::NAN];
+    let simples = [Simple::False, Simple::True, Simple::Null].into_iter().chain(floats.map(Simple::Float));
+    for simple in simples {
+        let cbor = CBOR::from(simple.clone());
+        assert_eq!(CBOR::try_from_data(cbor.to_cbor_data()).unwrap(), cbor, "{}", simple);
+    }
+
+    // Any other simple value that can be created round-trips through the
+    // other profiles.
+    for value in 0..=u8::MAX {
+        let Ok(simple_value) = SimpleValue::new(value) else {
+            assert!(matches!(value, 20..=22 | 24..=31));
+            continue;
+        };
+        let cbor = CBOR::from(Simple::Value(simple_value));
+        for profile in [EncodingProfile::CoreDeterministic, EncodingProfile::Preferred] {
+            let decoded = decode(&cbor.to_cbor_data_with_profile(profile), profile).unwrap();
+            assert!(matches!(decoded.try_into_simple_value(), Ok(Simple::Value(v)) if v == simple_value));
+        }
+        assert!(matches!(decode_error(&cbor.to_cbor_data_with_profile(EncodingProfile::CoreDeterministic), EncodingProfile::Dcbor), CBORError::InvalidSimpleValue));
+    }
+
+    // They can be map keys and values.
+    for profile in [EncodingProfile::CoreDeterministic, EncodingProfile::Preferred] {
+        let cbor = decode(&hex!("a2 81f7 f7 f7 00"), profile).unwrap();
+        assert_eq!(cbor.diagnostic_flat(), "{[undefined]: undefined, undefined: 0}");
+        assert_eq!(cbor.to_cbor_data_with_profile(profile), hex!("a2 81f7 f7 f7 00"));
+    }
+
+    // Nor can they be written as dCBOR.
+    #[cfg(feature = "std")]
+    {
+        let undefined = CBOR::from(vec![CBOR::from(Simple::Value(SimpleValue::UNDEFINED))]);
+        let mut writer = CBORWriter::new(Vec::new());
+        let error = writer.write_cbor(&undefined).unwrap_err();
+        assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::InvalidSimpleValue)));
+        assert_eq!(writer.position(), 0);
+    }
+}
+
+#[test]
+fn profile_simple_value_to_dcbor() {
+    // Values decoded under the other profiles can be encoded under any
+    // profile. dCBOR writes a simple value as the others do, and its decoder
+    // rejects the result.
+    for data in [&hex!("f7")[..], &hex!("81f7"), &hex!("a1 00 81f7"), &hex!("d818 f8ff")] {
+        for decode_profile in [EncodingProfile::CoreDeterministic, EncodingProfile::Preferred] {
+            let cbor = decode(data, decode_profile).unwrap();
+            for profile in PROFILES {
+                assert_eq!(cbor.to_cbor_data_with_profile(profile), data, "{}", hex::encode(data));
+            }
+            assert_eq!(cbor.hex(), hex::encode(data));
+            assert_eq!(cbor.encoded_len(), data.len());
+            assert_eq!(cbor.clone().memoized().to_cbor_data(), data);
+            assert!(matches!(decode_error(&cbor.to_cbor_data(), EncodingProfile::Dcbor), CBORError::InvalidSimpleValue));
+
+            let mut map = Map::new();
+            map.insert(cbor.clone(), 1);
+            assert_eq!(map.get::<_, i32>(cbor.clone()), Some(1));
+            let map = CBOR::from(map);
+            assert_eq!(map.to_cbor_data(), [&[0xa1][..], data, &[0x01]].concat());
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn profile_reader() {
+    let data = hex!("f93c00 a2 6162 f7 6161 f6");
+    let mut reader = CBORReader::with_options(&data[..], options(EncodingProfile::Preferred));
+    assert_eq!(reader.read_cbor().unwrap(), Some(CBOR::from(Simple::Float(1.0))));
+    let map = reader.read_cbor().unwrap().unwrap();
+    assert_eq!(map.diagnostic_flat(), r#"{"a": null, "b": undefined}"#);
+    assert_eq!(reader.read_cbor().unwrap(), None);
+
+    let mut reader = CBORReader::with_options(&data[..], options(EncodingProfile::CoreDeterministic));
+    reader.read_cbor().unwrap();
+    let error = reader.read_cbor().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+}
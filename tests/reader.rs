@@ -0,0 +1,117 @@
+#![cfg(feature = "std")]
+
+use std::io::{self, Read};
+
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+/// A reader that returns at most one byte per call.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+/// A reader that fails after its data is exhausted.
+struct Failing<'a>(&'a [u8]);
+
+impl Read for Failing<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"));
+        }
+        self.0.read(buf)
+    }
+}
+
+fn read_error(data: &[u8]) -> anyhow::Error {
+    CBORReader::new(data).read_cbor().unwrap_err()
+}
+
+#[test]
+fn reader_reads_items() {
+    // 1, [2, 3], {"a": h'0102', 1: -1.5}, "héllo"
+    let data = hex!("01 820203 a2 01 f9be00 6161 420102 66 68c3a96c6c6f");
+    let expected = [
+        CBOR::from(1),
+        CBOR::from([2, 3]),
+        CBOR::try_from_hex("a201f9be006161420102").unwrap(),
+        CBOR::from("héllo"),
+    ];
+    let items: Vec<CBOR> = CBORReader::new(&data[..]).map(Result::unwrap).collect();
+    assert_eq!(items, expected);
+
+    let mut reader = CBORReader::new(Trickle(&data));
+    for item in &expected {
+        assert_eq!(reader.read_cbor().unwrap().as_ref(), Some(item));
+    }
+    assert_eq!(reader.position(), data.len());
+    assert!(reader.read_cbor().unwrap().is_none());
+}
+
+#[test]
+fn reader_enforces_dcbor_rules() {
+    type IsExpected = fn(&CBORError) -> bool;
+    let cases: [(&[u8], IsExpected); 5] = [
+        (&hex!("1817"), |e| matches!(e, CBORError::NonCanonicalNumeric)),
+        (&hex!("fa3fc00000"), |e| matches!(e, CBORError::NonCanonicalNumeric)),
+        (&hex!("a2020001 00"), |e| matches!(e, CBORError::MisorderedMapKey)),
+        (&hex!("a2010001 00"), |e| matches!(e, CBORError::DuplicateMapKey)),
+        (&hex!("6365cc81"), |e| matches!(e, CBORError::NonCanonicalString)),
+    ];
+    for (data, is_expected) in cases {
+        let error = read_error(data);
+        assert!(is_expected(error.downcast_ref::<CBORError>().unwrap()), "{}", error);
+    }
+}
+
+#[test]
+fn reader_reports_eof() {
+    // An empty reader has no items.
+    assert!(CBORReader::new(&[][..]).read_cbor().unwrap().is_none());
+
+    // Ending partway through a header, a string, or a container is an
+    // underrun, located at the item that was cut short.
+    for (data, offset) in [(&hex!("8219")[..], 1), (&hex!("6361"), 0), (&hex!("a20102"), 3)] {
+        let error = read_error(data);
+        assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::Underrun)));
+        assert_eq!(error.downcast_ref::<DecodeError>().unwrap().offset(), offset);
+    }
+
+    // A huge declared length is read incrementally, so it fails at the end of
+    // the data rather than attempting to allocate.
+    let error = read_error(&hex!("5bffffffffffffffff00"));
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::Underrun)));
+}
+
+#[test]
+fn reader_passes_through_io_errors() {
+    let mut reader = CBORReader::new(Failing(&hex!("820102")));
+    assert_eq!(reader.read_cbor().unwrap(), Some(CBOR::from([1, 2])));
+    let error = reader.read_cbor().unwrap_err();
+    assert_eq!(error.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::ConnectionReset);
+
+    let mut reader = CBORReader::new(Failing(&hex!("8201")));
+    let error = reader.next().unwrap().unwrap_err();
+    assert_eq!(error.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::ConnectionReset);
+    // Iteration ends after an error.
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn reader_applies_limits_per_item() {
+    let options = DecodeOptions { max_nodes: 3, ..Default::default() };
+    let data = hex!("820102 820304 83050607");
+    let mut reader = CBORReader::with_options(&data[..], options);
+    assert_eq!(reader.read_cbor().unwrap(), Some(CBOR::from([1, 2])));
+    assert_eq!(reader.read_cbor().unwrap(), Some(CBOR::from([3, 4])));
+    let error = reader.read_cbor().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::NodeLimitExceeded(3))));
+}
@@ -0,0 +1,89 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+#[test]
+fn sequence_round_trip() {
+    let items = vec![
+        CBOR::from(1),
+        CBOR::from("hello"),
+        CBOR::from([1, 2, 3]),
+        CBOR::to_tagged_value(1, 2.5),
+        CBOR::null(),
+    ];
+    let data = dcbor::encode_sequence(&items);
+    assert_eq!(data, hex!("01 6568656c6c6f 83010203 c1f94100 f6"));
+
+    let decoded: Vec<CBOR> = dcbor::decode_sequence(&data).collect::<anyhow::Result<_>>().unwrap();
+    assert_eq!(decoded, items);
+
+    let mut buf = Vec::new();
+    for item in &items {
+        item.append_cbor_data(&mut buf);
+    }
+    assert_eq!(buf, data);
+
+    #[cfg(feature = "std")]
+    {
+        let mut written = Vec::new();
+        for item in &items {
+            item.write_cbor_data(&mut written).unwrap();
+        }
+        assert_eq!(written, data);
+    }
+
+    let mut rest = &data[..];
+    let mut prefixed = Vec::new();
+    while !rest.is_empty() {
+        let (item, remaining) = CBOR::try_from_data_prefix(rest).unwrap();
+        prefixed.push(item);
+        rest = remaining;
+    }
+    assert_eq!(prefixed, items);
+}
+
+#[test]
+fn sequence_empty() {
+    assert!(dcbor::decode_sequence(&[]).next().is_none());
+    assert!(dcbor::encode_sequence(Vec::<CBOR>::new()).is_empty());
+    let error = CBOR::try_from_data_prefix(&[]).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::Underrun)));
+}
+
+#[test]
+fn sequence_errors() {
+    // 1, [2, {2: 0, 1: 0}]
+    let data = hex!("01 8202 a2 0200 0100");
+    let mut items = dcbor::decode_sequence(&data);
+    assert_eq!(items.next().unwrap().unwrap(), CBOR::from(1));
+    assert_eq!(items.position(), 1);
+    let error = items.next().unwrap().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+    let error = error.downcast_ref::<DecodeError>().unwrap();
+    assert_eq!(error.offset(), 6);
+    assert_eq!(error.path().to_string(), "[1].key(1)");
+    assert!(items.next().is_none());
+    assert_eq!(items.remaining(), &data[1..]);
+
+    // A truncated final item.
+    let data = hex!("01 8202");
+    let results: Vec<_> = dcbor::decode_sequence(&data).collect();
+    assert_eq!(results.len(), 2);
+    let error = results[1].as_ref().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::Underrun)));
+}
+
+#[test]
+fn sequence_limits_apply_per_item() {
+    let options = DecodeOptions { max_nodes: 3, ..Default::default() };
+    // [1, 2], [3, 4], [5, 6, 7]
+    let data = hex!("820102 820304 83050607");
+    let mut items = CBORSequence::with_options(&data, options.clone());
+    assert!(items.next().unwrap().is_ok());
+    assert!(items.next().unwrap().is_ok());
+    let error = items.next().unwrap().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::NodeLimitExceeded(3))));
+
+    let (item, rest) = CBOR::try_from_data_prefix_with_options(&data, &options).unwrap();
+    assert_eq!(item, CBOR::from([1, 2]));
+    assert_eq!(rest, hex!("820304 83050607"));
+}
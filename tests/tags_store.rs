@@ -0,0 +1,103 @@
+use anyhow::Result;
+use dcbor::{prelude::*, Date};
+
+/// A point in the plane, tagged with 40000 or, in older data, 40001.
+#[derive(Debug, Clone, PartialEq)]
+struct Point(i32, i32);
+
+impl CBORTagged for Point {
+    fn cbor_tags() -> Vec<Tag> {
+        vec![Tag::new(40000, "point"), Tag::new(40001, "point-v0")]
+    }
+}
+
+impl CBORTaggedEncodable for Point {
+    fn untagged_cbor(&self) -> CBOR {
+        CBOR::from([self.0, self.1])
+    }
+}
+
+impl From<Point> for CBOR {
+    fn from(value: Point) -> Self {
+        value.tagged_cbor()
+    }
+}
+
+impl CBORTaggedDecodable for Point {
+    fn from_untagged_cbor(cbor: CBOR) -> Result<Self> {
+        match cbor.into_case() {
+            CBORCase::Array(items) if items.len() == 2 => Ok(Point(items[0].clone().try_into()?, items[1].clone().try_into()?)),
+            _ => Err(CBORError::WrongType.into()),
+        }
+    }
+}
+
+impl TryFrom<CBOR> for Point {
+    type Error = anyhow::Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        Self::from_tagged_cbor(cbor)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Payload {
+    Date(Date),
+    Point(Point),
+}
+
+#[test]
+fn decode_any_dispatches_on_tag() {
+    let mut tags = TagsStore::default();
+    tags.register_decoder::<Date>();
+    tags.register_decoder::<Point>();
+
+    let items = [
+        CBOR::from(Date::from_ymd(2024, 1, 1)),
+        CBOR::from(Point(1, -2)),
+        CBOR::to_tagged_value(40001, [3, 4]),
+    ];
+    let values: Vec<Box<dyn std::any::Any>> = items.into_iter().map(|item| tags.decode_any(item).unwrap()).collect();
+    assert_eq!(values[0].downcast_ref::<Date>(), Some(&Date::from_ymd(2024, 1, 1)));
+    assert_eq!(values[1].downcast_ref::<Point>(), Some(&Point(1, -2)));
+    assert_eq!(values[2].downcast_ref::<Point>(), Some(&Point(3, 4)));
+}
+
+#[test]
+fn decode_as_user_enum() {
+    let mut tags = TagsStore::default();
+    tags.register_decoder_with(Payload::Date);
+    tags.register_decoder_with(Payload::Point);
+
+    assert_eq!(tags.decode_as::<Payload>(Point(5, 6).into()).unwrap(), Payload::Point(Point(5, 6)));
+    assert_eq!(tags.decode_as::<Payload>(Date::from_ymd(2000, 2, 3).into()).unwrap(), Payload::Date(Date::from_ymd(2000, 2, 3)));
+
+    // A decoder producing some other type.
+    tags.register_decoder::<Point>();
+    let error = tags.decode_as::<Payload>(Point(5, 6).into()).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::WrongType)));
+}
+
+#[test]
+fn decode_any_errors() {
+    let mut tags = TagsStore::default();
+    tags.register_decoder::<Point>();
+
+    let error = tags.decode_any(CBOR::from([1, 2])).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::WrongType)));
+
+    let error = tags.decode_any(CBOR::to_tagged_value(99, 1)).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::UnregisteredTag(tag)) if tag.value() == 99));
+
+    // Errors from the decoder itself are passed through.
+    let error = tags.decode_any(CBOR::to_tagged_value(40000, "nope")).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::WrongType)));
+}
+
+#[test]
+fn global_tags_decode_dates() {
+    dcbor::register_tags();
+    let cbor = CBOR::from(Date::from_ymd(2023, 5, 6));
+    let date = with_tags!(|tags: &TagsStore| tags.decode_as::<Date>(cbor)).unwrap();
+    assert_eq!(date, Date::from_ymd(2023, 5, 6));
+}
@@ -0,0 +1,143 @@
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+#[test]
+fn validate_summary() {
+    // [1, -2, h'0102', "hello", {1: "a", "b": [true]}, 1(2.5), null]
+    let data = hex!("87 01 21 420102 6568656c6c6f a2 01 6161 6162 81f5 c1 f94100 f6");
+    assert_eq!(dcbor::validate(&data).unwrap(), ValidationSummary {
+        items: 14,
+        arrays: 2,
+        maps: 1,
+        tags: 1,
+        string_bytes: 9,
+    });
+}
+
+#[test]
+fn validate_agrees_with_decoder() {
+    let cases = [
+        // Valid
+        "00",
+        "a0",
+        "a2 01 a2 01 00 02 00 02 00",
+        "c1 c6 82 a1 6161 00 a1 6162 00",
+        "c2 49 010000000000000000",
+        "fb3ff199999999999a",
+        // Non-minimal integers and lengths
+        "1817",
+        "1900ff",
+        "9800",
+        "f814",
+        // Non-canonical floats
+        "f93c00",
+        "fa3fc00000",
+        "f97e01",
+        // Invalid simple values and headers
+        "f7",
+        "1c",
+        "9f00ff",
+        // Invalid or non-NFC text
+        "62c328",
+        "6365cc81",
+        // Misordered and duplicate keys, including in nested maps
+        "a2 02 00 01 00",
+        "a2 01 00 01 00",
+        "a2 6162 00 6161 00",
+        "a2 01 00 18 18 00",
+        "81 a1 01 a2 02 00 01 00",
+        "a2 a1 01 00 00 01 00",
+        "a2 81 a1 01 00 00 81 00 00",
+        "a2 01 a2 01 00 01 00 02 00",
+        "82 a2 01 00 02 81 a1 01 00 a2 01 00 01 00",
+        // Non-canonical and invalid bignums
+        "c2 48 0100000000000000",
+        "c3 49 000100000000000000",
+        "c2 40",
+        "81 c3 01",
+        // Truncated and extra data
+        "",
+        "83 01 02",
+        "a1 01",
+        "9bffffffffffffffff",
+        "bbffffffffffffffff",
+        "5bffffffffffffffff 00",
+        "c1",
+        "00 00",
+    ];
+    for case in cases {
+        let data = hex::decode(case.replace(' ', "")).unwrap();
+        let decoded = CBOR::try_from_data(&data);
+        let validated = dcbor::validate(&data);
+        match (decoded, validated) {
+            (Ok(_), Ok(_)) => {},
+            (Err(decode_error), Err(validate_error)) => {
+                assert_eq!(validate_error.to_string(), decode_error.to_string(), "{}", case);
+            },
+            (decoded, validated) => panic!("{}: decoded {:?}, validated {:?}", case, decoded, validated),
+        }
+    }
+}
+
+#[test]
+fn validate_deeply_nested() {
+    const DEPTH: usize = 1_000;
+    let mut data = Vec::new();
+    for _ in 0..DEPTH {
+        data.extend(hex!("a101c1"));
+    }
+    data.push(0x00);
+    let summary = dcbor::validate(&data).unwrap();
+    assert_eq!(summary.maps, DEPTH);
+    assert_eq!(summary.items, DEPTH * 3 + 1);
+}
+
+#[test]
+fn validate_accepts_what_decoder_accepts_at_any_depth() {
+    fn check(data: &[u8]) {
+        let decoded = CBOR::try_from_data(data);
+        let validated = dcbor::validate(data);
+        match (&decoded, &validated) {
+            (Ok(_), Ok(_)) => {},
+            (Err(decode_error), Err(validate_error)) => assert_eq!(validate_error.to_string(), decode_error.to_string()),
+            _ => panic!("decoded {:?}, validated {:?}", decoded.map(|_| ()), validated),
+        }
+        assert_eq!(CBOR::from_validated_data(data).is_ok(), validated.is_ok());
+        assert_eq!(dcbor::EncodedCBOR::try_from_data(data).is_ok(), validated.is_ok());
+    }
+
+    // Maps of one entry, as in `{1: {1: ... 0}}`.
+    let mut data = hex!("a101").repeat(40_000);
+    data.push(0x00);
+    check(&data);
+    assert_eq!(dcbor::validate(&data).unwrap().maps, 40_000);
+
+    // Maps of several entries, as in `{0: 0, 1: {0: 0, 1: ... 0}}`, whose
+    // keys are compared both on the stack and by scanning them again.
+    for depth in [15, 16, 17, 1_100] {
+        let mut data = hex!("a2 0000 01").repeat(depth);
+        data.push(0x00);
+        check(&data);
+        assert_eq!(dcbor::validate(&data).unwrap().maps, depth);
+
+        // A misordered or duplicate key at the innermost level.
+        for (inner, error) in [("a2 01 00 00 00", "the decoded CBOR map has keys that are not in canonical order"), ("a2 00 00 00 00", "the decoded CBOR map has a duplicate key")] {
+            let mut data = hex!("a2 0000 01").repeat(depth);
+            data.extend(hex::decode(inner.replace(' ', "")).unwrap());
+            check(&data);
+            assert_eq!(dcbor::validate(&data).unwrap_err().to_string(), error);
+        }
+
+        // A misordered key at the outermost level, after the nested maps.
+        let mut data = hex!("a2 01").to_vec();
+        data.extend(hex!("a2 0000 01").repeat(depth));
+        data.extend(hex!("00 00 00"));
+        check(&data);
+    }
+
+    // Arrays and tags.
+    let mut data = hex!("81c1").repeat(100_000);
+    data.push(0x00);
+    check(&data);
+    assert_eq!(dcbor::validate(&data).unwrap().arrays, 100_000);
+}
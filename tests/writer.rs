@@ -0,0 +1,142 @@
+#![cfg(feature = "std")]
+
+use dcbor::prelude::*;
+use hex_literal::hex;
+
+fn error_of(result: anyhow::Result<()>) -> CBORError {
+    result.unwrap_err().downcast_ref::<CBORError>().unwrap().clone()
+}
+
+#[test]
+fn writer_matches_to_cbor_data() {
+    const ROWS: usize = 1000;
+    let mut writer = CBORWriter::new(Vec::new());
+    let mut map = Map::new();
+    writer.begin_map(ROWS).unwrap();
+    for i in 0..ROWS {
+        let row = vec![CBOR::from(i as i64 - 500), CBOR::from(i as f64 / 4.0), CBOR::from(format!("row {}", i))];
+        map.insert(i, row.clone());
+        writer.write_u64(i as u64).unwrap();
+        writer.begin_array(3).unwrap();
+        writer.write_i64(i as i64 - 500).unwrap();
+        writer.write_f64(i as f64 / 4.0).unwrap();
+        writer.write_text(&format!("row {}", i)).unwrap();
+        assert_eq!(writer.depth(), if i + 1 == ROWS { 0 } else { 1 });
+    }
+    let position = writer.position();
+    let data = writer.finish().unwrap();
+    assert_eq!(data.len(), position);
+    assert_eq!(data, CBOR::from(map).to_cbor_data());
+}
+
+#[test]
+fn writer_scalars_and_sequences() {
+    let mut writer = CBORWriter::new(Vec::new());
+    writer.begin_array(0).unwrap();
+    writer.begin_map(0).unwrap();
+    writer.tag(1).unwrap();
+    writer.tag(2).unwrap();
+    // A bignum must be a byte string too large to be a plain integer.
+    let position = writer.position();
+    assert!(matches!(error_of(writer.write_bytes([1, 2])), CBORError::NonCanonicalNumeric));
+    assert!(matches!(error_of(writer.write_bytes([0, 1, 2, 3, 4, 5, 6, 7, 8])), CBORError::NonCanonicalNumeric));
+    assert!(matches!(error_of(writer.write_u64(1)), CBORError::WrongType));
+    assert!(matches!(error_of(writer.tag(2)), CBORError::WrongType));
+    assert!(matches!(error_of(writer.write_cbor(&CBOR::from(1))), CBORError::WrongType));
+    assert_eq!(writer.position(), position);
+    writer.write_bytes([1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+    writer.begin_array(6).unwrap();
+    writer.write_bool(true).unwrap();
+    writer.write_bool(false).unwrap();
+    writer.write_null().unwrap();
+    writer.write_f64(f64::NAN).unwrap();
+    writer.write_f64(-3.0).unwrap();
+    writer.write_text("A\u{30a}").unwrap();
+    writer.write_cbor(&CBOR::from([1, 2])).unwrap();
+    let data = writer.finish().unwrap();
+    assert_eq!(data, hex!("80 a0 c1c249010203040506070809 86 f5 f4 f6 f97e00 22 62c385 820102"));
+    let items: Vec<CBOR> = dcbor::decode_sequence(&data).collect::<anyhow::Result<_>>().unwrap();
+    assert_eq!(items.len(), 5);
+}
+
+#[test]
+fn writer_checks_items_written_whole() {
+    // Bignums are checked wherever they are within the item.
+    let short = CBOR::to_tagged_value(2, CBOR::to_byte_string([1]));
+    let padded = CBOR::to_tagged_value(3, CBOR::to_byte_string([0, 1, 2, 3, 4, 5, 6, 7, 8]));
+    let not_bytes = CBOR::to_tagged_value(2, CBOR::from("a"));
+    let mut writer = CBORWriter::new(Vec::new());
+    assert!(matches!(error_of(writer.write_cbor(&short)), CBORError::NonCanonicalNumeric));
+    assert!(matches!(error_of(writer.write_cbor(&CBOR::from(vec![CBOR::from(1), padded.memoized()]))), CBORError::NonCanonicalNumeric));
+    assert!(matches!(error_of(writer.write_cbor(&CBOR::to_tagged_value(1, not_bytes))), CBORError::WrongType));
+    let mut map = Map::new();
+    map.insert(short, 1);
+    assert!(matches!(error_of(writer.write_cbor(&CBOR::from(map))), CBORError::NonCanonicalNumeric));
+
+    // So are simple values and, in maps decoded under other profiles, keys
+    // that are the same under dCBOR.
+    let options = DecodeOptions { profile: EncodingProfile::CoreDeterministic, ..Default::default() };
+    let cbor = CBOR::try_from_data_with_options(hex!("82 00 f7"), &options).unwrap();
+    assert!(matches!(error_of(writer.write_cbor(&cbor)), CBORError::InvalidSimpleValue));
+    let cbor = CBOR::try_from_data_with_options(hex!("a1 00 a2 01 00 f93c00 00"), &options).unwrap();
+    assert!(matches!(error_of(writer.write_cbor(&cbor)), CBORError::DuplicateMapKey));
+    assert_eq!(writer.position(), 0);
+
+    let bignum = CBOR::to_tagged_value(2, CBOR::to_byte_string([1, 2, 3, 4, 5, 6, 7, 8, 9]));
+    writer.write_cbor(&CBOR::from(vec![bignum.clone()])).unwrap();
+    assert_eq!(CBOR::try_from_data(writer.finish().unwrap()).unwrap(), CBOR::from(vec![bignum]));
+}
+
+#[test]
+fn writer_rejects_misordered_keys() {
+    let mut writer = CBORWriter::new(Vec::new());
+    writer.begin_map(3).unwrap();
+    writer.begin_array(2).unwrap();
+    writer.write_u64(1).unwrap();
+    writer.write_u64(2).unwrap();
+    writer.write_null().unwrap();
+    assert!(matches!(error_of(writer.write_u64(10)), CBORError::MisorderedMapKey));
+    assert!(matches!(error_of(writer.write_cbor(&CBOR::from([1, 2]))), CBORError::DuplicateMapKey));
+    // A composite key is compared once it is complete, and is not written
+    // if it is rejected.
+    let position = writer.position();
+    writer.begin_array(2).unwrap();
+    writer.write_u64(1).unwrap();
+    assert!(matches!(error_of(writer.write_u64(1)), CBORError::MisorderedMapKey));
+    assert_eq!(writer.position(), position);
+    // Keys of maps within keys are checked too.
+    writer.begin_map(2).unwrap();
+    writer.write_text("b").unwrap();
+    writer.write_null().unwrap();
+    assert!(matches!(error_of(writer.write_text("a")), CBORError::MisorderedMapKey));
+    writer.write_text("c").unwrap();
+    writer.write_null().unwrap();
+    assert_eq!(writer.position(), position + 7);
+    writer.write_u64(1).unwrap();
+    writer.tag(1).unwrap();
+    writer.write_u64(0).unwrap();
+    writer.write_u64(2).unwrap();
+    let data = writer.finish().unwrap();
+    let cbor = CBOR::try_from_data(&data).unwrap();
+    assert_eq!(cbor.diagnostic_flat(), r#"{[1, 2]: null, {"b": null, "c": null}: 1, 1(0): 2}"#);
+}
+
+#[test]
+fn writer_checks_lengths() {
+    let mut writer = CBORWriter::new(Vec::new());
+    writer.begin_array(2).unwrap();
+    writer.tag(1).unwrap();
+    assert!(matches!(writer.finish().unwrap_err().downcast_ref::<CBORError>(), Some(CBORError::IncompleteContainer(2))));
+
+    let mut writer = CBORWriter::new(Vec::new());
+    writer.begin_map(1).unwrap();
+    writer.write_u64(1).unwrap();
+    assert!(matches!(writer.finish().unwrap_err().downcast_ref::<CBORError>(), Some(CBORError::IncompleteContainer(1))));
+
+    // Items past the declared length follow the container.
+    let mut writer = CBORWriter::new(Vec::new());
+    writer.begin_array(1).unwrap();
+    writer.write_u64(1).unwrap();
+    writer.write_u64(2).unwrap();
+    assert_eq!(writer.finish().unwrap(), hex!("8101 02"));
+}